@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS user_security_logs_user_id_created_at_idx;
+
+DELETE FROM user_security_logs WHERE user_id IS NULL;
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT IF EXISTS user_security_logs_event_type_check,
+    DROP COLUMN event_type,
+    DROP COLUMN user_agent,
+    DROP COLUMN path,
+    DROP COLUMN method,
+    DROP COLUMN status,
+    ALTER COLUMN user_id SET NOT NULL;
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+ALTER TABLE user_security_logs
+    ALTER COLUMN user_id DROP NOT NULL,
+    ADD COLUMN event_type VARCHAR(32) NOT NULL DEFAULT 'login_failure',
+    ADD COLUMN user_agent VARCHAR(512),
+    ADD COLUMN path VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN method VARCHAR(10) NOT NULL DEFAULT '',
+    ADD COLUMN status INT NOT NULL DEFAULT 0;
+
+-- Defaults only backfill the existing rows, which were all login failures.
+ALTER TABLE user_security_logs
+    ALTER COLUMN event_type DROP DEFAULT,
+    ALTER COLUMN path DROP DEFAULT,
+    ALTER COLUMN method DROP DEFAULT,
+    ALTER COLUMN status DROP DEFAULT,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected'
+    ));
+
+CREATE INDEX IF NOT EXISTS user_security_logs_user_id_created_at_idx
+    ON user_security_logs (user_id, created_at DESC);
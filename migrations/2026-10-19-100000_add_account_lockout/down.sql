@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS user_security_logs_ip_address_created_at_idx;
+
+DELETE FROM user_security_logs
+WHERE event_type IN ('account_locked', 'account_unlocked');
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT user_security_logs_event_type_check,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected'
+    ));
+
+DROP INDEX IF EXISTS users_locked_until_idx;
+
+ALTER TABLE users DROP COLUMN locked_until;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN locked_until TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_locked_until_idx
+    ON users (locked_until)
+    WHERE locked_until IS NOT NULL;
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT user_security_logs_event_type_check,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected',
+        'account_locked',
+        'account_unlocked'
+    ));
+
+CREATE INDEX IF NOT EXISTS user_security_logs_ip_address_created_at_idx
+    ON user_security_logs (ip_address, created_at DESC);
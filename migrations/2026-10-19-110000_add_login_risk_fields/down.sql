@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_security_logs
+    DROP COLUMN latitude,
+    DROP COLUMN longitude,
+    DROP COLUMN asn,
+    DROP COLUMN flag_reason;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE user_security_logs
+    ADD COLUMN latitude DOUBLE PRECISION,
+    ADD COLUMN longitude DOUBLE PRECISION,
+    ADD COLUMN asn BIGINT,
+    ADD COLUMN flag_reason VARCHAR(64);
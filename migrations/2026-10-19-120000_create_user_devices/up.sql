@@ -0,0 +1,37 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE IF NOT EXISTS user_devices (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id VARCHAR(50) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_id VARCHAR(128) NOT NULL,
+    user_agent VARCHAR(512),
+    ip_address VARCHAR(50) NOT NULL,
+    city VARCHAR(50) NOT NULL DEFAULT 'unknown',
+    country VARCHAR(50) NOT NULL DEFAULT 'unknown',
+    trusted BOOLEAN NOT NULL DEFAULT FALSE,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+    UNIQUE (user_id, device_id)
+);
+
+ALTER TABLE user_jwt_tokens
+    ADD COLUMN device_id UUID REFERENCES user_devices(id) ON DELETE SET NULL;
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT user_security_logs_event_type_check,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected',
+        'account_locked',
+        'account_unlocked',
+        'new_device',
+        'device_revoked',
+        'account_created'
+    ));
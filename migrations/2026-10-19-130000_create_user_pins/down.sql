@@ -0,0 +1,29 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM user_security_logs
+WHERE event_type IN (
+    'pin_set',
+    'pin_changed',
+    'pin_reset',
+    'pin_verified',
+    'pin_failed',
+    'pin_locked'
+);
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT user_security_logs_event_type_check,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected',
+        'account_locked',
+        'account_unlocked',
+        'new_device',
+        'device_revoked',
+        'account_created'
+    ));
+
+DROP TABLE IF EXISTS user_pins;
@@ -0,0 +1,48 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id VARCHAR(50) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret_ciphertext BYTEA NOT NULL,
+    secret_nonce BYTEA NOT NULL,
+    -- Unset until the first code is confirmed.
+    enabled_at TIMESTAMPTZ,
+    -- The last accepted time step, so a code cannot be replayed.
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id VARCHAR(50) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, code_hash)
+);
+
+ALTER TABLE user_security_logs
+    DROP CONSTRAINT user_security_logs_event_type_check,
+    ADD CONSTRAINT user_security_logs_event_type_check CHECK (event_type IN (
+        'login_success',
+        'login_failure',
+        'otp_sent',
+        'otp_failed',
+        'bank_added',
+        'token_refreshed',
+        'api_key_rejected',
+        'account_locked',
+        'account_unlocked',
+        'new_device',
+        'device_revoked',
+        'account_created',
+        'pin_set',
+        'pin_changed',
+        'pin_reset',
+        'pin_verified',
+        'pin_failed',
+        'pin_locked',
+        'totp_enabled',
+        'totp_disabled',
+        'totp_failed',
+        'recovery_code_used',
+        'recovery_codes_regenerated'
+    ));
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM otp;
+
+ALTER TABLE otp
+    DROP CONSTRAINT otp_user_id_purpose_key,
+    DROP COLUMN attempts,
+    DROP COLUMN purpose,
+    DROP COLUMN code_hash,
+    ADD COLUMN otp_code INT NOT NULL DEFAULT 0 CHECK (
+        otp_code BETWEEN 100000
+        AND 999999
+    );
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+-- Outstanding codes live for minutes and cannot be converted to hashes, so
+-- they are dropped; users request a new one.
+DELETE FROM otp;
+
+ALTER TABLE otp
+    DROP COLUMN otp_code,
+    ADD COLUMN code_hash VARCHAR(64) NOT NULL,
+    ADD COLUMN purpose VARCHAR(20) NOT NULL CHECK (
+        purpose IN (
+            'login',
+            'phone_change',
+            'pin_reset',
+            'bank_add',
+            'contact_change',
+            'email_confirm'
+        )
+    ),
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD CONSTRAINT otp_user_id_purpose_key UNIQUE (user_id, purpose);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users
+    DROP COLUMN notification_channel,
+    DROP COLUMN email;
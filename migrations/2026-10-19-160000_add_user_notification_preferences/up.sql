@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN email VARCHAR(255),
+    ADD COLUMN notification_channel VARCHAR(10) NOT NULL DEFAULT 'sms' CHECK (
+        notification_channel IN ('sms', 'whatsapp', 'email')
+    );
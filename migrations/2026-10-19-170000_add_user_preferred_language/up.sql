@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN preferred_language VARCHAR(5) NOT NULL DEFAULT 'en' CHECK (
+        preferred_language IN ('en', 'pcm', 'yo', 'ha', 'ig')
+    );
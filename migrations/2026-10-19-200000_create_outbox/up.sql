@@ -0,0 +1,36 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS outbox_events (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    event_type VARCHAR(50) NOT NULL CHECK (
+        event_type IN ('user.created', 'user.verified', 'bank_account.added', 'wallet.linked')
+    ),
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Set once a delivery has been queued for each subscriber.
+    fanned_out_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_outbox_events_pending
+    ON outbox_events (created_at)
+    WHERE fanned_out_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS outbox_deliveries (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    event_id UUID NOT NULL REFERENCES outbox_events(id) ON DELETE CASCADE,
+    subscriber VARCHAR(100) NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+    dead_at TIMESTAMPTZ,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (event_id, subscriber)
+);
+
+CREATE INDEX IF NOT EXISTS idx_outbox_deliveries_due
+    ON outbox_deliveries (next_attempt_at)
+    WHERE delivered_at IS NULL AND dead_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_outbox_deliveries_dead
+    ON outbox_deliveries (dead_at)
+    WHERE dead_at IS NOT NULL;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_wallet DROP CONSTRAINT IF EXISTS user_wallet_network_check;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+UPDATE user_wallet
+SET network_used_last = lower(trim(network_used_last))
+WHERE network_used_last IS NOT NULL;
+
+-- Starknet addresses to 0x and 64 lowercase hex digits, unless that would
+-- collide with a row already in that form. EVM checksums need Keccak, so
+-- those rows are normalized when they are next written.
+UPDATE user_wallet w
+SET wallet_address = '0x' || lpad(lower(substr(w.wallet_address, 3)), 64, '0')
+WHERE w.network_used_last = 'starknet'
+  AND w.wallet_address ~ '^0[xX][0-9a-fA-F]{1,64}$'
+  AND NOT EXISTS (
+      SELECT 1 FROM user_wallet other
+      WHERE other.id <> w.id
+        AND other.wallet_address = '0x' || lpad(lower(substr(w.wallet_address, 3)), 64, '0')
+  );
+
+-- NOT VALID: rows written before networks were checked keep whatever they
+-- hold; new and updated rows must name a known network.
+ALTER TABLE user_wallet
+    ADD CONSTRAINT user_wallet_network_check
+    CHECK (network_used_last IN ('starknet', 'ethereum', 'base', 'arbitrum')) NOT VALID;
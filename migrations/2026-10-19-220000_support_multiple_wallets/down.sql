@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_user_wallet_user_id;
+DROP INDEX IF EXISTS user_wallet_primary_key;
+DROP INDEX IF EXISTS user_wallet_network_address_key;
+
+-- The old constraint allows one wallet per address; keep the active one,
+-- else the newest.
+DELETE FROM user_wallet
+WHERE id NOT IN (
+    SELECT DISTINCT ON (wallet_address) id
+    FROM user_wallet
+    ORDER BY wallet_address, archived_at DESC NULLS FIRST, created_at DESC NULLS LAST
+);
+
+ALTER TABLE user_wallet
+    DROP COLUMN IF EXISTS archived_at,
+    DROP COLUMN IF EXISTS is_primary,
+    DROP COLUMN IF EXISTS label;
+
+ALTER TABLE user_wallet ADD CONSTRAINT user_wallet_wallet_address_key UNIQUE (wallet_address);
+ALTER TABLE user_wallet RENAME COLUMN network TO network_used_last;
@@ -0,0 +1,30 @@
+-- Your SQL goes here
+ALTER TABLE user_wallet RENAME COLUMN network_used_last TO network;
+
+ALTER TABLE user_wallet
+    ADD COLUMN IF NOT EXISTS label VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+-- The same address may be linked on two networks, and again after it is
+-- archived.
+ALTER TABLE user_wallet DROP CONSTRAINT IF EXISTS user_wallet_wallet_address_key;
+CREATE UNIQUE INDEX IF NOT EXISTS user_wallet_network_address_key
+    ON user_wallet (network, wallet_address)
+    WHERE archived_at IS NULL;
+
+-- Wallets so far were each their user's only one; the newest per network
+-- becomes primary.
+UPDATE user_wallet
+SET is_primary = TRUE
+WHERE id IN (
+    SELECT DISTINCT ON (user_id, network) id
+    FROM user_wallet
+    ORDER BY user_id, network, created_at DESC NULLS LAST
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_wallet_primary_key
+    ON user_wallet (user_id, network)
+    WHERE is_primary AND archived_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_user_wallet_user_id ON user_wallet (user_id);
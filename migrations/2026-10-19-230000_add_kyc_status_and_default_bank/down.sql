@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS user_bank_account_default_key;
+ALTER TABLE user_bank_account DROP COLUMN is_default;
+ALTER TABLE users DROP COLUMN kyc_status;
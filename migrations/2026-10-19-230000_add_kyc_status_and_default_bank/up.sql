@@ -0,0 +1,21 @@
+-- Your SQL goes here
+ALTER TABLE users
+    ADD COLUMN kyc_status VARCHAR(10) NOT NULL DEFAULT 'none' CHECK (
+        kyc_status IN ('none', 'pending', 'verified', 'rejected')
+    );
+
+ALTER TABLE user_bank_account
+    ADD COLUMN is_default BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Payouts go to the first account a user added until they pick another.
+UPDATE user_bank_account
+SET is_default = TRUE
+WHERE id IN (
+    SELECT DISTINCT ON (user_id) id
+    FROM user_bank_account
+    ORDER BY user_id, created_at ASC NULLS LAST
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_bank_account_default_key
+    ON user_bank_account (user_id)
+    WHERE is_default;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_wallet
+    DROP COLUMN challenge_expires_at,
+    DROP COLUMN challenge_nonce,
+    DROP COLUMN verified_at;
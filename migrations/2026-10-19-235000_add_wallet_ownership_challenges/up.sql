@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE user_wallet
+    ADD COLUMN verified_at TIMESTAMPTZ,
+    ADD COLUMN challenge_nonce VARCHAR(66),
+    ADD COLUMN challenge_expires_at TIMESTAMPTZ;
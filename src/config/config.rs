@@ -1,33 +1,1218 @@
-#[derive(Debug, Clone)]
+use ::config::{Config as ConfigLayers, Environment, File, Map, Source};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+
+use super::cors;
+use crate::models::models::{ChannelKind, OutboxEventType};
+
+/// Typed service configuration.
+///
+/// Values are layered, lowest precedence first: built-in defaults, the TOML
+/// file named by `CONFIG_FILE` (or `./config.toml` if present), `APP__*`
+/// variables such as `APP__SERVER__PORT`, and finally the legacy variables
+/// (`PORT`, `DATABASE_URL`, `JWT_SECRET`, ...) that existing deployments set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
-    pub port: String,
-    pub _database_url: String,
-    pub jwt_secret: String,
-    pub ip_info_token: String,
-    pub flutterwave_secret_key: String,
-    pub hmac_key: String,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub providers: ProvidersConfig,
+    pub cors: CorsConfig,
+    pub geolocation: GeolocationConfig,
+    pub redis: RedisConfig,
+    pub rate_limit: RateLimitConfig,
+    pub lockout: LockoutConfig,
+    pub risk: RiskConfig,
+    pub otp: OtpConfig,
+    pub notifications: NotificationsConfig,
+    pub pin: PinConfig,
+    pub totp: TotpConfig,
+    pub wallets: WalletsConfig,
+    pub outbox: OutboxConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub environment: String,
+    /// Reverse proxies whose `X-Forwarded-For` is believed. A request from
+    /// anywhere else is attributed to its peer address, whatever headers it
+    /// sends.
+    pub trusted_proxies: Vec<std::net::IpAddr>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "0.0.0.0".to_string(),
+            port: 8001,
+            environment: "prod".to_string(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn is_dev(&self) -> bool {
+        self.environment == "dev"
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: Secret,
+    pub max_connections: u32,
+    pub connection_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: Secret::default(),
+            max_connections: 10,
+            connection_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub jwt_secret: Secret,
+    /// Shared key partner services send in `x-api-key`.
+    pub api_key: Secret,
+    /// Key for the `/api/v1/admin` routes, sent in `x-admin-key`. Admin
+    /// routes reject every request while it is unset, and also need an
+    /// admin user's TOTP code (see `TotpConfig`).
+    pub admin_api_key: Option<Secret>,
+    /// Key for the internal wallet lookup the indexer uses, sent in
+    /// `x-indexer-key`. The lookup rejects every request while it is unset.
+    pub indexer_api_key: Option<Secret>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProvidersConfig {
+    pub flutterwave: FlutterwaveConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FlutterwaveConfig {
+    pub enabled: bool,
+    pub base_url: String,
+    pub secret_key: Option<Secret>,
+    /// The secret hash set in the Flutterwave dashboard, sent back in the
+    /// `verif-hash` header of webhooks. `/webhooks/flutterwave` is off
+    /// without it.
+    pub secret_hash: Option<Secret>,
+}
+
+impl Default for FlutterwaveConfig {
+    fn default() -> Self {
+        FlutterwaveConfig {
+            enabled: true,
+            base_url: "https://api.flutterwave.com/v3".to_string(),
+            secret_key: None,
+            secret_hash: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Exact origins, `https://*.example.com` subdomain patterns, or `*`.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Header names, or `*` for any.
+    pub allowed_headers: Vec<String>,
+    pub supports_credentials: bool,
+    pub max_age_secs: Option<usize>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec![
+                "http://localhost:3000".to_string(),
+                "http://127.0.0.1:3000".to_string(),
+            ],
+            allowed_methods: ["GET", "POST", "PUT", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: ["content-type", "authorization", "accept"]
+                .map(String::from)
+                .to_vec(),
+            supports_credentials: true,
+            max_age_secs: Some(3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GeolocationConfig {
+    pub enabled: bool,
+    pub provider: GeoProviderKind,
+    pub ipinfo_token: Option<Secret>,
+    /// MaxMind-format City or Country database, e.g. GeoLite2-City.mmdb.
+    pub mmdb_city_path: Option<String>,
+    /// Optional ASN database, e.g. GeoLite2-ASN.mmdb.
+    pub mmdb_asn_path: Option<String>,
+    /// Upper bound on a single lookup, including waiting for the client.
+    pub timeout_ms: u64,
+    pub cache_ttl_secs: u64,
+    /// Most addresses kept in the in-process cache.
+    pub cache_capacity: usize,
+}
+
+impl Default for GeolocationConfig {
+    fn default() -> Self {
+        GeolocationConfig {
+            enabled: true,
+            provider: GeoProviderKind::Ipinfo,
+            ipinfo_token: None,
+            mmdb_city_path: None,
+            mmdb_asn_path: None,
+            timeout_ms: 1500,
+            cache_ttl_secs: 24 * 60 * 60,
+            cache_capacity: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeoProviderKind {
+    /// The ipinfo.io API; needs `ipinfo_token`.
+    Ipinfo,
+    /// Local MaxMind-format databases, reloaded when the files change.
+    Mmdb,
+}
+
+/// Shared cache and counter store. Without a url every replica falls back
+/// to its own in-memory store.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RedisConfig {
+    pub url: Option<Secret>,
+    pub pool_size: usize,
+    /// Namespaces our keys when the Redis instance is shared with other services.
+    pub key_prefix: String,
+    /// Most keys the in-memory fallback holds before evicting the least
+    /// recently used.
+    pub memory_capacity: usize,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        RedisConfig {
+            url: None,
+            pool_size: 4,
+            key_prefix: "user-management:".to_string(),
+            memory_capacity: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub rules: Vec<RateLimitRule>,
+}
+
+/// What a rate limit counts against.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    Ip,
+    /// The `phone` query parameter or JSON body field.
+    Phone,
+    /// The caller's `x-api-key`.
+    ApiClient,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitRule {
+    /// Exact request path, or a prefix ending in `*`.
+    pub path: String,
+    /// Methods the rule applies to; empty means all.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    pub key: RateLimitKey,
+    pub limit: u64,
+    pub window_secs: u64,
+}
+
+impl RateLimitRule {
+    fn new(path: &str, methods: &[&str], key: RateLimitKey, limit: u64, window_secs: u64) -> Self {
+        RateLimitRule {
+            path: path.to_string(),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            key,
+            limit,
+            window_secs,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            rules: vec![
+                RateLimitRule::new("/api/v1/auth/*", &["POST"], RateLimitKey::Ip, 20, 60),
+                RateLimitRule::new("/api/v1/auth/*", &["POST"], RateLimitKey::Phone, 5, 300),
+                // Every verify call is a paid Flutterwave resolve.
+                RateLimitRule::new(
+                    "/api/v1/users/me/bank-accounts/verify",
+                    &["POST"],
+                    RateLimitKey::Phone,
+                    10,
+                    3600,
+                ),
+                RateLimitRule::new(
+                    "/api/v1/users/me/bank-accounts/verify",
+                    &["POST"],
+                    RateLimitKey::Ip,
+                    30,
+                    60,
+                ),
+                // Every send is a paid SMS or WhatsApp message.
+                RateLimitRule::new(
+                    "/api/v1/users/me/otp",
+                    &["POST"],
+                    RateLimitKey::Phone,
+                    5,
+                    300,
+                ),
+                RateLimitRule::new("/api/v1/users/me/otp", &["POST"], RateLimitKey::Ip, 20, 60),
+                RateLimitRule::new(
+                    "/api/v1/users/me/otp/verify",
+                    &["POST"],
+                    RateLimitKey::Phone,
+                    10,
+                    300,
+                ),
+                RateLimitRule::new(
+                    "/api/v1/users/me/otp/verify",
+                    &["POST"],
+                    RateLimitKey::Ip,
+                    30,
+                    60,
+                ),
+                RateLimitRule::new("/api/v1/*", &[], RateLimitKey::ApiClient, 600, 60),
+            ],
+        }
+    }
+}
+
+/// Reaction to failed login and OTP attempts, counted over a sliding window.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LockoutConfig {
+    pub enabled: bool,
+    pub window_secs: u64,
+    /// Failures within the window that lock the account.
+    pub max_failures: i64,
+    /// How long a lock lasts before it is lifted automatically.
+    pub lock_secs: u64,
+    /// Failures from one IP before it is slowed down; IPs are often shared,
+    /// so this is more lenient than the per-user limit.
+    pub ip_free_failures: i64,
+    /// Wait after the first counted failure; it doubles with each further one.
+    pub backoff_base_ms: u64,
+    pub backoff_max_secs: u64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        LockoutConfig {
+            enabled: true,
+            window_secs: 15 * 60,
+            max_failures: 5,
+            lock_secs: 15 * 60,
+            ip_free_failures: 10,
+            backoff_base_ms: 1000,
+            backoff_max_secs: 5 * 60,
+        }
+    }
+}
+
+/// One-time codes sent over SMS or WhatsApp.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OtpConfig {
+    /// HMAC key codes are hashed with before they are stored. Falls back to
+    /// `auth.jwt_secret`; changing it invalidates every outstanding code.
+    pub hash_key: Option<Secret>,
+    /// How long a code stays valid.
+    pub ttl_secs: u64,
+    /// Wrong guesses after which a code is discarded.
+    pub max_attempts: i32,
+    /// Minimum time between two codes for the same user and purpose.
+    pub resend_interval_secs: u64,
+}
+
+impl Default for OtpConfig {
+    fn default() -> Self {
+        OtpConfig {
+            hash_key: None,
+            ttl_secs: 10 * 60,
+            max_attempts: 5,
+            resend_interval_secs: 60,
+        }
+    }
+}
+
+/// Channels OTPs and alerts go out on. Each is tried in turn, starting with
+/// the user's preferred one, until a delivery succeeds.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Tried after the user's preferred channel, skipping it.
+    pub fallback_order: Vec<ChannelKind>,
+    /// Upper bound on a single SMS or WhatsApp API call.
+    pub timeout_ms: u64,
+    /// Overrides for the built-in message templates, as
+    /// `<language>/<name>.hbs`. Reloaded on change in development.
+    pub templates_dir: String,
+    pub sms: SmsConfig,
+    pub whatsapp: WhatsappConfig,
+    pub email: EmailConfig,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            fallback_order: vec![ChannelKind::Sms, ChannelKind::Whatsapp, ChannelKind::Email],
+            timeout_ms: 5000,
+            templates_dir: "templates/messages".to_string(),
+            sms: SmsConfig::default(),
+            whatsapp: WhatsappConfig::default(),
+            email: EmailConfig::default(),
+        }
+    }
+}
+
+/// How a channel delivers. `log` and `file` write messages, codes included,
+/// in the clear, so they are only accepted when `server.environment` is
+/// `dev`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    #[default]
+    Off,
+    /// The real provider.
+    Live,
+    Log,
+    /// Appends a JSON line per message to `file_path`.
+    File,
+}
+
+/// SMS through Termii.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SmsConfig {
+    pub mode: ChannelMode,
+    pub file_path: Option<String>,
+    pub base_url: String,
+    pub api_key: Option<Secret>,
+    /// Registered alphanumeric sender ID.
+    pub sender_id: String,
+}
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        SmsConfig {
+            mode: ChannelMode::Off,
+            file_path: None,
+            base_url: "https://api.ng.termii.com".to_string(),
+            api_key: None,
+            sender_id: "Kharon".to_string(),
+        }
+    }
+}
+
+/// WhatsApp Cloud API text messages.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WhatsappConfig {
+    pub mode: ChannelMode,
+    pub file_path: Option<String>,
+    /// Graph API base url, including the version.
+    pub base_url: String,
+    /// The sending business number's ID, not the number itself.
+    pub phone_number_id: Option<String>,
+    pub access_token: Option<Secret>,
+    /// Signs inbound webhooks (`X-Hub-Signature-256`). With `verify_token`,
+    /// enables the `/webhooks/whatsapp` conversation.
+    pub app_secret: Option<Secret>,
+    /// Echoed back by Meta when the webhook subscription is set up.
+    pub verify_token: Option<Secret>,
+    /// Prefixed to a message's name, e.g. `kharon_otp`, to give the approved
+    /// template OTPs and alerts are sent with.
+    pub template_prefix: String,
+    /// WhatsApp locale of the templates for each of our languages, e.g.
+    /// `yo = "en"` while no Yoruba templates are approved. Languages left
+    /// out use their own code.
+    pub template_languages: HashMap<String, String>,
+}
+
+impl Default for WhatsappConfig {
+    fn default() -> Self {
+        WhatsappConfig {
+            mode: ChannelMode::Off,
+            file_path: None,
+            base_url: "https://graph.facebook.com/v21.0".to_string(),
+            phone_number_id: None,
+            access_token: None,
+            app_secret: None,
+            verify_token: None,
+            template_prefix: "kharon_".to_string(),
+            template_languages: HashMap::new(),
+        }
+    }
+}
+
+/// Email over SMTP with STARTTLS.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub mode: ChannelMode,
+    pub file_path: Option<String>,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    /// Sender mailbox, e.g. `Kharon <no-reply@kharon.io>`.
+    pub from: Option<String>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        EmailConfig {
+            mode: ChannelMode::Off,
+            file_path: None,
+            smtp_host: "smtp.gmail.com".to_string(),
+            smtp_port: 587,
+            username: None,
+            password: None,
+            from: None,
+        }
+    }
+}
+
+/// Attempt limit for transaction PINs, separate from the login lockout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PinConfig {
+    /// Wrong PINs in a row that lock the PIN.
+    pub max_attempts: i32,
+    /// How long the PIN stays locked; a reset through OTP lifts it early.
+    pub lock_secs: u64,
+}
+
+impl Default for PinConfig {
+    fn default() -> Self {
+        PinConfig {
+            max_attempts: 5,
+            lock_secs: 30 * 60,
+        }
+    }
+}
+
+/// Authenticator-app (RFC 6238) second factor for admins and opted-in users.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TotpConfig {
+    /// Shown as the account's issuer in authenticator apps.
+    pub issuer: String,
+    /// 32-byte AES-256 key, hex encoded, that TOTP secrets are encrypted
+    /// with. Enrolment, and with it every admin route, is unavailable
+    /// while it is unset.
+    pub encryption_key: Option<Secret>,
+    /// 30-second steps either side of now that are still accepted, for
+    /// clocks that have drifted.
+    pub skew_steps: u8,
+    /// Recovery codes issued at enrolment.
+    pub recovery_codes: usize,
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        TotpConfig {
+            issuer: "Kharon".to_string(),
+            encryption_key: None,
+            skew_steps: 1,
+            recovery_codes: 10,
+        }
+    }
+}
+
+/// Checks each login against the user's recent login locations.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub enabled: bool,
+    /// How far back, and how many, previous logins are compared against.
+    pub history_days: u64,
+    pub history_size: i64,
+    /// Faster than this between consecutive logins counts as impossible
+    /// travel. Airliners cruise at roughly 900 km/h.
+    pub max_travel_speed_kmh: f64,
+    /// Moves shorter than this are ignored; geolocation is often off by a
+    /// city or two.
+    pub min_travel_distance_km: f64,
+    /// Autonomous systems of hosting, cloud and VPN providers.
+    pub hosting_asns: Vec<u32>,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        RiskConfig {
+            enabled: true,
+            history_days: 90,
+            history_size: 20,
+            max_travel_speed_kmh: 1000.0,
+            min_travel_distance_km: 200.0,
+            hosting_asns: vec![
+                16509,  // Amazon
+                14618,  // Amazon
+                15169,  // Google
+                396982, // Google Cloud
+                8075,   // Microsoft
+                14061,  // DigitalOcean
+                16276,  // OVH
+                24940,  // Hetzner
+                63949,  // Akamai (Linode)
+                20473,  // Vultr
+                9009,   // M247, common VPN exit
+                60068,  // Datacamp (CDN77), common VPN exit
+                212238, // Datacamp (CDN77), common VPN exit
+            ],
+        }
+    }
+}
+
+/// Proving wallet ownership by signing a challenge.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WalletsConfig {
+    /// How long a challenge can be signed for.
+    pub challenge_ttl_secs: u64,
+    /// JSON-RPC node the public keys of Starknet accounts are read from.
+    /// Starknet wallets cannot be verified while it is unset.
+    pub starknet_rpc_url: Option<String>,
+    /// Chain ID in the domain of the typed data Starknet wallets sign.
+    pub starknet_chain_id: String,
+    pub rpc_timeout_ms: u64,
+}
+
+impl Default for WalletsConfig {
+    fn default() -> Self {
+        WalletsConfig {
+            challenge_ttl_secs: 10 * 60,
+            starknet_rpc_url: None,
+            starknet_chain_id: "SN_MAIN".to_string(),
+            rpc_timeout_ms: 5000,
+        }
+    }
+}
+
+/// Delivery of outbox events to other services.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    /// How often the dispatcher looks for new events and due retries.
+    pub poll_interval_ms: u64,
+    /// Events fanned out, and deliveries attempted, per poll.
+    pub batch_size: i64,
+    pub timeout_ms: u64,
+    /// Failed attempts after which a delivery becomes a dead letter.
+    pub max_attempts: i32,
+    /// Wait before the first retry, doubling with each failure up to
+    /// `max_retry_delay_secs`.
+    pub retry_delay_secs: u64,
+    pub max_retry_delay_secs: u64,
+    pub subscribers: Vec<SubscriberConfig>,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        OutboxConfig {
+            poll_interval_ms: 1000,
+            batch_size: 50,
+            timeout_ms: 5000,
+            max_attempts: 10,
+            retry_delay_secs: 10,
+            max_retry_delay_secs: 60 * 60,
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+/// A service that receives outbox events, signed with its `secret`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SubscriberConfig {
+    /// Identifies the subscriber's deliveries; renaming one drops its
+    /// pending deliveries.
+    pub name: String,
+    pub url: String,
+    pub secret: Secret,
+    /// Event types to send; all of them when empty.
+    #[serde(default)]
+    pub events: Vec<OutboxEventType>,
 }
 
+impl SubscriberConfig {
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|event| event.as_str() == event_type)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base url. Traces stay in-process when unset.
+    pub otlp_endpoint: Option<String>,
+}
+
+/// A configuration value that must never be printed. `Debug` and `Serialize`
+/// both emit a placeholder; call `expose` where the real value is needed.
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("[REDACTED]")
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Load(::config::ConfigError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Load(e) => write!(f, "failed to load configuration: {}", e),
+            ConfigError::Invalid(problems) => {
+                writeln!(f, "invalid configuration:")?;
+                for problem in problems {
+                    writeln!(f, "  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<::config::ConfigError> for ConfigError {
+    fn from(e: ::config::ConfigError) -> Self {
+        ConfigError::Load(e)
+    }
+}
+
+/// Longest lock the config accepts. Anything longer is a typo, and far
+/// larger values would overflow when added to the current time.
+const MAX_LOCK_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Legacy variable names and the keys they override.
+const LEGACY_ENV: &[(&str, &str)] = &[
+    ("BIND_ADDRESS", "server.host"),
+    ("PORT", "server.port"),
+    ("APP_ENV", "server.environment"),
+    ("DATABASE_URL", "database.url"),
+    ("JWT_SECRET", "auth.jwt_secret"),
+    ("HMAC_KEY", "auth.api_key"),
+    ("FLUTTERWAVE_SECRET_KEY", "providers.flutterwave.secret_key"),
+    (
+        "FLUTTERWAVE_SECRET_HASH",
+        "providers.flutterwave.secret_hash",
+    ),
+    ("IP_INFO_TOKEN", "geolocation.ipinfo_token"),
+    ("EMAIL_FROM", "notifications.email.from"),
+    ("SMTP_USERNAME", "notifications.email.username"),
+    ("SMTP_PASSWORD", "notifications.email.password"),
+    ("REDIS_URL", "redis.url"),
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "telemetry.otlp_endpoint"),
+];
+
 impl Config {
-    pub fn init() -> Config {
-        let _database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let ip_info_token = std::env::var("IP_INFO_TOKEN").expect("IP_INFO_TOKEN must be set");
-        let port = std::env::var("PORT").expect("PORT must be set");
-        let flutterwave_secret_key =
-            std::env::var("FLUTTERWAVE_SECRET_KEY").expect("FLUTTERWAVE_SECRET_KEY must be set");
-        let hmac_key = std::env::var("HMAC_KEY").expect("HMAC_KEY must be set");
+    /// Loads the configuration and rejects it unless it validates.
+    pub fn load() -> Result<Config, ConfigError> {
+        let config = Config::merged()?;
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+
+        Ok(config)
+    }
+
+    /// Layers the file and environment without validating the result, so
+    /// `--print-config` can show a configuration that fails validation.
+    pub fn merged() -> Result<Config, ConfigError> {
+        let file = match std::env::var("CONFIG_FILE") {
+            Ok(path) => File::with_name(&path).required(true),
+            Err(_) => File::with_name("config").required(false),
+        };
+
+        Config::from_sources(file, std::env::vars().collect())
+    }
+
+    /// `env` stands in for the process environment.
+    fn from_sources(
+        file: impl Source + Send + Sync + 'static,
+        env: Map<String, String>,
+    ) -> Result<Config, ConfigError> {
+        let mut builder = ConfigLayers::builder().add_source(file).add_source(
+            Environment::with_prefix("APP")
+                .prefix_separator("__")
+                .separator("__")
+                .list_separator(",")
+                .with_list_parse_key("cors.allowed_origins")
+                .with_list_parse_key("cors.allowed_methods")
+                .with_list_parse_key("cors.allowed_headers")
+                .try_parsing(true)
+                .source(Some(env.clone())),
+        );
+
+        for (var, key) in LEGACY_ENV {
+            let value = env
+                .get(*var)
+                .filter(|value| !value.trim().is_empty())
+                .cloned();
+            builder = builder.set_override_option(*key, value)?;
+        }
+
+        Ok(builder.build()?.try_deserialize()?)
+    }
+
+    /// Collects every problem rather than stopping at the first, so a bad
+    /// deployment can be fixed in one pass.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.server.port == 0 {
+            problems.push("server.port must be between 1 and 65535 (PORT)".to_string());
+        }
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is required (DATABASE_URL)".to_string());
+        } else if !self.database.url.expose().starts_with("postgres://")
+            && !self.database.url.expose().starts_with("postgresql://")
+        {
+            problems.push("database.url must be a postgres:// connection string".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+
+        if self.auth.jwt_secret.is_empty() {
+            problems.push("auth.jwt_secret is required (JWT_SECRET)".to_string());
+        }
+        if self.auth.api_key.is_empty() {
+            problems.push("auth.api_key is required (HMAC_KEY)".to_string());
+        }
+
+        let flutterwave = &self.providers.flutterwave;
+        if flutterwave.enabled && flutterwave.secret_key.as_ref().is_none_or(Secret::is_empty) {
+            problems.push(
+                "providers.flutterwave.secret_key is required when providers.flutterwave.enabled is true (FLUTTERWAVE_SECRET_KEY)"
+                    .to_string(),
+            );
+        }
+
+        let geolocation = &self.geolocation;
+        if geolocation.enabled
+            && geolocation.provider == GeoProviderKind::Ipinfo
+            && geolocation
+                .ipinfo_token
+                .as_ref()
+                .is_none_or(Secret::is_empty)
+        {
+            problems.push(
+                "geolocation.ipinfo_token is required when geolocation.provider is \"ipinfo\" (IP_INFO_TOKEN)"
+                    .to_string(),
+            );
+        }
+        if geolocation.enabled
+            && geolocation.provider == GeoProviderKind::Mmdb
+            && geolocation
+                .mmdb_city_path
+                .as_ref()
+                .is_none_or(String::is_empty)
+        {
+            problems.push(
+                "geolocation.mmdb_city_path is required when geolocation.provider is \"mmdb\""
+                    .to_string(),
+            );
+        }
+        if self.geolocation.timeout_ms == 0 {
+            problems.push("geolocation.timeout_ms must be greater than 0".to_string());
+        }
+        if self.geolocation.cache_capacity == 0 {
+            problems.push("geolocation.cache_capacity must be at least 1".to_string());
+        }
+
+        problems.extend(cors::validate(&self.cors));
+
+        if self.redis.pool_size == 0 {
+            problems.push("redis.pool_size must be at least 1".to_string());
+        }
+        if self.redis.memory_capacity == 0 {
+            problems.push("redis.memory_capacity must be at least 1".to_string());
+        }
+        for (index, rule) in self.rate_limit.rules.iter().enumerate() {
+            if !rule.path.starts_with('/') {
+                problems.push(format!(
+                    "rate_limit.rules[{}].path must start with '/'",
+                    index
+                ));
+            }
+            if rule.limit == 0 || rule.window_secs == 0 {
+                problems.push(format!(
+                    "rate_limit.rules[{}] needs a limit and window_secs of at least 1",
+                    index
+                ));
+            }
+        }
+
+        if self.risk.enabled
+            && (self.risk.max_travel_speed_kmh <= 0.0 || self.risk.history_size < 1)
+        {
+            problems.push(
+                "risk.max_travel_speed_kmh and risk.history_size must be greater than 0"
+                    .to_string(),
+            );
+        }
 
-        Config {
-            _database_url,
-            jwt_secret,
-            ip_info_token,
-            port,
-            flutterwave_secret_key,
-            hmac_key,
+        let lockout = &self.lockout;
+        if lockout.enabled
+            && (lockout.window_secs == 0 || lockout.max_failures < 1 || lockout.lock_secs == 0)
+        {
+            problems.push(
+                "lockout.window_secs, lockout.max_failures and lockout.lock_secs must be at least 1"
+                    .to_string(),
+            );
+        }
+        if lockout.window_secs > MAX_LOCK_SECS || lockout.lock_secs > MAX_LOCK_SECS {
+            problems.push(format!(
+                "lockout.window_secs and lockout.lock_secs must be at most {}",
+                MAX_LOCK_SECS
+            ));
+        }
+
+        if let Some(key) = self
+            .totp
+            .encryption_key
+            .as_ref()
+            .filter(|key| !key.is_empty())
+            && hex::decode(key.expose()).map_or(true, |key| key.len() != 32)
+        {
+            problems.push("totp.encryption_key must be 64 hex characters (32 bytes)".to_string());
+        }
+        if self.totp.issuer.contains(':') {
+            problems.push("totp.issuer cannot contain ':'".to_string());
         }
+
+        if self.otp.ttl_secs == 0 || self.otp.max_attempts < 1 {
+            problems.push("otp.ttl_secs and otp.max_attempts must be at least 1".to_string());
+        }
+        if self.otp.resend_interval_secs >= self.otp.ttl_secs {
+            problems.push("otp.resend_interval_secs must be less than otp.ttl_secs".to_string());
+        }
+
+        problems.extend(self.validate_notifications());
+
+        if self.pin.max_attempts < 1 || self.pin.lock_secs == 0 {
+            problems.push("pin.max_attempts and pin.lock_secs must be at least 1".to_string());
+        }
+        if self.pin.lock_secs > MAX_LOCK_SECS {
+            problems.push(format!("pin.lock_secs must be at most {}", MAX_LOCK_SECS));
+        }
+
+        let wallets = &self.wallets;
+        if wallets.challenge_ttl_secs == 0 || wallets.rpc_timeout_ms == 0 {
+            problems.push(
+                "wallets.challenge_ttl_secs and rpc_timeout_ms must be at least 1".to_string(),
+            );
+        }
+        if let Some(url) = &wallets.starknet_rpc_url
+            && !url.starts_with("https://")
+            && !url.starts_with("http://")
+        {
+            problems.push("wallets.starknet_rpc_url must be an http(s) URL".to_string());
+        }
+        // Encoded as a Cairo short string, so at most 31 ASCII characters.
+        let chain_id = &wallets.starknet_chain_id;
+        if chain_id.is_empty() || chain_id.len() > 31 || !chain_id.is_ascii() {
+            problems.push("wallets.starknet_chain_id must be 1 to 31 ASCII characters".to_string());
+        }
+
+        problems.extend(self.validate_outbox());
+
+        problems
+    }
+
+    fn validate_outbox(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let outbox = &self.outbox;
+
+        if outbox.poll_interval_ms == 0 || outbox.batch_size < 1 || outbox.max_attempts < 1 {
+            problems.push(
+                "outbox.poll_interval_ms, batch_size and max_attempts must be at least 1"
+                    .to_string(),
+            );
+        }
+        if outbox.retry_delay_secs == 0 || outbox.retry_delay_secs > outbox.max_retry_delay_secs {
+            problems.push(
+                "outbox.retry_delay_secs must be at least 1 and at most max_retry_delay_secs"
+                    .to_string(),
+            );
+        }
+        if outbox.max_retry_delay_secs > 7 * 24 * 60 * 60 {
+            problems.push("outbox.max_retry_delay_secs can be at most a week".to_string());
+        }
+
+        let mut names = std::collections::HashSet::new();
+        for subscriber in &outbox.subscribers {
+            let name = subscriber.name.trim();
+            if name.is_empty() || name.len() > 100 {
+                problems.push("outbox.subscribers names must be 1 to 100 characters".to_string());
+            } else if !names.insert(name) {
+                problems.push(format!("outbox.subscribers has two named \"{}\"", name));
+            }
+            if !subscriber.url.starts_with("https://") && !subscriber.url.starts_with("http://") {
+                problems.push(format!(
+                    "outbox.subscribers \"{}\" url must be an http(s) URL",
+                    name
+                ));
+            }
+            if subscriber.secret.is_empty() {
+                problems.push(format!("outbox.subscribers \"{}\" needs a secret", name));
+            }
+        }
+
+        problems
+    }
+
+    fn validate_notifications(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let notifications = &self.notifications;
+
+        let modes = [
+            ("sms", notifications.sms.mode, &notifications.sms.file_path),
+            (
+                "whatsapp",
+                notifications.whatsapp.mode,
+                &notifications.whatsapp.file_path,
+            ),
+            (
+                "email",
+                notifications.email.mode,
+                &notifications.email.file_path,
+            ),
+        ];
+        for (name, mode, file_path) in modes {
+            if matches!(mode, ChannelMode::Log | ChannelMode::File) && !self.server.is_dev() {
+                problems.push(format!(
+                    "notifications.{}.mode can only be \"log\" or \"file\" when server.environment is \"dev\"",
+                    name
+                ));
+            }
+            if mode == ChannelMode::File && file_path.as_ref().is_none_or(String::is_empty) {
+                problems.push(format!(
+                    "notifications.{}.file_path is required when its mode is \"file\"",
+                    name
+                ));
+            }
+        }
+
+        if notifications.sms.mode == ChannelMode::Live
+            && notifications
+                .sms
+                .api_key
+                .as_ref()
+                .is_none_or(Secret::is_empty)
+        {
+            problems.push(
+                "notifications.sms.api_key is required when its mode is \"live\"".to_string(),
+            );
+        }
+
+        let whatsapp = &notifications.whatsapp;
+        if whatsapp.mode == ChannelMode::Live
+            && (whatsapp
+                .phone_number_id
+                .as_ref()
+                .is_none_or(String::is_empty)
+                || whatsapp.access_token.as_ref().is_none_or(Secret::is_empty))
+        {
+            problems.push(
+                "notifications.whatsapp.phone_number_id and access_token are required when its mode is \"live\""
+                    .to_string(),
+            );
+        }
+        let app_secret_set = whatsapp.app_secret.as_ref().is_some_and(|s| !s.is_empty());
+        let verify_token_set = whatsapp
+            .verify_token
+            .as_ref()
+            .is_some_and(|s| !s.is_empty());
+        if app_secret_set != verify_token_set {
+            problems.push(
+                "notifications.whatsapp.app_secret and verify_token must be set together"
+                    .to_string(),
+            );
+        }
+        if app_secret_set && whatsapp.mode == ChannelMode::Off {
+            problems.push(
+                "notifications.whatsapp.mode cannot be \"off\" when the webhook is enabled, as replies go over WhatsApp"
+                    .to_string(),
+            );
+        }
+
+        let email = &notifications.email;
+        if email.mode != ChannelMode::Off
+            && email
+                .from
+                .as_ref()
+                .is_none_or(|from| from.parse::<lettre::message::Mailbox>().is_err())
+        {
+            problems.push(
+                "notifications.email.from must be a valid mailbox when email is enabled (EMAIL_FROM)"
+                    .to_string(),
+            );
+        }
+        if email.mode == ChannelMode::Live && email.smtp_host.trim().is_empty() {
+            problems.push(
+                "notifications.email.smtp_host is required when its mode is \"live\"".to_string(),
+            );
+        }
+
+        if notifications.timeout_ms == 0 {
+            problems.push("notifications.timeout_ms must be greater than 0".to_string());
+        }
+
+        problems
+    }
+
+    /// TOML dump of the effective configuration with secrets masked.
+    pub fn to_redacted_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
     }
 }
 
-unsafe impl Send for Config {}
-unsafe impl Sync for Config {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::config::FileFormat;
+
+    const FILE: &str = r#"
+        [server]
+        port = 8080
+
+        [database]
+        url = "postgres://file/db"
+
+        [auth]
+        jwt_secret = "file-jwt-secret"
+        api_key = "file-api-key"
+    "#;
+
+    fn load(env: &[(&str, &str)]) -> Config {
+        let env = env
+            .iter()
+            .map(|(var, value)| (var.to_string(), value.to_string()))
+            .collect();
+
+        Config::from_sources(File::from_str(FILE, FileFormat::Toml), env).unwrap()
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let mut config = Config::default();
+        config.server.port = 0;
+        config.database.max_connections = 0;
+
+        let problems = config.validate();
+        for expected in [
+            "server.port must be between 1 and 65535 (PORT)",
+            "database.url is required (DATABASE_URL)",
+            "database.max_connections must be at least 1",
+            "auth.jwt_secret is required (JWT_SECRET)",
+        ] {
+            assert!(
+                problems.iter().any(|problem| problem == expected),
+                "missing {:?} in {:?}",
+                expected,
+                problems
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_variables_override_the_file_and_app_variables() {
+        let config = load(&[
+            ("APP__SERVER__PORT", "7000"),
+            ("APP__SERVER__HOST", "0.0.0.0"),
+            ("PORT", "9090"),
+            ("DATABASE_URL", "postgres://legacy/db"),
+        ]);
+
+        assert_eq!(config.server.port, 9090);
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.database.url.expose(), "postgres://legacy/db");
+        assert_eq!(config.auth.jwt_secret.expose(), "file-jwt-secret");
+    }
+
+    #[test]
+    fn blank_legacy_variables_are_ignored() {
+        let config = load(&[("JWT_SECRET", "  "), ("PORT", "")]);
+
+        assert_eq!(config.auth.jwt_secret.expose(), "file-jwt-secret");
+        assert_eq!(config.server.port, 8080);
+    }
+
+    #[test]
+    fn printed_config_hides_secrets() {
+        let dump = load(&[]).to_redacted_toml().unwrap();
+
+        assert!(!dump.contains("file-jwt-secret"));
+        assert!(!dump.contains("postgres://file/db"));
+        assert!(dump.contains("[REDACTED]"));
+    }
+}
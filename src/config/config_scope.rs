@@ -1,8 +1,33 @@
+use crate::routes::admin::outbox::{list_dead_letters_handler, retry_dead_letter_handler};
+use crate::routes::admin::totp::{confirm_admin_totp_handler, enrol_admin_totp_handler};
+use crate::routes::admin::users::unlock_user_handler;
+use crate::routes::admin::webhooks::replay_flutterwave_event_handler;
 use crate::routes::healthz::{check_health, health};
+use crate::routes::internal::wallets::lookup_wallets_handler;
+use crate::routes::metrics::metrics_handler;
+use crate::routes::users::devices::{list_user_devices_handler, revoke_user_device_handler};
+use crate::routes::users::notifications::{
+    confirm_notification_email_handler, update_notification_preferences_handler,
+};
+use crate::routes::users::otp::{send_otp_handler, verify_otp_handler};
+use crate::routes::users::pin::{
+    change_pin_handler, confirm_pin_reset_handler, request_pin_reset_handler, set_pin_handler,
+    verify_pin_handler,
+};
 use crate::routes::users::profile::{
     confirm_user_bank_account_handler, create_user_handler, get_user_bank_accounts_handler,
     verify_user_bank_account_handler,
 };
+use crate::routes::users::totp::{
+    confirm_totp_handler, disable_totp_handler, enrol_totp_handler,
+    regenerate_recovery_codes_handler,
+};
+use crate::routes::users::wallets::{
+    archive_wallet_handler, link_wallet_handler, list_wallets_handler, update_wallet_handler,
+    verify_wallet_handler, wallet_challenge_handler,
+};
+use crate::routes::webhooks::flutterwave::flutterwave_webhook_handler;
+use crate::routes::webhooks::whatsapp::{whatsapp_subscription_handler, whatsapp_webhook_handler};
 use actix_web::web::{self, service};
 
 pub fn config(conf: &mut web::ServiceConfig) {
@@ -12,7 +37,41 @@ pub fn config(conf: &mut web::ServiceConfig) {
         .service(verify_user_bank_account_handler)
         .service(confirm_user_bank_account_handler)
         .service(get_user_bank_accounts_handler)
+        .service(list_user_devices_handler)
+        .service(revoke_user_device_handler)
+        .service(update_notification_preferences_handler)
+        .service(confirm_notification_email_handler)
+        .service(send_otp_handler)
+        .service(verify_otp_handler)
+        .service(set_pin_handler)
+        .service(verify_pin_handler)
+        .service(change_pin_handler)
+        .service(request_pin_reset_handler)
+        .service(confirm_pin_reset_handler)
+        .service(enrol_totp_handler)
+        .service(confirm_totp_handler)
+        .service(disable_totp_handler)
+        .service(regenerate_recovery_codes_handler)
+        .service(link_wallet_handler)
+        .service(list_wallets_handler)
+        .service(update_wallet_handler)
+        .service(archive_wallet_handler)
+        .service(wallet_challenge_handler)
+        .service(verify_wallet_handler)
+        .service(unlock_user_handler)
+        .service(enrol_admin_totp_handler)
+        .service(confirm_admin_totp_handler)
+        .service(replay_flutterwave_event_handler)
+        .service(list_dead_letters_handler)
+        .service(retry_dead_letter_handler)
+        .service(lookup_wallets_handler)
         .service(health)
         .service(check_health);
-    conf.service(scope);
+    // Webhooks are called by providers, which authenticate with signatures
+    // rather than the API key.
+    conf.service(scope)
+        .service(metrics_handler)
+        .service(whatsapp_subscription_handler)
+        .service(whatsapp_webhook_handler)
+        .service(flutterwave_webhook_handler);
 }
@@ -1,2 +1,3 @@
 pub mod config;
 pub mod config_scope;
+pub mod cors;
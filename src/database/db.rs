@@ -1,12 +1,18 @@
 use crate::database::{
-    otp_db::OtpImpl, token_db::TokenImpl, user_bank_account_db::UserBankImpl, user_db::UserImpl,
-    user_security_log_db::UserSecurityLogsImpl, user_wallet_db::UserWalletImpl,
+    flutterwave_event_db::FlutterwaveEventImpl, otp_db::OtpImpl, outbox_db::OutboxImpl,
+    query_tracing::QueryTracing, token_db::TokenImpl, transaction_db::TransactionImpl,
+    user_bank_account_db::UserBankImpl, user_db::UserImpl, user_device_db::UserDeviceImpl,
+    user_pin_db::UserPinImpl, user_security_log_db::UserSecurityLogsImpl,
+    user_totp_db::UserTotpImpl, user_wallet_db::UserWalletImpl,
+    whatsapp_conversation_db::WhatsappConversationImpl,
 };
 use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
-use dotenv::dotenv;
-use r2d2::{Error as PoolError, Pool, PooledConnection};
+use r2d2::{Error as PoolError, HandleEvent, Pool, PooledConnection};
+use std::time::Duration;
+
+use crate::config::config::DatabaseConfig;
 
 pub type DBPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
@@ -21,7 +27,6 @@ pub enum AppError {
 pub enum DatabaseSetupError {
     DbConnectionError(PoolError),
     DieselError(diesel::result::Error),
-    DatabaseUrlNotSet,
     ErrorRunningMigrations,
 }
 
@@ -31,20 +36,24 @@ pub struct Database {
 }
 
 impl Database {
-    pub fn new() -> Result<Self, DatabaseSetupError> {
-        dotenv().ok();
-
-        let database_url =
-            std::env::var("DATABASE_URL").map_err(|_| DatabaseSetupError::DatabaseUrlNotSet)?;
+    pub fn new(
+        config: &DatabaseConfig,
+        run_pending_migrations: bool,
+        event_handler: Box<dyn HandleEvent>,
+    ) -> Result<Self, DatabaseSetupError> {
+        diesel::connection::set_default_instrumentation(QueryTracing::boxed)
+            .map_err(DatabaseSetupError::DieselError)?;
 
-        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        let manager = ConnectionManager::<PgConnection>::new(config.url.expose());
         let pool = Pool::builder()
+            .max_size(config.max_connections)
+            .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
+            .event_handler(event_handler)
             .build(manager)
             .map_err(DatabaseSetupError::DbConnectionError)?;
 
         // Only run migrations in development
-        let env = std::env::var("APP_ENV").unwrap_or_else(|_| "prod".into());
-        if env == "dev" {
+        if run_pending_migrations {
             run_migrations(&pool)?;
         }
 
@@ -53,11 +62,11 @@ impl Database {
 }
 
 fn run_migrations(pool: &Pool<ConnectionManager<PgConnection>>) -> Result<(), DatabaseSetupError> {
-    println!("RUNNING MIGRATIONS....");
+    tracing::info!("Running migrations");
     let mut conn = pool.get().map_err(DatabaseSetupError::DbConnectionError)?;
     conn.run_pending_migrations(MIGRATIONS)
         .map_err(|_| DatabaseSetupError::ErrorRunningMigrations)?;
-    println!("MIGRATIONS COMPLETED....");
+    tracing::info!("Migrations completed");
     Ok(())
 }
 
@@ -77,3 +86,10 @@ impl OtpImpl for Database {}
 impl UserSecurityLogsImpl for Database {}
 impl UserBankImpl for Database {}
 impl TokenImpl for Database {}
+impl UserDeviceImpl for Database {}
+impl UserPinImpl for Database {}
+impl UserTotpImpl for Database {}
+impl WhatsappConversationImpl for Database {}
+impl FlutterwaveEventImpl for Database {}
+impl TransactionImpl for Database {}
+impl OutboxImpl for Database {}
@@ -0,0 +1,52 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{FlutterwaveEvent, NewFlutterwaveEvent};
+use crate::models::schema::flutterwave_events::dsl::*;
+use chrono::Utc;
+use diesel::prelude::*;
+
+pub trait FlutterwaveEventImpl: DbAccess {
+    /// Stores an event unless one with its ID was already received, in which
+    /// case `None` is returned.
+    fn store_flutterwave_event(
+        &self,
+        event: NewFlutterwaveEvent,
+    ) -> Result<Option<FlutterwaveEvent>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::insert_into(flutterwave_events)
+            .values(&event)
+            .on_conflict_do_nothing()
+            .get_result(&mut conn)
+            .optional()
+            .map_err(AppError::DieselError)
+    }
+
+    fn get_flutterwave_event(&self, find_event_id: &str) -> Result<FlutterwaveEvent, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        flutterwave_events
+            .find(find_event_id)
+            .first::<FlutterwaveEvent>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Records a handler run: processed if `error` is `None`, otherwise left
+    /// for a replay with the error kept.
+    fn record_flutterwave_event_attempt(
+        &self,
+        find_event_id: &str,
+        error: Option<String>,
+    ) -> Result<FlutterwaveEvent, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let processed = error.is_none().then(Utc::now);
+        diesel::update(flutterwave_events.find(find_event_id))
+            .set((
+                attempts.eq(attempts + 1),
+                processed_at.eq(processed),
+                last_error.eq(error),
+            ))
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+}
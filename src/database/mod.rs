@@ -1,7 +1,15 @@
 pub mod db;
+pub mod flutterwave_event_db;
 pub mod otp_db;
+pub mod outbox_db;
+pub mod query_tracing;
 pub mod token_db;
+pub mod transaction_db;
 pub mod user_bank_account_db;
 pub mod user_db;
+pub mod user_device_db;
+pub mod user_pin_db;
 pub mod user_security_log_db;
+pub mod user_totp_db;
 pub mod user_wallet_db;
+pub mod whatsapp_conversation_db;
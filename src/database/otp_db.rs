@@ -1,7 +1,7 @@
 use super::db::{AppError, DbAccess};
 use crate::models::models::{NewOtp, Otp};
 use crate::models::schema::otp::dsl::*;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::sql_types::Text;
 
@@ -10,23 +10,49 @@ diesel::define_sql_function! {
 }
 
 pub trait OtpImpl: DbAccess {
-    fn create_otp(&self, new_otp: NewOtp) -> Result<Otp, AppError> {
+    /// Stores a code, replacing the user's active one for the same purpose
+    /// and starting its attempt count afresh. An existing code is only
+    /// replaced if it was issued at or before `replace_issued_before`;
+    /// otherwise nothing is stored and `None` is returned.
+    fn create_otp(
+        &self,
+        new_otp: NewOtp,
+        replace_issued_before: DateTime<Utc>,
+    ) -> Result<Option<Otp>, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
-        diesel::insert_into(otp)
+        let upsert = diesel::insert_into(otp)
             .values(&new_otp)
+            .on_conflict((user_id, purpose))
+            .do_update()
+            .set((&new_otp, attempts.eq(0), created_at.eq(Utc::now())));
+        // `ON CONFLICT ... DO UPDATE ... WHERE`; `QueryDsl::filter` does not
+        // cover upserts.
+        diesel::query_dsl::methods::FilterDsl::filter(upsert, created_at.le(replace_issued_before))
             .get_result(&mut conn)
+            .optional()
             .map_err(AppError::DieselError)
     }
 
-    fn get_otp_by_user_id(&self, find_user: String) -> Result<Otp, AppError> {
+    fn get_otp(&self, find_user: &str, find_purpose: &str) -> Result<Otp, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
         otp.filter(user_id.eq(find_user))
+            .filter(purpose.eq(find_purpose))
             .first::<Otp>(&mut conn)
             .map_err(AppError::DieselError)
     }
 
+    /// Counts a wrong guess against a code and returns it updated.
+    fn record_otp_attempt(&self, find_id: uuid::Uuid) -> Result<Otp, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(otp.find(find_id))
+            .set(attempts.eq(attempts + 1))
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
     fn delete_otp_by_id(&self, find_id: uuid::Uuid) -> Result<Otp, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
@@ -35,6 +61,19 @@ pub trait OtpImpl: DbAccess {
             .map_err(AppError::DieselError)
     }
 
+    /// Withdraws the user's outstanding code for a purpose, if any.
+    fn delete_otp(&self, find_user: &str, find_purpose: &str) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::delete(
+            otp.filter(user_id.eq(find_user))
+                .filter(purpose.eq(find_purpose)),
+        )
+        .execute(&mut conn)
+        .map(|_| ())
+        .map_err(AppError::DieselError)
+    }
+
     fn delete_expired_otps(&self) -> Result<Vec<Otp>, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
         diesel::delete(otp.filter(expires_at.lt(Utc::now().naive_utc())))
@@ -0,0 +1,184 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{
+    NewOutboxDelivery, NewOutboxEvent, OutboxDelivery, OutboxEvent, OutboxEventType,
+};
+use crate::models::schema::{outbox_deliveries, outbox_events};
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::prelude::*;
+
+/// Adds an event to the outbox. Call it inside the transaction that makes
+/// the change, so the event is recorded if and only if the change is.
+pub fn enqueue_event(
+    conn: &mut PgConnection,
+    event_type: OutboxEventType,
+    payload: serde_json::Value,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(outbox_events::table)
+        .values(NewOutboxEvent {
+            event_type: event_type.as_str().to_string(),
+            payload,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+pub trait OutboxImpl: DbAccess {
+    /// Queues a delivery of each new event to every subscriber
+    /// `subscribers_for` names for its type. Returns how many events were
+    /// fanned out.
+    fn fan_out_outbox_events(
+        &self,
+        limit: i64,
+        subscribers_for: impl Fn(&str) -> Vec<String>,
+    ) -> Result<usize, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let events = outbox_events::table
+                .filter(outbox_events::fanned_out_at.is_null())
+                .order(outbox_events::created_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .load::<OutboxEvent>(conn)?;
+            if events.is_empty() {
+                return Ok(0);
+            }
+
+            let deliveries: Vec<NewOutboxDelivery> = events
+                .iter()
+                .flat_map(|event| {
+                    subscribers_for(&event.event_type)
+                        .into_iter()
+                        .map(|subscriber| NewOutboxDelivery {
+                            event_id: event.id,
+                            subscriber,
+                        })
+                })
+                .collect();
+            diesel::insert_into(outbox_deliveries::table)
+                .values(&deliveries)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            let ids: Vec<uuid::Uuid> = events.iter().map(|event| event.id).collect();
+            diesel::update(outbox_events::table.filter(outbox_events::id.eq_any(&ids)))
+                .set(outbox_events::fanned_out_at.eq(Utc::now()))
+                .execute(conn)?;
+
+            Ok(events.len())
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// Takes up to `limit` deliveries that are due, pushing their next
+    /// attempt back by `lease` so no other dispatcher picks them up while
+    /// they are being sent.
+    fn claim_outbox_deliveries(
+        &self,
+        limit: i64,
+        lease: TimeDelta,
+    ) -> Result<Vec<(OutboxDelivery, OutboxEvent)>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let now = Utc::now();
+            let ids = outbox_deliveries::table
+                .select(outbox_deliveries::id)
+                .filter(outbox_deliveries::delivered_at.is_null())
+                .filter(outbox_deliveries::dead_at.is_null())
+                .filter(outbox_deliveries::next_attempt_at.le(now))
+                .order(outbox_deliveries::next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .load::<uuid::Uuid>(conn)?;
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            diesel::update(outbox_deliveries::table.filter(outbox_deliveries::id.eq_any(&ids)))
+                .set(outbox_deliveries::next_attempt_at.eq(now + lease))
+                .execute(conn)?;
+
+            outbox_deliveries::table
+                .inner_join(outbox_events::table)
+                .filter(outbox_deliveries::id.eq_any(&ids))
+                .load::<(OutboxDelivery, OutboxEvent)>(conn)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    fn mark_outbox_delivered(&self, delivery_id: uuid::Uuid) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(outbox_deliveries::table.find(delivery_id))
+            .set((
+                outbox_deliveries::attempts.eq(outbox_deliveries::attempts + 1),
+                outbox_deliveries::delivered_at.eq(Utc::now()),
+                outbox_deliveries::last_error.eq(None::<String>),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::DieselError)?;
+        Ok(())
+    }
+
+    /// Records a failed attempt, to be retried at `retry_at`, or with `None`
+    /// moved to the dead letters.
+    fn record_outbox_failure(
+        &self,
+        delivery_id: uuid::Uuid,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let now = Utc::now();
+        diesel::update(outbox_deliveries::table.find(delivery_id))
+            .set((
+                outbox_deliveries::attempts.eq(outbox_deliveries::attempts + 1),
+                outbox_deliveries::last_error.eq(error),
+                outbox_deliveries::next_attempt_at.eq(retry_at.unwrap_or(now)),
+                outbox_deliveries::dead_at.eq(retry_at.is_none().then_some(now)),
+            ))
+            .execute(&mut conn)
+            .map_err(AppError::DieselError)?;
+        Ok(())
+    }
+
+    /// Deliveries that ran out of attempts, most recent first.
+    fn get_dead_outbox_deliveries(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<(OutboxDelivery, OutboxEvent)>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        outbox_deliveries::table
+            .inner_join(outbox_events::table)
+            .filter(outbox_deliveries::dead_at.is_not_null())
+            .order(outbox_deliveries::dead_at.desc())
+            .limit(limit)
+            .load::<(OutboxDelivery, OutboxEvent)>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Gives a dead delivery a fresh set of attempts. Returns false if it
+    /// is not dead.
+    fn requeue_outbox_delivery(&self, delivery_id: uuid::Uuid) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let requeued = diesel::update(
+            outbox_deliveries::table
+                .find(delivery_id)
+                .filter(outbox_deliveries::dead_at.is_not_null()),
+        )
+        .set((
+            outbox_deliveries::attempts.eq(0),
+            outbox_deliveries::dead_at.eq(None::<DateTime<Utc>>),
+            outbox_deliveries::next_attempt_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::DieselError)?;
+        Ok(requeued > 0)
+    }
+}
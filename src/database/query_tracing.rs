@@ -0,0 +1,43 @@
+use diesel::connection::{Instrumentation, InstrumentationEvent};
+use tracing::Span;
+
+/// Opens a `db.query` span around every statement a pooled connection runs,
+/// so each Diesel call made through the `*Impl` traits shows up under the
+/// span of the request that issued it.
+#[derive(Default)]
+pub struct QueryTracing {
+    current: Option<Span>,
+}
+
+impl QueryTracing {
+    pub fn boxed() -> Option<Box<dyn Instrumentation>> {
+        Some(Box::new(QueryTracing::default()))
+    }
+}
+
+impl Instrumentation for QueryTracing {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                // Display output ends with the bind values, which carry phone
+                // and account numbers; only the statement is recorded.
+                let rendered = query.to_string();
+                let statement = rendered.split(" -- binds:").next().unwrap_or_default();
+
+                self.current = Some(tracing::info_span!(
+                    "db.query",
+                    otel.kind = "client",
+                    db.system = "postgresql",
+                    db.statement = statement,
+                    error = tracing::field::Empty,
+                ));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let (Some(span), Some(error)) = (self.current.take(), error) {
+                    span.record("error", tracing::field::display(error));
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use super::db::{AppError, DbAccess};
+use crate::models::schema::transactions::dsl::*;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+pub trait TransactionImpl: DbAccess {
+    /// Records the outcome of the payout with the given provider reference.
+    /// A completed transaction is final, so a replayed or late event does
+    /// not overwrite it. Returns how many transactions were updated.
+    fn settle_transaction(
+        &self,
+        find_reference: &str,
+        status: &str,
+        settled_at: Option<DateTime<Utc>>,
+    ) -> Result<usize, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            transactions
+                .filter(transaction_reference.eq(find_reference))
+                .filter(settlement_status.is_distinct_from("completed")),
+        )
+        .set((
+            settlement_status.eq(status),
+            settlement_date.eq(settled_at),
+            updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .map_err(AppError::DieselError)
+    }
+
+    fn transaction_reference_exists(&self, find_reference: &str) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::select(diesel::dsl::exists(
+            transactions.filter(transaction_reference.eq(find_reference)),
+        ))
+        .get_result(&mut conn)
+        .map_err(AppError::DieselError)
+    }
+}
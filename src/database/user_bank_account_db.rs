@@ -1,6 +1,7 @@
 use super::db::{AppError, DbAccess};
+use super::outbox_db::enqueue_event;
 
-use crate::models::models::{NewUserBankAccount, UserBankAccount};
+use crate::models::models::{NewUserBankAccount, OutboxEventType, UserBankAccount};
 use crate::models::schema::user_bank_account::dsl::*;
 use diesel::prelude::*;
 use diesel::sql_types::Text;
@@ -10,16 +11,37 @@ diesel::define_sql_function! {
 }
 
 pub trait UserBankImpl: DbAccess {
+    /// Adds a bank account, making it the default if the user has none.
     fn create_user_bank(
         &self,
         bank_details: NewUserBankAccount,
     ) -> Result<UserBankAccount, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
-        diesel::insert_into(user_bank_account)
-            .values(&bank_details)
-            .get_result(&mut conn)
-            .map_err(AppError::DieselError)
+        conn.transaction(|conn| {
+            let has_default: bool = diesel::select(diesel::dsl::exists(
+                user_bank_account
+                    .filter(user_id.eq(&bank_details.user_id))
+                    .filter(is_default.eq(true)),
+            ))
+            .get_result(conn)?;
+            let bank: UserBankAccount = diesel::insert_into(user_bank_account)
+                .values((&bank_details, is_default.eq(!has_default)))
+                .get_result(conn)?;
+            enqueue_event(
+                conn,
+                OutboxEventType::BankAccountAdded,
+                serde_json::json!({
+                    "user_id": bank.user_id,
+                    "bank_account_id": bank.id,
+                    "bank_name": bank.bank_name,
+                    "account_name": bank.account_name,
+                    "account_last4": bank.account_last4(),
+                }),
+            )?;
+            Ok(bank)
+        })
+        .map_err(AppError::DieselError)
     }
 
     fn get_wallet_by_id(&self, bank_account_id: uuid::Uuid) -> Result<UserBankAccount, AppError> {
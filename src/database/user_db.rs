@@ -1,6 +1,8 @@
 use super::db::{AppError, DbAccess};
-use crate::models::models::{NewUser, User};
+use super::outbox_db::enqueue_event;
+use crate::models::models::{NewUser, OutboxEventType, User, UserNotificationPreferences};
 use crate::models::schema::users::dsl::*;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::sql_types::Text;
 
@@ -14,11 +16,10 @@ pub trait UserImpl: DbAccess {
         users.load::<User>(&mut conn).map_err(AppError::DieselError)
     }
 
-    // TODO: EDITED FOR WHATSAPP INTEGRATION, REVERT BACK LATER
     fn get_user_by_email(&self, find_email: String) -> Result<User, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
         users
-            // .filter(lower(email).eq(lower(find_email)))
+            .filter(lower(email.assume_not_null()).eq(lower(find_email)))
             .first::<User>(&mut conn)
             .map_err(AppError::DieselError)
     }
@@ -42,9 +43,113 @@ pub trait UserImpl: DbAccess {
     fn create_user(&self, user: NewUser) -> Result<User, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
-        diesel::insert_into(users)
-            .values(&user)
+        conn.transaction(|conn| {
+            let created: User = diesel::insert_into(users).values(&user).get_result(conn)?;
+            enqueue_event(
+                conn,
+                OutboxEventType::UserCreated,
+                serde_json::json!({
+                    "user_id": created.id,
+                    "phone": created.phone,
+                    "verified": created.verified,
+                    "role": created.role,
+                }),
+            )?;
+            Ok(created)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// Marks the phone number as verified, once the user has entered an OTP
+    /// sent to it.
+    fn mark_user_verified(&self, uid: &str) -> Result<User, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let updated: Option<User> = diesel::update(users.find(uid).filter(verified.eq(false)))
+                .set(verified.eq(true))
+                .get_result(conn)
+                .optional()?;
+            let Some(user) = updated else {
+                return users.find(uid).first::<User>(conn);
+            };
+
+            enqueue_event(
+                conn,
+                OutboxEventType::UserVerified,
+                serde_json::json!({
+                    "user_id": user.id,
+                    "phone": user.phone,
+                }),
+            )?;
+            Ok(user)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    fn update_user_notification_preferences(
+        &self,
+        uid: &str,
+        preferences: UserNotificationPreferences,
+    ) -> Result<User, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(users.find(uid))
+            .set(&preferences)
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Makes the pending email the user's address. Returns `None` if there
+    /// was none waiting.
+    fn confirm_pending_email(&self, uid: &str) -> Result<Option<User>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(users.find(uid).filter(pending_email.is_not_null()))
+            .set((email.eq(pending_email), pending_email.eq(None::<String>)))
             .get_result(&mut conn)
+            .optional()
+            .map_err(AppError::DieselError)
+    }
+
+    /// Locks the account until `until`. Returns false, leaving the existing
+    /// lock alone, if the account is already locked.
+    fn lock_user(&self, uid: &str, until: DateTime<Utc>) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+        let now = Utc::now();
+
+        diesel::update(
+            users
+                .find(uid)
+                .filter(locked_until.is_null().or(locked_until.le(now))),
+        )
+        .set(locked_until.eq(until))
+        .execute(&mut conn)
+        .map(|updated| updated > 0)
+        .map_err(AppError::DieselError)
+    }
+
+    /// Lifts a lock early. Returns false if the account was not locked.
+    fn unlock_user(&self, uid: &str) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+        let now = Utc::now();
+
+        diesel::update(users.find(uid).filter(locked_until.gt(now)))
+            .set(locked_until.eq(None::<DateTime<Utc>>))
+            .execute(&mut conn)
+            .map(|updated| updated > 0)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Clears locks that have run out and returns the affected user IDs.
+    fn unlock_expired_users(&self) -> Result<Vec<String>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+        let now = Utc::now();
+
+        diesel::update(users.filter(locked_until.le(now)))
+            .set(locked_until.eq(None::<DateTime<Utc>>))
+            .returning(id)
+            .get_results(&mut conn)
             .map_err(AppError::DieselError)
     }
 }
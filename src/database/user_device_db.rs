@@ -0,0 +1,120 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{NewUserDevice, UserDevice};
+use crate::models::schema::user_devices::dsl::*;
+use crate::models::schema::user_jwt_tokens;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+pub trait UserDeviceImpl: DbAccess {
+    /// Records a sighting of a device, adding it if the user has not used it
+    /// before. Returns the device and whether it is new to the user; a device
+    /// that was revoked comes back as new and untrusted.
+    fn touch_user_device(&self, device: NewUserDevice) -> Result<(UserDevice, bool), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let inserted = diesel::insert_into(user_devices)
+                .values(&device)
+                .on_conflict((user_id, device_id))
+                .do_nothing()
+                .get_result::<UserDevice>(conn)
+                .optional()?;
+            if let Some(inserted) = inserted {
+                return Ok((inserted, true));
+            }
+
+            let now = Utc::now();
+            let existing = user_devices
+                .filter(user_id.eq(&device.user_id))
+                .filter(device_id.eq(&device.device_id))
+                .for_update()
+                .first::<UserDevice>(conn)?;
+            let target = user_devices.find(existing.id);
+
+            if existing.revoked_at.is_none() {
+                let updated = diesel::update(target)
+                    .set((&device, last_seen_at.eq(now)))
+                    .get_result::<UserDevice>(conn)?;
+                return Ok((updated, false));
+            }
+
+            let restored = diesel::update(target)
+                .set((
+                    &device,
+                    trusted.eq(false),
+                    first_seen_at.eq(now),
+                    last_seen_at.eq(now),
+                    revoked_at.eq(None::<DateTime<Utc>>),
+                ))
+                .get_result::<UserDevice>(conn)?;
+            Ok((restored, true))
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    fn get_user_device(&self, uid: &str, find_device: &str) -> Result<UserDevice, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_devices
+            .filter(user_id.eq(uid))
+            .filter(device_id.eq(find_device))
+            .first::<UserDevice>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Marks an unrevoked device trusted. Returns false if the user has no
+    /// such device.
+    fn trust_user_device(&self, uid: &str, find_device: &str) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            user_devices
+                .filter(user_id.eq(uid))
+                .filter(device_id.eq(find_device))
+                .filter(revoked_at.is_null()),
+        )
+        .set(trusted.eq(true))
+        .execute(&mut conn)
+        .map(|updated| updated > 0)
+        .map_err(AppError::DieselError)
+    }
+
+    /// The user's devices that have not been revoked, most recently seen first.
+    fn get_user_devices(&self, uid: &str) -> Result<Vec<UserDevice>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_devices
+            .filter(user_id.eq(uid))
+            .filter(revoked_at.is_null())
+            .order(last_seen_at.desc())
+            .load::<UserDevice>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Revokes a device and deletes the tokens issued to it. Returns false if
+    /// the user has no such active device.
+    fn revoke_user_device(&self, uid: &str, id_device: uuid::Uuid) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let revoked = diesel::update(
+                user_devices
+                    .find(id_device)
+                    .filter(user_id.eq(uid))
+                    .filter(revoked_at.is_null()),
+            )
+            .set((trusted.eq(false), revoked_at.eq(Utc::now())))
+            .execute(conn)?;
+            if revoked == 0 {
+                return Ok(false);
+            }
+
+            diesel::delete(
+                user_jwt_tokens::table.filter(user_jwt_tokens::device_id.eq(Some(id_device))),
+            )
+            .execute(conn)?;
+            Ok(true)
+        })
+        .map_err(AppError::DieselError)
+    }
+}
@@ -0,0 +1,98 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{NewUserPin, UserPin};
+use crate::models::schema::user_pins::dsl::*;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+pub trait UserPinImpl: DbAccess {
+    fn create_user_pin(&self, new_pin: NewUserPin) -> Result<UserPin, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::insert_into(user_pins)
+            .values(&new_pin)
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    fn get_user_pin(&self, uid: &str) -> Result<UserPin, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_pins
+            .find(uid)
+            .first::<UserPin>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Replaces the PIN, clearing failed attempts and any lock.
+    fn update_user_pin_hash(&self, uid: &str, new_hash: &str) -> Result<UserPin, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(user_pins.find(uid))
+            .set((
+                pin_hash.eq(new_hash),
+                failed_attempts.eq(0),
+                locked_until.eq(None::<DateTime<Utc>>),
+                updated_at.eq(Utc::now()),
+            ))
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Counts an attempt before the PIN is checked, so concurrent guesses
+    /// cannot outrun the limit. The `max_attempts`th in a row locks the PIN
+    /// until `lock_until` and starts the count again; a correct PIN lifts
+    /// that lock through `clear_pin_failures`. Returns the row and whether
+    /// the attempt was counted, which it is not while the PIN is locked.
+    fn claim_pin_attempt(
+        &self,
+        uid: &str,
+        max_attempts: i32,
+        lock_until: DateTime<Utc>,
+    ) -> Result<(UserPin, bool), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let pin = user_pins.find(uid).for_update().first::<UserPin>(conn)?;
+            if pin.locked_until.is_some_and(|until| until > Utc::now()) {
+                return Ok((pin, false));
+            }
+
+            let attempts = pin.failed_attempts + 1;
+            let pin = if attempts < max_attempts {
+                diesel::update(user_pins.find(uid))
+                    .set(failed_attempts.eq(attempts))
+                    .get_result::<UserPin>(conn)?
+            } else {
+                diesel::update(user_pins.find(uid))
+                    .set((failed_attempts.eq(0), locked_until.eq(lock_until)))
+                    .get_result::<UserPin>(conn)?
+            };
+            Ok((pin, true))
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// Clears counted attempts after a correct PIN, unless another attempt
+    /// has locked the PIN since. `own_lock` is the lock this attempt set, if
+    /// it was the one that reached the limit. Returns whether it cleared.
+    fn clear_pin_failures(
+        &self,
+        uid: &str,
+        own_lock: Option<DateTime<Utc>>,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let unlocked = locked_until
+            .is_null()
+            .or(locked_until.le(Utc::now()))
+            .or(locked_until.eq(own_lock));
+        diesel::update(user_pins.find(uid).filter(unlocked))
+            .set((
+                failed_attempts.eq(0),
+                locked_until.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(&mut conn)
+            .map(|updated| updated > 0)
+            .map_err(AppError::DieselError)
+    }
+}
@@ -1,14 +1,28 @@
 use super::db::{AppError, DbAccess};
-use crate::models::models::{NewUserSecurityLog, UserSecurityLog};
+use crate::models::models::{NewUserSecurityLog, SecurityEventType, UserSecurityLog};
 use crate::models::schema::user_security_logs::dsl::*;
-use diesel::dsl::sum;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use diesel::sql_types::Text;
+use diesel::sql_types::{Integer, Nullable, Text, Timestamptz};
 
 diesel::define_sql_function! {
     fn lower(x: Text) -> Text;
 }
 
+// Declared here because `diesel::dsl` exports its own `max` and `sum` twice,
+// which the compiler warns is ambiguous.
+diesel::define_sql_function! {
+    #[aggregate]
+    #[sql_name = "SUM"]
+    fn sum_of(x: Integer) -> Nullable<diesel::sql_types::BigInt>;
+}
+
+diesel::define_sql_function! {
+    #[aggregate]
+    #[sql_name = "MAX"]
+    fn max_of(x: Nullable<Timestamptz>) -> Nullable<Timestamptz>;
+}
+
 pub trait UserSecurityLogsImpl: DbAccess {
     fn create_user_security_log(
         &self,
@@ -34,14 +48,75 @@ pub trait UserSecurityLogsImpl: DbAccess {
             .map_err(AppError::DieselError)
     }
 
-    fn get_user_total_failed_logins(&self, uid: String) -> Result<i64, AppError> {
+    /// Failed attempts by the user since `since`, and when the latest was.
+    fn get_user_failures_since(
+        &self,
+        uid: &str,
+        since: DateTime<Utc>,
+    ) -> Result<(i64, Option<DateTime<Utc>>), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_security_logs
+            .filter(user_id.eq(uid))
+            .filter(failed_login_attempts.gt(0))
+            .filter(created_at.ge(since))
+            .select((sum_of(failed_login_attempts), max_of(created_at)))
+            .first::<(Option<i64>, Option<DateTime<Utc>>)>(&mut conn)
+            .map(|(total, latest)| (total.unwrap_or(0), latest))
+            .map_err(AppError::DieselError)
+    }
+
+    /// Failed attempts from the address since `since`, and when the latest was.
+    fn get_ip_failures_since(
+        &self,
+        ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<(i64, Option<DateTime<Utc>>), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_security_logs
+            .filter(ip_address.eq(ip))
+            .filter(failed_login_attempts.gt(0))
+            .filter(created_at.ge(since))
+            .select((sum_of(failed_login_attempts), max_of(created_at)))
+            .first::<(Option<i64>, Option<DateTime<Utc>>)>(&mut conn)
+            .map(|(total, latest)| (total.unwrap_or(0), latest))
+            .map_err(AppError::DieselError)
+    }
+
+    /// The user's most recent events of one type since `since`, newest first.
+    fn get_recent_events(
+        &self,
+        uid: &str,
+        kind: SecurityEventType,
+        since: DateTime<Utc>,
+        limit_count: i64,
+    ) -> Result<Vec<UserSecurityLog>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_security_logs
+            .filter(user_id.eq(uid))
+            .filter(event_type.eq(kind.as_str()))
+            .filter(created_at.ge(since))
+            .order(created_at.desc())
+            .limit(limit_count)
+            .load::<UserSecurityLog>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// When the user last had an event of the given type, if ever.
+    fn get_last_event_at(
+        &self,
+        uid: &str,
+        kind: SecurityEventType,
+    ) -> Result<Option<DateTime<Utc>>, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
         user_security_logs
             .filter(user_id.eq(uid))
-            .select(sum(failed_login_attempts))
-            .first::<Option<i64>>(&mut conn)
-            .map(|opt| opt.unwrap_or(0))
+            .filter(event_type.eq(kind.as_str()))
+            .select(max_of(created_at))
+            .first::<Option<DateTime<Utc>>>(&mut conn)
             .map_err(AppError::DieselError)
     }
 
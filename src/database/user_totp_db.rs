@@ -0,0 +1,128 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{NewRecoveryCode, NewUserTotp, UserTotp};
+use crate::models::schema::user_recovery_codes;
+use crate::models::schema::user_totp::dsl::*;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+pub trait UserTotpImpl: DbAccess {
+    /// Stores a new secret awaiting confirmation, replacing any earlier one.
+    fn create_pending_totp(&self, new_totp: NewUserTotp) -> Result<UserTotp, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::insert_into(user_totp)
+            .values(&new_totp)
+            .on_conflict(user_id)
+            .do_update()
+            .set((
+                &new_totp,
+                enabled_at.eq(None::<DateTime<Utc>>),
+                last_used_step.eq(None::<i64>),
+                created_at.eq(Utc::now()),
+            ))
+            .get_result(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    fn get_user_totp(&self, uid: &str) -> Result<UserTotp, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_totp
+            .find(uid)
+            .first::<UserTotp>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Turns on a pending secret, confirmed with the code for `step`, and
+    /// replaces the user's recovery codes. Returns false if it was already on.
+    fn enable_user_totp(
+        &self,
+        uid: &str,
+        step: i64,
+        codes: Vec<NewRecoveryCode>,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let enabled = diesel::update(user_totp.find(uid).filter(enabled_at.is_null()))
+                .set((enabled_at.eq(Utc::now()), last_used_step.eq(step)))
+                .execute(conn)?;
+            if enabled == 0 {
+                return Ok(false);
+            }
+
+            replace_codes(conn, uid, codes)?;
+            Ok(true)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// Marks `step` as used. Returns false if it, or a later step, already
+    /// was, which makes each code single use.
+    fn use_totp_step(&self, uid: &str, step: i64) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            user_totp
+                .find(uid)
+                .filter(last_used_step.is_null().or(last_used_step.lt(step))),
+        )
+        .set(last_used_step.eq(step))
+        .execute(&mut conn)
+        .map(|updated| updated > 0)
+        .map_err(AppError::DieselError)
+    }
+
+    /// Turns TOTP off and deletes the secret and recovery codes.
+    fn delete_user_totp(&self, uid: &str) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            diesel::delete(user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(uid)))
+                .execute(conn)?;
+            diesel::delete(user_totp.find(uid)).execute(conn)?;
+            Ok(())
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    fn replace_recovery_codes(
+        &self,
+        uid: &str,
+        codes: Vec<NewRecoveryCode>,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| replace_codes(conn, uid, codes))
+            .map_err(AppError::DieselError)
+    }
+
+    /// Uses up an unused recovery code. Returns false if there is none.
+    fn use_recovery_code(&self, uid: &str, hash: &str) -> Result<bool, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            user_recovery_codes::table
+                .filter(user_recovery_codes::user_id.eq(uid))
+                .filter(user_recovery_codes::code_hash.eq(hash))
+                .filter(user_recovery_codes::used_at.is_null()),
+        )
+        .set(user_recovery_codes::used_at.eq(Utc::now()))
+        .execute(&mut conn)
+        .map(|updated| updated > 0)
+        .map_err(AppError::DieselError)
+    }
+}
+
+fn replace_codes(
+    conn: &mut PgConnection,
+    uid: &str,
+    codes: Vec<NewRecoveryCode>,
+) -> Result<(), diesel::result::Error> {
+    diesel::delete(user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(uid)))
+        .execute(conn)?;
+    diesel::insert_into(user_recovery_codes::table)
+        .values(&codes)
+        .execute(conn)?;
+    Ok(())
+}
@@ -1,7 +1,10 @@
 use super::db::{AppError, DbAccess};
+use super::outbox_db::enqueue_event;
 
-use crate::models::models::{NewUserWallet, UserWallet};
+use crate::models::models::{NewUserWallet, OutboxEventType, User, UserBankAccount, UserWallet};
 use crate::models::schema::user_wallet::dsl::*;
+use crate::models::schema::{user_bank_account, users};
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::sql_types::Text;
 
@@ -9,22 +12,435 @@ diesel::define_sql_function! {
     fn lower(x: Text) -> Text;
 }
 
+/// Clears the primary flag on the user's other active wallets on `on_network`.
+fn unset_primary(
+    conn: &mut PgConnection,
+    owner: &str,
+    on_network: Option<&str>,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(
+        user_wallet
+            .filter(user_id.eq(owner))
+            .filter(network.eq(on_network))
+            .filter(is_primary.eq(true))
+            .filter(archived_at.is_null()),
+    )
+    .set((is_primary.eq(false), updated_at.eq(Utc::now())))
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Archives an active wallet. If it was primary, the owner's newest
+/// remaining wallet on its network takes over.
+fn archive_wallet(
+    conn: &mut PgConnection,
+    wallet: &UserWallet,
+    now: DateTime<Utc>,
+) -> Result<UserWallet, diesel::result::Error> {
+    let archived = diesel::update(user_wallet.find(&wallet.id))
+        .set((
+            archived_at.eq(now),
+            is_primary.eq(false),
+            updated_at.eq(now),
+        ))
+        .get_result::<UserWallet>(conn)?;
+
+    if wallet.is_primary {
+        let successor = user_wallet
+            .select(id)
+            .filter(user_id.eq(&wallet.user_id))
+            .filter(network.eq(&wallet.network))
+            .filter(archived_at.is_null())
+            .order(created_at.desc())
+            .first::<String>(conn)
+            .optional()?;
+        if let Some(successor) = successor {
+            diesel::update(user_wallet.find(successor))
+                .set((is_primary.eq(true), updated_at.eq(now)))
+                .execute(conn)?;
+        }
+    }
+    Ok(archived)
+}
+
 pub trait UserWalletImpl: DbAccess {
-    fn create_user_wallet(&self, wallet: NewUserWallet) -> Result<UserWallet, AppError> {
+    /// Links a wallet, which claims the address until its owner proves it
+    /// with `complete_wallet_challenge`. It becomes the primary one on its
+    /// network if asked, or if the user has no primary wallet there yet.
+    ///
+    /// Only one active wallet may hold an address on a network. Another
+    /// user's unproven claim to it is archived, so nobody can hold an
+    /// address they do not own against its owner.
+    fn create_user_wallet(&self, mut wallet: NewUserWallet) -> Result<UserWallet, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
-        diesel::insert_into(user_wallet)
-            .values(&wallet)
-            .get_result(&mut conn)
+        conn.transaction(|conn| {
+            let now = Utc::now();
+            let unproven = user_wallet
+                .filter(network.eq(&wallet.network))
+                .filter(wallet_address.eq(&wallet.wallet_address))
+                .filter(archived_at.is_null())
+                .filter(verified_at.is_null())
+                .filter(user_id.ne(&wallet.user_id))
+                .for_update()
+                .load::<UserWallet>(conn)?;
+            for claim in &unproven {
+                archive_wallet(conn, claim, now)?;
+            }
+
+            let has_primary: bool = diesel::select(diesel::dsl::exists(
+                user_wallet
+                    .filter(user_id.eq(&wallet.user_id))
+                    .filter(network.eq(&wallet.network))
+                    .filter(is_primary.eq(true))
+                    .filter(archived_at.is_null()),
+            ))
+            .get_result(conn)?;
+            if wallet.is_primary && has_primary {
+                unset_primary(conn, &wallet.user_id, wallet.network.as_deref())?;
+            }
+            wallet.is_primary = wallet.is_primary || !has_primary;
+
+            diesel::insert_into(user_wallet)
+                .values(&wallet)
+                .get_result(conn)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// The user's wallets, primary ones first, then newest first.
+    fn get_wallets_by_user_id(
+        &self,
+        find_user: &str,
+        include_archived: bool,
+    ) -> Result<Vec<UserWallet>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let mut query = user_wallet.filter(user_id.eq(find_user)).into_boxed();
+        if !include_archived {
+            query = query.filter(archived_at.is_null());
+        }
+        query
+            .order((is_primary.desc(), created_at.desc()))
+            .load::<UserWallet>(&mut conn)
             .map_err(AppError::DieselError)
     }
 
-    fn get_wallet_by_user_id(&self, find_user: &str) -> Result<UserWallet, AppError> {
+    fn get_user_wallet(&self, find_user: &str, wallet_id: &str) -> Result<UserWallet, AppError> {
         let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
 
         user_wallet
+            .filter(id.eq(wallet_id))
             .filter(user_id.eq(find_user))
             .get_result::<UserWallet>(&mut conn)
             .map_err(AppError::DieselError)
     }
+
+    /// An active wallet at a normalized address on a network that stops
+    /// `for_user` linking it: their own claim, or anyone's verified wallet.
+    /// Other users' unverified claims do not count, as linking archives
+    /// them.
+    fn get_conflicting_wallet(
+        &self,
+        on_network: &str,
+        address: &str,
+        for_user: &str,
+    ) -> Result<Option<UserWallet>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        user_wallet
+            .filter(network.eq(on_network))
+            .filter(wallet_address.eq(address))
+            .filter(archived_at.is_null())
+            .filter(user_id.eq(for_user).or(verified_at.is_not_null()))
+            .order(verified_at.desc().nulls_last())
+            .first::<UserWallet>(&mut conn)
+            .optional()
+            .map_err(AppError::DieselError)
+    }
+
+    /// The verified active wallets at any of the normalized `addresses`,
+    /// each with its owner and the owner's default bank account. Unproven
+    /// claims never resolve, so linking someone else's address gains
+    /// nothing.
+    fn resolve_wallet_owners(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(UserWallet, User, Option<UserBankAccount>)>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        let wallets = user_wallet
+            .filter(wallet_address.eq_any(addresses))
+            .filter(archived_at.is_null())
+            .filter(verified_at.is_not_null())
+            .load::<UserWallet>(&mut conn)
+            .map_err(AppError::DieselError)?;
+        if wallets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let owner_ids: Vec<&str> = wallets
+            .iter()
+            .map(|wallet| wallet.user_id.as_str())
+            .collect();
+        let owners = users::table
+            .filter(users::id.eq_any(&owner_ids))
+            .load::<User>(&mut conn)
+            .map_err(AppError::DieselError)?;
+        let banks = user_bank_account::table
+            .filter(user_bank_account::user_id.eq_any(&owner_ids))
+            .filter(user_bank_account::is_default.eq(true))
+            .load::<UserBankAccount>(&mut conn)
+            .map_err(AppError::DieselError)?;
+
+        Ok(wallets
+            .into_iter()
+            .filter_map(|wallet| {
+                let owner = owners
+                    .iter()
+                    .find(|user| user.id == wallet.user_id)?
+                    .clone();
+                let bank = banks
+                    .iter()
+                    .find(|bank| bank.user_id == wallet.user_id)
+                    .cloned();
+                Some((wallet, owner, bank))
+            })
+            .collect())
+    }
+
+    /// Replaces any outstanding ownership challenge on an active wallet.
+    fn set_wallet_challenge(
+        &self,
+        find_user: &str,
+        wallet_id: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<UserWallet, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            user_wallet
+                .filter(id.eq(wallet_id))
+                .filter(user_id.eq(find_user))
+                .filter(archived_at.is_null()),
+        )
+        .set((
+            challenge_nonce.eq(nonce),
+            challenge_expires_at.eq(expires_at),
+            updated_at.eq(Utc::now()),
+        ))
+        .get_result::<UserWallet>(&mut conn)
+        .map_err(AppError::DieselError)
+    }
+
+    /// Marks the wallet verified if `nonce` is still its unexpired
+    /// challenge, using the challenge up. Returns `None` if it is not.
+    /// `wallet.linked` is announced now that the owner is known.
+    fn complete_wallet_challenge(
+        &self,
+        wallet_id: &str,
+        nonce: &str,
+    ) -> Result<Option<UserWallet>, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let now = Utc::now();
+            let Some(verified) = diesel::update(
+                user_wallet
+                    .filter(id.eq(wallet_id))
+                    .filter(archived_at.is_null())
+                    .filter(challenge_nonce.eq(nonce))
+                    .filter(challenge_expires_at.gt(now)),
+            )
+            .set((
+                verified_at.eq(now),
+                challenge_nonce.eq(None::<String>),
+                challenge_expires_at.eq(None::<DateTime<Utc>>),
+                updated_at.eq(now),
+            ))
+            .get_result::<UserWallet>(conn)
+            .optional()?
+            else {
+                return Ok(None);
+            };
+
+            enqueue_event(
+                conn,
+                OutboxEventType::WalletLinked,
+                serde_json::json!({
+                    "user_id": verified.user_id,
+                    "wallet_id": verified.id,
+                    "wallet_address": verified.wallet_address,
+                    "network": verified.network,
+                    "verified_at": verified.verified_at,
+                }),
+            )?;
+            Ok(Some(verified))
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    fn update_wallet_label(
+        &self,
+        find_user: &str,
+        wallet_id: &str,
+        new_label: Option<String>,
+    ) -> Result<UserWallet, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::update(
+            user_wallet
+                .filter(id.eq(wallet_id))
+                .filter(user_id.eq(find_user)),
+        )
+        .set((label.eq(new_label), updated_at.eq(Utc::now())))
+        .get_result::<UserWallet>(&mut conn)
+        .map_err(AppError::DieselError)
+    }
+
+    /// Makes an active wallet the primary one on its network.
+    fn set_primary_wallet(&self, find_user: &str, wallet_id: &str) -> Result<UserWallet, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let wallet = user_wallet
+                .filter(id.eq(wallet_id))
+                .filter(user_id.eq(find_user))
+                .filter(archived_at.is_null())
+                .for_update()
+                .get_result::<UserWallet>(conn)?;
+            if wallet.is_primary {
+                return Ok(wallet);
+            }
+
+            unset_primary(conn, find_user, wallet.network.as_deref())?;
+            diesel::update(user_wallet.find(&wallet.id))
+                .set((is_primary.eq(true), updated_at.eq(Utc::now())))
+                .get_result::<UserWallet>(conn)
+        })
+        .map_err(AppError::DieselError)
+    }
+
+    /// Archives an active wallet. If it was primary, the newest remaining
+    /// wallet on its network takes over.
+    fn archive_user_wallet(
+        &self,
+        find_user: &str,
+        wallet_id: &str,
+    ) -> Result<UserWallet, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        conn.transaction(|conn| {
+            let wallet = user_wallet
+                .filter(id.eq(wallet_id))
+                .filter(user_id.eq(find_user))
+                .filter(archived_at.is_null())
+                .for_update()
+                .get_result::<UserWallet>(conn)?;
+
+            archive_wallet(conn, &wallet, Utc::now())
+        })
+        .map_err(AppError::DieselError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db::{Database, MIGRATIONS};
+    use crate::database::user_db::UserImpl;
+    use crate::models::models::NewUser;
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use diesel_migrations::MigrationHarness;
+
+    /// A migrated database at `TEST_DATABASE_URL`, or `None` to skip the
+    /// test when there is none.
+    fn test_db() -> Option<Database> {
+        let url = std::env::var("TEST_DATABASE_URL").ok()?;
+        let pool = Pool::builder()
+            .max_size(2)
+            .build(ConnectionManager::<PgConnection>::new(url))
+            .expect("TEST_DATABASE_URL should be reachable");
+        pool.get()
+            .unwrap()
+            .run_pending_migrations(MIGRATIONS)
+            .expect("migrations should run");
+        Some(Database { pool })
+    }
+
+    fn new_user(db: &Database) -> User {
+        let new_id = uuid::Uuid::new_v4().simple().to_string();
+        db.create_user(NewUser {
+            phone: format!("+234{}", &new_id[..10]),
+            id: new_id,
+            verified: true,
+            role: String::from("user"),
+        })
+        .unwrap()
+    }
+
+    fn link(db: &Database, user: &User, address: &str) -> Result<UserWallet, AppError> {
+        db.create_user_wallet(NewUserWallet {
+            user_id: user.id.clone(),
+            wallet_address: Some(address.to_string()),
+            network: Some(String::from("ethereum")),
+            label: None,
+            is_primary: false,
+        })
+    }
+
+    fn verify(db: &Database, user: &User, wallet: &UserWallet) -> UserWallet {
+        let expires_at = Utc::now() + chrono::TimeDelta::minutes(5);
+        db.set_wallet_challenge(&user.id, &wallet.id, "0x2a", expires_at)
+            .unwrap();
+        db.complete_wallet_challenge(&wallet.id, "0x2a")
+            .unwrap()
+            .expect("the challenge should complete")
+    }
+
+    #[test]
+    fn verified_address_cannot_be_linked_again() {
+        let Some(db) = test_db() else {
+            return;
+        };
+        let address = format!("0x{}", &uuid::Uuid::new_v4().simple().to_string()[..32]);
+        let (owner, other) = (new_user(&db), new_user(&db));
+
+        let wallet = link(&db, &owner, &address).unwrap();
+        verify(&db, &owner, &wallet);
+
+        assert!(matches!(
+            link(&db, &other, &address),
+            Err(AppError::DieselError(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _
+            )))
+        ));
+        let owners = db.resolve_wallet_owners(&[address]).unwrap();
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].1.id, owner.id);
+    }
+
+    #[test]
+    fn linking_archives_an_unproven_claim() {
+        let Some(db) = test_db() else {
+            return;
+        };
+        let address = format!("0x{}", &uuid::Uuid::new_v4().simple().to_string()[..32]);
+        let (squatter, owner) = (new_user(&db), new_user(&db));
+
+        let claim = link(&db, &squatter, &address).unwrap();
+        assert!(
+            db.resolve_wallet_owners(std::slice::from_ref(&address))
+                .unwrap()
+                .is_empty()
+        );
+
+        let wallet = link(&db, &owner, &address).unwrap();
+        let claim = db.get_user_wallet(&squatter.id, &claim.id).unwrap();
+        assert!(claim.archived_at.is_some());
+        verify(&db, &owner, &wallet);
+        assert!(link(&db, &squatter, &address).is_err());
+    }
 }
@@ -0,0 +1,37 @@
+use super::db::{AppError, DbAccess};
+use crate::models::models::{NewWhatsappConversation, WhatsappConversation};
+use crate::models::schema::whatsapp_conversations::dsl::*;
+use chrono::Utc;
+use diesel::prelude::*;
+
+pub trait WhatsappConversationImpl: DbAccess {
+    fn get_whatsapp_conversation(
+        &self,
+        find_phone: &str,
+    ) -> Result<WhatsappConversation, AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        whatsapp_conversations
+            .find(find_phone)
+            .select((state, last_message_id))
+            .first::<WhatsappConversation>(&mut conn)
+            .map_err(AppError::DieselError)
+    }
+
+    /// Stores the conversation's new state, starting it if needed.
+    fn save_whatsapp_conversation(
+        &self,
+        conversation: NewWhatsappConversation,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn().map_err(AppError::DbConnectionError)?;
+
+        diesel::insert_into(whatsapp_conversations)
+            .values(&conversation)
+            .on_conflict(phone)
+            .do_update()
+            .set((&conversation, updated_at.eq(Utc::now())))
+            .execute(&mut conn)
+            .map(|_| ())
+            .map_err(AppError::DieselError)
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    AppState, database::user_db::UserImpl, helpers::totp_helpers::require_totp,
+    models::models::User, services::totp::authenticator::is_admin,
+};
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+
+/// Admin routes are closed unless `auth.admin_api_key` is configured.
+fn has_admin_key(req: &HttpRequest, data: &AppState) -> bool {
+    let Some(expected) = data
+        .env
+        .auth
+        .admin_api_key
+        .as_ref()
+        .filter(|key| !key.is_empty())
+    else {
+        return false;
+    };
+
+    req.headers().get("x-admin-key").is_some_and(|provided| {
+        provided
+            .as_bytes()
+            .ct_eq(expected.expose().as_bytes())
+            .into()
+    })
+}
+
+/// Admin requests need the admin key, the phone of an admin user in
+/// `x-admin-phone` and that user's TOTP code in `x-totp-code`.
+pub fn authorize_admin(req: &HttpRequest, data: &AppState) -> Result<User, HttpResponse> {
+    let admin = admin_user(req, data)?;
+    require_totp(req, data, &admin)?;
+    Ok(admin)
+}
+
+/// The admin user named in `x-admin-phone`, on a request carrying the admin
+/// key. Their second factor is not checked, so this is only for setting it
+/// up.
+pub fn admin_user(req: &HttpRequest, data: &AppState) -> Result<User, HttpResponse> {
+    if !has_admin_key(req, data) {
+        return Err(HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid admin key"
+        })));
+    }
+
+    let admin = req
+        .headers()
+        .get("x-admin-phone")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|phone| data.db.get_user_by_phone(phone).ok())
+        .filter(is_admin);
+    let Some(admin) = admin else {
+        return Err(HttpResponse::Forbidden().json(json!({
+            "status": "error",
+            "message": "Admin user required"
+        })));
+    };
+    Ok(admin)
+}
@@ -1,14 +1,29 @@
 use crate::{
     AppState,
     models::models::{AccountVerificationResponse, Bank, FlutterwaveBankApiResponse},
+    services::telemetry::tracer::inject_trace_context,
 };
 use actix_web::{HttpResponse, web};
+use std::time::{Duration, Instant};
+
+/// Flutterwave's bank list changes a few times a year; refreshing it a few
+/// times a day keeps new banks visible without a call per verification.
+const BANK_LIST_CACHE_KEY: &str = "flutterwave:banks:NG";
+const BANK_LIST_TTL: Duration = Duration::from_secs(6 * 60 * 60);
 
 pub async fn get_bank_code_and_verify_account(
     app_state: &web::Data<AppState>,
     bank_name: String,
     account_number: String,
 ) -> Result<(AccountVerificationResponse, String), HttpResponse> {
+    if !app_state.env.providers.flutterwave.enabled {
+        return Err(HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "success": false,
+            "message": "Bank verification is not enabled",
+            "data": null,
+        })));
+    }
+
     let banks = match fetch_banks_via_flutterwave(&app_state).await {
         Ok(banks) => banks,
         Err(e) => {
@@ -51,16 +66,80 @@ pub async fn get_bank_code_and_verify_account(
     }
 }
 
+#[tracing::instrument(name = "flutterwave.list_banks", skip_all, fields(otel.kind = "client"))]
 pub async fn fetch_banks_via_flutterwave(
     app_state: &web::Data<AppState>,
 ) -> Result<Vec<Bank>, String> {
+    match app_state
+        .cache
+        .get_json::<Vec<Bank>>(BANK_LIST_CACHE_KEY)
+        .await
+    {
+        Ok(Some(banks)) => return Ok(banks),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = ?e, "Bank list cache read failed"),
+    }
+
+    let started = Instant::now();
+    let result = request_banks(app_state).await;
+
+    app_state.metrics.observe_flutterwave_call(
+        "list_banks",
+        result.is_ok(),
+        started.elapsed().as_secs_f64(),
+    );
+
+    if let Ok(banks) = &result
+        && let Err(e) = app_state
+            .cache
+            .set_json(BANK_LIST_CACHE_KEY, banks, BANK_LIST_TTL)
+            .await
+    {
+        tracing::warn!(error = ?e, "Bank list cache write failed");
+    }
+
+    result
+}
+
+#[tracing::instrument(
+    name = "flutterwave.resolve_account",
+    skip_all,
+    fields(otel.kind = "client")
+)]
+pub async fn verify_account_via_flutterwave(
+    app_state: &web::Data<AppState>,
+    account_number: &str,
+    bank_code: &str,
+) -> Result<AccountVerificationResponse, String> {
+    let started = Instant::now();
+    let result = request_account_resolution(app_state, account_number, bank_code).await;
+
+    app_state.metrics.observe_flutterwave_call(
+        "resolve_account",
+        result.is_ok(),
+        started.elapsed().as_secs_f64(),
+    );
+
+    result
+}
+
+fn flutterwave_secret_key(app_state: &web::Data<AppState>) -> Result<&str, String> {
+    app_state
+        .env
+        .providers
+        .flutterwave
+        .secret_key
+        .as_ref()
+        .map(|key| key.expose())
+        .ok_or_else(|| "Flutterwave secret key is not configured".to_string())
+}
+
+async fn request_banks(app_state: &web::Data<AppState>) -> Result<Vec<Bank>, String> {
+    let secret_key = flutterwave_secret_key(app_state)?;
+    let base_url = &app_state.env.providers.flutterwave.base_url;
     let client = reqwest::Client::new();
-    let response = client
-        .get("https://api.flutterwave.com/v3/banks/NG")
-        .header(
-            "Authorization",
-            format!("Bearer {}", app_state.env.flutterwave_secret_key),
-        )
+    let response = inject_trace_context(client.get(format!("{}/banks/NG", base_url)))
+        .header("Authorization", format!("Bearer {}", secret_key))
         .send()
         .await
         .map_err(|e| format!("API request failed: {}", e))?;
@@ -79,26 +158,26 @@ pub async fn fetch_banks_via_flutterwave(
     }
 }
 
-pub async fn verify_account_via_flutterwave(
+async fn request_account_resolution(
     app_state: &web::Data<AppState>,
     account_number: &str,
     bank_code: &str,
 ) -> Result<AccountVerificationResponse, String> {
+    let secret_key = flutterwave_secret_key(app_state)?;
     let client = reqwest::Client::new();
 
-    let url = format!("https://api.flutterwave.com/v3/accounts/resolve");
+    let url = format!(
+        "{}/accounts/resolve",
+        app_state.env.providers.flutterwave.base_url
+    );
 
     let payload = serde_json::json!({
         "account_number": account_number,
         "account_bank": bank_code
     });
 
-    let response = client
-        .post(&url)
-        .header(
-            "Authorization",
-            format!("Bearer {}", app_state.env.flutterwave_secret_key),
-        )
+    let response = inject_trace_context(client.post(&url))
+        .header("Authorization", format!("Bearer {}", secret_key))
         .header("Content-Type", "application/json")
         .json(&payload)
         .send()
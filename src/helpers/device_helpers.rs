@@ -0,0 +1,57 @@
+use crate::{
+    AppState,
+    helpers::request_helpers::client_ip,
+    middleware::security_log::{record_security_event, truncate},
+    models::models::{NewUserDevice, SecurityEventType},
+    services::devices::registry::device_id_from_header,
+};
+use actix_web::{HttpRequest, http::header};
+
+/// Records a completed sign-in from the request's `X-Device-Id` device and
+/// trusts it, before the response says so. A device new to the user also
+/// raises `NewDevice`. Returns whether a device is now trusted.
+pub async fn sign_in_device(req: &HttpRequest, data: &AppState, user_id: &str) -> bool {
+    let Some(device_id) = device_id_from_header(
+        req.headers()
+            .get("x-device-id")
+            .and_then(|value| value.to_str().ok()),
+    ) else {
+        return false;
+    };
+
+    let ip_address = client_ip(req, &data.env.server.trusted_proxies)
+        .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    // Cached, so the security log's lookup for the same request is free.
+    let geo = match data.geo_locator.lookup(&ip_address).await {
+        Ok(geo) => geo,
+        Err(e) => {
+            tracing::warn!(error = %e, "Geolocation lookup failed");
+            Default::default()
+        }
+    };
+
+    let device = NewUserDevice {
+        user_id: user_id.to_string(),
+        device_id,
+        user_agent: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| truncate(value, 512)),
+        ip_address: truncate(&ip_address, 50),
+        city: truncate(geo.city.as_deref().unwrap_or("unknown"), 50),
+        country: truncate(geo.country.as_deref().unwrap_or("unknown"), 50),
+    };
+    match data.devices.sign_in(device) {
+        Ok((device, is_new)) => {
+            if is_new {
+                record_security_event(req, SecurityEventType::NewDevice, Some(user_id.to_string()));
+            }
+            device.trusted
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to record device");
+            false
+        }
+    }
+}
@@ -1 +1,7 @@
+pub mod admin_helpers;
 pub mod bank_helpers;
+pub mod device_helpers;
+pub mod otp_helpers;
+pub mod request_helpers;
+pub mod time_helpers;
+pub mod totp_helpers;
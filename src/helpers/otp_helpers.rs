@@ -0,0 +1,84 @@
+use crate::{
+    AppState,
+    helpers::time_helpers::retry_after_secs,
+    middleware::{lockout::lockout_response, security_log::record_security_event},
+    models::models::{OtpPurpose, SecurityEventType, User},
+    services::otp::issuer::OtpError,
+};
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{StatusCode, header},
+};
+use serde_json::json;
+
+/// Header carrying a one-time code sent to the user for the request's
+/// purpose.
+pub const OTP_CODE_HEADER: &str = "x-otp-code";
+
+pub fn otp_error_response(e: &OtpError) -> HttpResponse {
+    match e {
+        OtpError::Invalid => HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid or expired OTP"
+        })),
+        OtpError::AttemptsExhausted => HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Too many wrong attempts; request a new OTP"
+        })),
+        OtpError::ResendTooSoon { retry_after } => {
+            let retry_after_secs = retry_after_secs(*retry_after);
+
+            HttpResponse::build(StatusCode::TOO_MANY_REQUESTS)
+                .insert_header((header::RETRY_AFTER, retry_after_secs))
+                .json(json!({
+                    "status": "error",
+                    "message": "An OTP was sent recently; wait before requesting another",
+                    "retry_after_secs": retry_after_secs
+                }))
+        }
+        OtpError::Db(_) => {
+            tracing::error!(error = ?e, "Failed to process OTP");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to process OTP"
+            }))
+        }
+    }
+}
+
+/// Demands a code issued for `purpose` before the action goes ahead, using
+/// it up. Wrong codes count towards the account lockout.
+pub fn require_otp(
+    req: &HttpRequest,
+    data: &AppState,
+    user: &User,
+    purpose: OtpPurpose,
+) -> Result<(), HttpResponse> {
+    let Some(code) = req
+        .headers()
+        .get(OTP_CODE_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Err(HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "OTP required",
+            "otp_purpose": purpose.as_str()
+        })));
+    };
+
+    if let Err(rejection) = data.lockout.check_user(user) {
+        return Err(lockout_response(&rejection));
+    }
+    data.otp.verify(&user.id, purpose, code).map_err(|e| {
+        record_otp_error(req, &user.id, &e);
+        otp_error_response(&e)
+    })
+}
+
+/// Records a wrong code as an `OtpFailed` event, which counts towards the
+/// account lockout.
+pub fn record_otp_error(req: &HttpRequest, user_id: &str, e: &OtpError) {
+    if matches!(e, OtpError::Invalid | OtpError::AttemptsExhausted) {
+        record_security_event(req, SecurityEventType::OtpFailed, Some(user_id.to_string()));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{AppState, database::db::AppError};
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::json;
+use std::net::IpAddr;
+use subtle::ConstantTimeEq;
+
+/// The address a request came from, for rate limits, backoff and the
+/// security log. Forwarding headers are only read when the peer is one of
+/// `trusted_proxies`, walking `X-Forwarded-For` from the right past any
+/// other trusted proxies, so a client cannot choose its own address.
+pub fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<IpAddr> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map_while(|hop| hop.trim().parse().ok())
+        .collect();
+    Some(
+        forwarded
+            .into_iter()
+            .rev()
+            .find(|hop| !trusted_proxies.contains(hop))
+            .unwrap_or(peer),
+    )
+}
+
+/// Checks the `x-api-key` header in constant time, giving the 401 to return
+/// if it is wrong.
+pub fn check_api_key(req: &HttpRequest, data: &AppState) -> Result<(), HttpResponse> {
+    let expected_api_key = data.env.auth.api_key.expose();
+
+    match req.headers().get("x-api-key") {
+        Some(provided_key)
+            if bool::from(provided_key.as_bytes().ct_eq(expected_api_key.as_bytes())) =>
+        {
+            Ok(())
+        }
+        Some(_) => Err(HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid API key"
+        }))),
+        None => Err(HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "API key missing"
+        }))),
+    }
+}
+
+/// Checks the `x-indexer-key` header for the internal routes, which are
+/// closed unless `auth.indexer_api_key` is configured.
+pub fn check_indexer_key(req: &HttpRequest, data: &AppState) -> Result<(), HttpResponse> {
+    let expected = data
+        .env
+        .auth
+        .indexer_api_key
+        .as_ref()
+        .filter(|key| !key.is_empty());
+
+    match (expected, req.headers().get("x-indexer-key")) {
+        (Some(expected), Some(provided))
+            if bool::from(provided.as_bytes().ct_eq(expected.expose().as_bytes())) =>
+        {
+            Ok(())
+        }
+        _ => Err(HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid indexer key"
+        }))),
+    }
+}
+
+/// The response for a failed lookup of the user a request is about.
+pub fn user_lookup_error(e: AppError) -> HttpResponse {
+    match e {
+        AppError::DieselError(diesel::result::Error::NotFound) => {
+            HttpResponse::NotFound().json(json!({
+                "status": "error",
+                "message": "User not found"
+            }))
+        }
+        e => {
+            tracing::error!(error = ?e, "Failed to fetch user");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to fetch user"
+            }))
+        }
+    }
+}
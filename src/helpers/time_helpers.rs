@@ -0,0 +1,28 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::Duration;
+
+/// `secs` after `at`, stopping at the latest representable time rather than
+/// overflowing on a huge configured duration.
+pub fn secs_after(at: DateTime<Utc>, secs: u64) -> DateTime<Utc> {
+    at.checked_add_signed(seconds(secs))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// `secs` before `at`, stopping at the earliest representable time.
+pub fn secs_before(at: DateTime<Utc>, secs: u64) -> DateTime<Utc> {
+    at.checked_sub_signed(seconds(secs))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Whole seconds for a `Retry-After` header, rounded up so clients never
+/// retry a moment too early.
+pub fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+fn seconds(secs: u64) -> TimeDelta {
+    i64::try_from(secs)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .unwrap_or(TimeDelta::MAX)
+}
@@ -0,0 +1,112 @@
+use crate::{
+    AppState,
+    middleware::{lockout::lockout_response, security_log::record_security_event},
+    models::models::{SecurityEventType, User},
+    services::totp::authenticator::{TotpCheck, TotpError},
+};
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::json;
+
+/// Header carrying the authenticator code, or a recovery code, for
+/// sensitive requests.
+pub const TOTP_CODE_HEADER: &str = "x-totp-code";
+
+pub fn totp_error_response(e: &TotpError) -> HttpResponse {
+    match e {
+        TotpError::Unavailable => HttpResponse::ServiceUnavailable().json(json!({
+            "status": "error",
+            "message": "Two-factor authentication is not configured"
+        })),
+        TotpError::AlreadyEnabled => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": "Two-factor authentication is already enabled"
+        })),
+        TotpError::NotEnrolled => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "Two-factor authentication is not enabled"
+        })),
+        TotpError::EnrolmentRequired => HttpResponse::Forbidden().json(json!({
+            "status": "error",
+            "message": "Two-factor authentication must be enabled for this account"
+        })),
+        TotpError::CodeRequired => HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Two-factor code required"
+        })),
+        TotpError::InvalidCode => HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid two-factor code"
+        })),
+        TotpError::Decrypt | TotpError::Secret(_) | TotpError::Db(_) => {
+            tracing::error!(error = ?e, "Two-factor authentication failed");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Two-factor authentication failed"
+            }))
+        }
+    }
+}
+
+/// Starts enrolment, returning the secret for the authenticator app.
+pub fn enrol_totp(data: &AppState, user: &User) -> HttpResponse {
+    match data.totp.enrol(user) {
+        Ok(enrolment) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "data": {
+                "secret": enrolment.secret,
+                "otpauth_uri": enrolment.otpauth_uri
+            }
+        })),
+        Err(e) => totp_error_response(&e),
+    }
+}
+
+/// Turns TOTP on with a code from the app and returns the recovery codes,
+/// which are not shown again.
+pub fn confirm_totp(req: &HttpRequest, data: &AppState, user: &User, code: &str) -> HttpResponse {
+    match data.totp.confirm(user, code) {
+        Ok(recovery_codes) => {
+            record_security_event(req, SecurityEventType::TotpEnabled, Some(user.id.clone()));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Two-factor authentication enabled",
+                "data": { "recovery_codes": recovery_codes }
+            }))
+        }
+        Err(e) => totp_error_response(&e),
+    }
+}
+
+/// Demands the second factor from users who have TOTP turned on, and from
+/// every admin, before a sensitive action. Others pass straight through.
+/// Wrong codes count towards the account lockout.
+pub fn require_totp(req: &HttpRequest, data: &AppState, user: &User) -> Result<(), HttpResponse> {
+    let code = req
+        .headers()
+        .get(TOTP_CODE_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    if code.is_some()
+        && let Err(rejection) = data.lockout.check_user(user)
+    {
+        return Err(lockout_response(&rejection));
+    }
+
+    match data.totp.check(user, code) {
+        Ok(TotpCheck::NotRequired | TotpCheck::Code) => Ok(()),
+        Ok(TotpCheck::RecoveryCode) => {
+            record_security_event(
+                req,
+                SecurityEventType::RecoveryCodeUsed,
+                Some(user.id.clone()),
+            );
+            Ok(())
+        }
+        Err(e) => {
+            if matches!(e, TotpError::InvalidCode) {
+                record_security_event(req, SecurityEventType::TotpFailed, Some(user.id.clone()));
+            }
+            Err(totp_error_response(&e))
+        }
+    }
+}
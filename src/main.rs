@@ -7,83 +7,246 @@ mod models;
 mod routes;
 mod services;
 
-use actix_cors::Cors;
-use actix_web::{
-    App, HttpServer,
-    http::header,
-    middleware::{Logger, from_fn},
-    web,
+use actix_web::{App, HttpServer, middleware::from_fn, web};
+use config::{
+    config::{Config, ConfigError},
+    config_scope,
+    cors::build_cors,
 };
-use config::{config::Config, config_scope};
 use database::db::Database;
 use dotenv::dotenv;
+use services::cache::store::Cache;
+use services::devices::registry::DeviceRegistry;
+use services::flutterwave::webhooks::FlutterwaveWebhooks;
 use services::geolocation::geolocator::GeoLocator;
+use services::lockout::policy::LockoutPolicy;
+use services::metrics::collector::Metrics;
+use services::notifications::{notifier::Notifier, templates::TemplateRegistry};
+use services::otp::issuer::OtpIssuer;
+use services::outbox::dispatcher::OutboxDispatcher;
+use services::pin::manager::PinManager;
+use services::rate_limit::limiter::RateLimiter;
+use services::risk::engine::{LogStepUp, RiskEngine};
+use services::telemetry::{logging::init_logging, tracer::init_tracer_provider};
+use services::totp::authenticator::TotpAuthenticator;
+use services::wallets::ownership::WalletOwnership;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::middleware::security_log::security_logger_middleware;
+use crate::middleware::{
+    lockout::lockout_middleware, metrics::metrics_middleware, rate_limit::rate_limit_middleware,
+    request_id::request_id_middleware, security_log::security_logger_middleware,
+};
 
 pub struct AppState {
     db: Database,
     env: Config,
-    // pub redis_pool: RedisPool,
+    pub cache: Cache,
     pub geo_locator: GeoLocator,
+    pub metrics: Metrics,
+    pub rate_limiter: RateLimiter,
+    pub lockout: LockoutPolicy,
+    pub risk: RiskEngine,
+    pub devices: DeviceRegistry,
+    pub notifier: Notifier,
+    pub otp: OtpIssuer,
+    pub pins: PinManager,
+    pub totp: TotpAuthenticator,
+    pub flutterwave: FlutterwaveWebhooks,
+    pub wallets: WalletOwnership,
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    if std::env::var_os("RUST_LOG").is_none() {
-        unsafe {
-            std::env::set_var("RUST_LOG", "actix_web=trace");
+    // Printed before validation so a broken configuration can be inspected.
+    if std::env::args().any(|arg| arg == "--print-config") {
+        let config = match Config::merged() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        match config.to_redacted_toml() {
+            Ok(dump) => println!("{}", dump),
+            Err(e) => {
+                eprintln!("Failed to render configuration: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            eprintln!("{}", ConfigError::Invalid(problems));
+            std::process::exit(1);
         }
+        return Ok(());
     }
 
-    env_logger::init();
-    let bind_address = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    log::info!("Starting Server......");
+    let tracer_provider = match init_tracer_provider(config.telemetry.otlp_endpoint.as_deref()) {
+        Ok(provider) => provider,
+        Err(e) => {
+            eprintln!("Failed to initialize tracing: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    init_logging(&tracer_provider);
 
-    let config = Config::init();
+    tracing::info!("Starting Server......");
 
-    let db = match database::db::Database::new() {
+    let metrics = match Metrics::new() {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to initialize metrics");
+            std::process::exit(1);
+        }
+    };
+
+    let db = match database::db::Database::new(
+        &config.database,
+        config.server.is_dev(),
+        Box::new(metrics.pool_event_handler()),
+    ) {
         Ok(db) => db,
         Err(e) => {
-            eprintln!("Failed to initialize DB: {:?}", e);
+            tracing::error!(error = ?e, "Failed to initialize DB");
+            std::process::exit(1);
+        }
+    };
+    let cache = match Cache::connect(&config.redis).await {
+        Ok(cache) => cache,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to connect to Redis");
             std::process::exit(1);
         }
     };
-    let geo_locator = GeoLocator::new(config.ip_info_token.clone());
-    let port: u16 = config.port.parse().expect("PORT must be i16 type");
+
+    let rate_limiter = RateLimiter::new(&config.rate_limit, cache.clone());
+    let geo_locator = match GeoLocator::new(&config.geolocation, cache.clone(), metrics.clone()) {
+        Ok(geo_locator) => geo_locator,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to initialize geolocation");
+            std::process::exit(1);
+        }
+    };
+    let lockout = LockoutPolicy::new(&config.lockout, db.clone());
+    actix_web::rt::spawn({
+        let lockout = lockout.clone();
+        async move {
+            let mut interval = actix_web::rt::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = lockout.unlock_expired() {
+                    tracing::error!(error = ?e, "Failed to lift expired account locks");
+                }
+            }
+        }
+    });
+
+    let risk = RiskEngine::new(&config.risk, db.clone(), Arc::new(LogStepUp));
+    // Reloaded on change in development only.
+    let templates = match TemplateRegistry::load(
+        Path::new(&config.notifications.templates_dir),
+        config.server.is_dev(),
+    ) {
+        Ok(templates) => templates,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load message templates");
+            std::process::exit(1);
+        }
+    };
+    let notifier = match Notifier::new(&config.notifications, templates, db.clone()) {
+        Ok(notifier) => notifier,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to initialize notification channels");
+            std::process::exit(1);
+        }
+    };
+    let devices = DeviceRegistry::new(db.clone(), Arc::new(notifier.clone()));
+    let otp = OtpIssuer::new(
+        &config.otp,
+        config.auth.jwt_secret.expose(),
+        db.clone(),
+        Arc::new(notifier.clone()),
+    );
+    let pins = PinManager::new(&config.pin, db.clone(), otp.clone());
+    let totp = TotpAuthenticator::new(&config.totp, db.clone());
+    let flutterwave = FlutterwaveWebhooks::new(db.clone());
+    let wallets = match WalletOwnership::new(&config.wallets, db.clone()) {
+        Ok(wallets) => wallets,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to initialize wallet verification");
+            std::process::exit(1);
+        }
+    };
+    match OutboxDispatcher::new(&config.outbox, db.clone()) {
+        Ok(dispatcher) => {
+            actix_web::rt::spawn(dispatcher.run());
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to initialize outbox dispatcher");
+            std::process::exit(1);
+        }
+    }
+
+    let port = config.server.port;
+    let bind_address = config.server.host.clone();
+    let cors_config = config.cors.clone();
 
     let app_state = web::Data::new(AppState {
         db: db.clone(),
         env: config.clone(),
+        cache,
         geo_locator: geo_locator.clone(),
+        metrics: metrics.clone(),
+        rate_limiter,
+        lockout,
+        risk,
+        devices,
+        notifier,
+        otp,
+        pins,
+        totp,
+        flutterwave,
+        wallets,
     });
 
-    log::info!("Server is running on port: {}", port);
-
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:3000")
-            .allowed_origin("http://127.0.0.1:3000")
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-            .allowed_headers(vec![
-                header::CONTENT_TYPE,
-                header::AUTHORIZATION,
-                header::ACCEPT,
-            ])
-            .supports_credentials()
-            .max_age(3600);
+    tracing::info!(port, "Server is running");
+
+    let server = HttpServer::new(move || {
+        let cors = build_cors(&cors_config);
 
         App::new()
             .app_data(app_state.clone())
             .configure(config_scope::config)
-            .wrap(cors)
-            .wrap(Logger::default())
+            .wrap(from_fn(lockout_middleware))
             .wrap(from_fn(security_logger_middleware))
+            .wrap(from_fn(rate_limit_middleware))
+            // Wraps the rate limiter so browsers can read `Retry-After` on
+            // its 429s.
+            .wrap(cors)
+            .wrap(from_fn(metrics_middleware))
+            .wrap(from_fn(request_id_middleware))
     })
     .bind((bind_address, port))?
     .run()
-    .await
+    .await;
+
+    // Flush any spans still sitting in the batch exporter.
+    if let Err(e) = tracer_provider.shutdown() {
+        eprintln!("Failed to shut down tracer provider: {:?}", e);
+    }
+
+    server
 }
@@ -0,0 +1,65 @@
+use crate::{
+    AppState,
+    helpers::{request_helpers::client_ip, time_helpers::retry_after_secs},
+    services::lockout::policy::LockoutRejection,
+};
+use actix_web::{
+    Error, HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{StatusCode, header},
+    middleware::Next,
+    web,
+};
+use serde_json::json;
+
+/// Turns away login and OTP attempts from an address that is backing off
+/// after repeated failures. Per-user locks are checked by the handlers,
+/// which know who the attempt is for.
+pub async fn lockout_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let path = req.path();
+    let is_attempt = path.contains("/auth") || path.contains("/otp");
+
+    let app_data = req.app_data::<web::Data<AppState>>().cloned();
+    if let (true, Some(app_data)) = (is_attempt, app_data) {
+        let ip = client_ip(req.request(), &app_data.env.server.trusted_proxies)
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+
+        if let Err(rejection) = app_data.lockout.check_ip(&ip) {
+            tracing::warn!(ip = %ip, ?rejection, "Attempt rejected by lockout policy");
+            let response = lockout_response(&rejection);
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// 423 for a locked account, 429 while backing off; both say when to retry.
+pub fn lockout_response(rejection: &LockoutRejection) -> HttpResponse {
+    let (status, message, retry_after) = match rejection {
+        LockoutRejection::Locked { retry_after } => (
+            StatusCode::LOCKED,
+            "Account temporarily locked after too many failed attempts",
+            retry_after,
+        ),
+        LockoutRejection::Backoff { retry_after } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many failed attempts, please wait before trying again",
+            retry_after,
+        ),
+    };
+
+    let retry_after_secs = retry_after_secs(*retry_after);
+
+    HttpResponse::build(status)
+        .insert_header((header::RETRY_AFTER, retry_after_secs))
+        .json(json!({
+            "status": "error",
+            "message": message,
+            "retry_after_secs": retry_after_secs
+        }))
+}
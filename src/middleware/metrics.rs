@@ -0,0 +1,37 @@
+use crate::AppState;
+use actix_web::{
+    Error,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web,
+};
+use std::time::Instant;
+
+pub async fn metrics_middleware(
+    req: ServiceRequest,
+    next: Next<impl actix_web::body::MessageBody>,
+) -> Result<ServiceResponse<impl actix_web::body::MessageBody>, Error> {
+    let app_data = req.app_data::<web::Data<AppState>>().cloned();
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let response = next.call(req).await?;
+
+    if let Some(app_data) = app_data {
+        // Label by the matched route pattern rather than the raw path so
+        // ids in the URL don't blow up the series count.
+        let route = response
+            .request()
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+
+        app_data.metrics.observe_http_request(
+            &method,
+            &route,
+            response.status().as_u16(),
+            started.elapsed().as_secs_f64(),
+        );
+    }
+
+    Ok(response)
+}
@@ -1 +1,5 @@
+pub mod lockout;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
 pub mod security_log;
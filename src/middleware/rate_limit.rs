@@ -0,0 +1,136 @@
+use crate::{
+    AppState, config::config::RateLimitKey, helpers::request_helpers::client_ip,
+    services::rate_limit::limiter::RateLimitDecision,
+};
+use actix_web::{
+    Error, HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    web,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+pub async fn rate_limit_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(app_data) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let limiter = &app_data.rate_limiter;
+    let rules = limiter.matching_rules(req.method().as_str(), req.path());
+    if rules.is_empty() {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let phone = if rules.iter().any(|rule| rule.key == RateLimitKey::Phone) {
+        extract_phone(&mut req).await
+    } else {
+        None
+    };
+
+    // Headers on a successful response describe whichever rule is closest to
+    // its limit.
+    let mut tightest: Option<RateLimitDecision> = None;
+
+    for rule in rules {
+        let key = match rule.key {
+            RateLimitKey::Ip => client_ip(req.request(), &app_data.env.server.trusted_proxies)
+                .map(|ip| ip.to_string()),
+            RateLimitKey::Phone => phone.clone(),
+            RateLimitKey::ApiClient => api_client_fingerprint(req.headers()),
+        };
+        let Some(key) = key else {
+            continue;
+        };
+
+        let decision = limiter.check(rule, &key).await;
+
+        if !decision.allowed {
+            tracing::warn!(
+                path = %rule.path,
+                key_type = ?rule.key,
+                "Rate limit exceeded"
+            );
+
+            let mut response = HttpResponse::TooManyRequests();
+            response.insert_header((header::RETRY_AFTER, decision.reset_secs));
+            let mut response = response.json(json!({
+                "status": "error",
+                "message": "Too many requests, please try again later"
+            }));
+            insert_rate_limit_headers(response.headers_mut(), &decision);
+
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+
+        if tightest.is_none_or(|current| decision.remaining < current.remaining) {
+            tightest = Some(decision);
+        }
+    }
+
+    let mut response = next.call(req).await?;
+    if let Some(decision) = tightest {
+        insert_rate_limit_headers(response.headers_mut(), &decision);
+    }
+
+    Ok(response.map_into_left_body())
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(decision.reset_secs));
+}
+
+/// Partners share API keys today, so the key is hashed rather than used (or
+/// stored in Redis) verbatim.
+fn api_client_fingerprint(headers: &HeaderMap) -> Option<String> {
+    let api_key = headers.get("x-api-key")?.as_bytes();
+    let digest = Sha256::digest(api_key);
+
+    Some(hex::encode(&digest[..8]))
+}
+
+/// Finds the phone number in the query string or, for JSON requests, the
+/// body. The body is buffered and put back so the handler can still read it.
+async fn extract_phone(req: &mut ServiceRequest) -> Option<String> {
+    let from_query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get("phone").cloned());
+    if let Some(phone) = from_query {
+        return normalize_phone(&phone);
+    }
+
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return None;
+    }
+
+    let body = req.extract::<web::Bytes>().await.ok()?;
+    req.set_payload(Payload::from(body.clone()));
+
+    let value: serde_json::Value = serde_json::from_slice(&body).ok()?;
+    value.get("phone")?.as_str().and_then(normalize_phone)
+}
+
+/// Digits only: a `+` in an unencoded query string arrives as a space, so
+/// keeping it would split one number across two counters.
+fn normalize_phone(phone: &str) -> Option<String> {
+    let normalized: String = phone.chars().filter(char::is_ascii_digit).collect();
+
+    (!normalized.is_empty()).then_some(normalized)
+}
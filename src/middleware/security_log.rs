@@ -1,26 +1,104 @@
 use crate::database::user_security_log_db::UserSecurityLogsImpl;
-use crate::models::models::NewUserSecurityLog;
+use crate::helpers::request_helpers::client_ip;
+use crate::models::models::{NewUserSecurityLog, SecurityEventType};
+use crate::services::geolocation::provider::GeoLocation;
+use crate::services::notifications::templates::AccountLockedMessage;
+use crate::services::risk::engine::RiskAssessment;
 use crate::{AppState, models::models::TokenClaims};
 use actix_web::{
-    Error,
+    Error, HttpMessage, HttpRequest,
     dev::{ServiceRequest, ServiceResponse},
     http::header,
     middleware::Next,
     web,
 };
 use chrono::Utc;
+use subtle::ConstantTimeEq;
+use tracing::Instrument;
+
+/// A security event raised by a handler, picked up by
+/// `security_logger_middleware` once the response is ready.
+#[derive(Debug, Clone)]
+struct SecurityEvent {
+    event_type: SecurityEventType,
+    user_id: Option<String>,
+    /// Raised for a WhatsApp message. Those reach us from Meta's servers, so
+    /// the request's address and user agent say nothing about the user.
+    via_whatsapp: bool,
+}
+
+/// Stored as the address of events from WhatsApp messages, like `system`
+/// for ones the server raises itself.
+const WHATSAPP_ADDRESS: &str = "whatsapp";
+
+/// The events recorded for one request, in the order they happened.
+#[derive(Debug, Clone, Default)]
+struct SecurityEvents(Vec<SecurityEvent>);
+
+/// Records a security event for the current request. Handlers call this when
+/// they know what happened (and to whom) better than the path and status can
+/// tell. A request can raise several, e.g. a recovery code used to authorise
+/// a PIN change; each is written.
+pub fn record_security_event(
+    req: &HttpRequest,
+    event_type: SecurityEventType,
+    user_id: Option<String>,
+) {
+    push_event(req, event_type, user_id, false);
+}
+
+/// Records a security event for a WhatsApp message handled by the current
+/// request. It is stored without an address or location, so Meta's servers
+/// are not geolocated as the user or counted as a failing address.
+pub fn record_whatsapp_event(
+    req: &HttpRequest,
+    event_type: SecurityEventType,
+    user_id: Option<String>,
+) {
+    push_event(req, event_type, user_id, true);
+}
+
+fn push_event(
+    req: &HttpRequest,
+    event_type: SecurityEventType,
+    user_id: Option<String>,
+    via_whatsapp: bool,
+) {
+    req.extensions_mut()
+        .get_or_insert_with(SecurityEvents::default)
+        .0
+        .push(SecurityEvent {
+            event_type,
+            user_id,
+            via_whatsapp,
+        });
+}
 
 pub async fn security_logger_middleware(
     req: ServiceRequest,
     next: Next<impl actix_web::body::MessageBody>,
 ) -> Result<ServiceResponse<impl actix_web::body::MessageBody>, Error> {
     let app_data = req.app_data::<actix_web::web::Data<AppState>>().cloned();
-    let user_id = extract_user_id_from_jwt(&req, app_data.as_ref());
-    let ip_address = req
-        .connection_info()
-        .realip_remote_addr()
-        .unwrap_or("unknown")
-        .to_string();
+    let jwt_user_id = extract_user_id_from_jwt(&req, app_data.as_ref());
+    let trusted_proxies = app_data
+        .as_ref()
+        .map(|app_data| app_data.env.server.trusted_proxies.as_slice())
+        .unwrap_or_default();
+    let ip_address = client_ip(req.request(), trusted_proxies)
+        .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| truncate(value, 512));
+    let api_key_rejected = app_data.as_ref().is_some_and(|app_data| {
+        req.headers().get("x-api-key").is_none_or(|key| {
+            !bool::from(
+                key.as_bytes()
+                    .ct_eq(app_data.env.auth.api_key.expose().as_bytes()),
+            )
+        })
+    });
 
     let path = req.path().to_string();
     let method = req.method().to_string();
@@ -28,59 +106,187 @@ pub async fn security_logger_middleware(
     let response = next.call(req).await?;
     let status = response.status().as_u16();
 
-    if let Some(app_data) = app_data {
-        let is_login_failure = (path.contains("/auth") || path.contains("/validate-otp"))
-            && (status == 401 || status == 403);
+    let Some(app_data) = app_data else {
+        return Ok(response);
+    };
 
-        let failed_login_attempts = if is_login_failure { 1 } else { 0 };
+    let recorded = response
+        .request()
+        .extensions()
+        .get::<SecurityEvents>()
+        .map(|events| events.0.clone())
+        .unwrap_or_default();
+    let events: Vec<SecurityEvent> = if recorded.is_empty() {
+        infer_event(&path, status, api_key_rejected)
+            .map(|event_type| SecurityEvent {
+                event_type,
+                user_id: None,
+                via_whatsapp: false,
+            })
+            .into_iter()
+            .collect()
+    } else {
+        recorded
+    };
+    if events.is_empty() {
+        return Ok(response);
+    }
+    let jwt_user_id = jwt_user_id.map(|id| id.simple().to_string());
 
-        let geo = app_data
-            .geo_locator
-            .lookup(&ip_address)
-            .await
-            .unwrap_or_default();
-        let city = geo.city.unwrap_or_else(|| "unknown".into());
-        let country = geo.country.unwrap_or_else(|| "unknown".into());
+    // Geolocation and the write happen in the spawned task so they never
+    // delay the response.
+    actix_web::rt::spawn({
+        let db = app_data.db.clone();
+        let metrics = app_data.metrics.clone();
+        let geo_locator = app_data.geo_locator.clone();
+        let lockout = app_data.lockout.clone();
+        let risk = app_data.risk.clone();
+        let notifier = app_data.notifier.clone();
 
-        actix_web::rt::spawn({
-            let db = app_data.db.clone();
+        async move {
+            let delete_on_users = method == "DELETE" && path.contains("/users");
 
-            async move {
-                let mut flagged_for_review = false;
+            let no_geo = GeoLocation::default();
+            let geo = if events.iter().all(|event| event.via_whatsapp) {
+                GeoLocation::default()
+            } else {
+                match geo_locator.lookup(&ip_address).await {
+                    Ok(geo) => geo,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Geolocation lookup failed");
+                        Default::default()
+                    }
+                }
+            };
 
-                if is_login_failure {
-                    let failures =
-                        db.get_user_total_failed_logins(user_id.clone().unwrap().to_string());
+            for event in events {
+                let user_id = event.user_id.or_else(|| jwt_user_id.clone());
+                let is_failure = event.event_type.is_failure();
+                let mut flagged_for_review = delete_on_users;
+                let created_at = Utc::now();
+                let (ip_address, user_agent, geo) = if event.via_whatsapp {
+                    (WHATSAPP_ADDRESS, None, &no_geo)
+                } else {
+                    (ip_address.as_str(), user_agent.clone(), &geo)
+                };
 
-                    if let Ok(recent_failures) = failures {
-                        if recent_failures + failed_login_attempts >= 3 {
-                            flagged_for_review = true;
-                        }
+                // Assessed before the login is written so it is not compared
+                // with itself.
+                let mut assessment = RiskAssessment::default();
+                if event.event_type == SecurityEventType::LoginSuccess
+                    && let Some(user_id) = &user_id
+                {
+                    match risk.assess(user_id, geo, created_at) {
+                        Ok(result) => assessment = result,
+                        Err(e) => tracing::warn!(error = ?e, "Failed to assess login risk"),
+                    }
+                }
+                flagged_for_review |= assessment.is_flagged();
 
-                        if method == "DELETE" && path.clone().contains("/users") {
-                            flagged_for_review = true;
-                        }
+                let new_log = NewUserSecurityLog {
+                    user_id,
+                    ip_address: truncate(ip_address, 50),
+                    city: truncate(geo.city.as_deref().unwrap_or("unknown"), 50),
+                    country: truncate(geo.country.as_deref().unwrap_or("unknown"), 50),
+                    failed_login_attempts: i32::from(is_failure),
+                    flagged_for_review,
+                    created_at,
+                    event_type: event.event_type.as_str().to_string(),
+                    user_agent,
+                    path: truncate(&path, 255),
+                    method: method.clone(),
+                    status: i32::from(status),
+                    latitude: geo.latitude,
+                    longitude: geo.longitude,
+                    asn: geo.asn.map(i64::from),
+                    flag_reason: assessment.reason_code(),
+                };
+
+                // A lost log line must not also skip the step-up and the
+                // lockout below.
+                if let Err(e) = db.create_user_security_log(new_log.clone()) {
+                    tracing::error!(error = ?e, "Failed to write security log");
+                    metrics.security_log_write_failures_total.inc();
+                }
+
+                if let Some(user_id) = &new_log.user_id {
+                    risk.step_up(user_id, &assessment);
+                }
 
-                        let new_log = NewUserSecurityLog {
-                            user_id: user_id.unwrap().to_string().clone(),
-                            ip_address: ip_address.clone(),
-                            city: city.clone(),
-                            country: country.clone(),
-                            failed_login_attempts: failed_login_attempts as i32,
-                            flagged_for_review: flagged_for_review.clone(),
+                // After the failure is written, so it counts towards the lock.
+                let Some(user_id) = new_log.user_id.as_deref().filter(|_| is_failure) else {
+                    continue;
+                };
+                match lockout.after_failure(user_id) {
+                    Ok(Some(until)) => {
+                        notifier.dispatch_to(
+                            user_id,
+                            AccountLockedMessage {
+                                minutes: (until - Utc::now()).num_minutes().max(1),
+                                until: until.format("%H:%M UTC").to_string(),
+                            },
+                        );
+                        let locked = NewUserSecurityLog {
+                            event_type: SecurityEventType::AccountLocked.as_str().to_string(),
+                            failed_login_attempts: 0,
+                            flagged_for_review: true,
                             created_at: Utc::now(),
+                            flag_reason: Some("lockout".to_string()),
+                            ..new_log
                         };
-
-                        let _ = db.create_user_security_log(new_log);
+                        if let Err(e) = db.create_user_security_log(locked) {
+                            tracing::error!(error = ?e, "Failed to write security log");
+                            metrics.security_log_write_failures_total.inc();
+                        }
                     }
+                    Ok(None) => {}
+                    Err(e) => tracing::error!(error = ?e, "Failed to apply lockout policy"),
                 }
             }
-        });
-    }
+        }
+        .in_current_span()
+    });
 
     Ok(response)
 }
 
+/// The event for a request no handler described, judged from the path and
+/// status alone. Most requests are not security events.
+fn infer_event(path: &str, status: u16, api_key_rejected: bool) -> Option<SecurityEventType> {
+    let rejected = status == 401 || status == 403;
+
+    if status == 401 && api_key_rejected {
+        return Some(SecurityEventType::ApiKeyRejected);
+    }
+
+    if path.contains("/validate-otp") || path.contains("/otp") {
+        return rejected.then_some(SecurityEventType::OtpFailed);
+    }
+
+    // A successful response is not necessarily a login: it may still be
+    // waiting for an OTP. Handlers record `LoginSuccess` themselves.
+    if path.contains("/auth") && rejected {
+        return Some(SecurityEventType::LoginFailure);
+    }
+
+    None
+}
+
+/// Cuts `value` to at most `max` bytes on a character boundary, to fit the
+/// column it is stored in.
+pub fn truncate(value: &str, max: usize) -> String {
+    if value.len() <= max {
+        return value.to_string();
+    }
+
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    value[..end].to_string()
+}
+
 fn extract_user_id_from_jwt(
     req: &ServiceRequest,
     app_state: Option<&web::Data<AppState>>,
@@ -91,13 +297,17 @@ fn extract_user_id_from_jwt(
         .or_else(|| {
             req.headers()
                 .get(header::AUTHORIZATION)
-                .map(|h| h.to_str().unwrap_or_default().split_at(7).1.to_string())
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.strip_prefix("Bearer "))
+                .map(str::to_string)
         });
 
     if let (Some(token), Some(app_state)) = (token, app_state) {
         match jsonwebtoken::decode::<TokenClaims>(
             &token,
-            &jsonwebtoken::DecodingKey::from_secret(app_state.env.jwt_secret.as_ref()),
+            &jsonwebtoken::DecodingKey::from_secret(
+                app_state.env.auth.jwt_secret.expose().as_ref(),
+            ),
             &jsonwebtoken::Validation::default(),
         ) {
             Ok(data) => {
@@ -14,6 +14,22 @@ pub struct User {
     pub role: String,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
+    /// Set while the account is locked after repeated failed attempts.
+    #[serde(rename = "lockedUntil")]
+    pub locked_until: Option<DateTime<Utc>>,
+    pub email: Option<String>,
+    /// Preferred `ChannelKind` for OTPs and alerts, stored as its `as_str`.
+    #[serde(rename = "notificationChannel")]
+    pub notification_channel: String,
+    /// `Language` of the messages sent to the user, stored as its `as_str`.
+    #[serde(rename = "preferredLanguage")]
+    pub preferred_language: String,
+    /// Identity check progress: `none`, `pending`, `verified` or `rejected`.
+    #[serde(rename = "kycStatus")]
+    pub kyc_status: String,
+    /// A new email address, used once the user confirms a code sent to it.
+    #[serde(rename = "pendingEmail")]
+    pub pending_email: Option<String>,
 }
 
 #[allow(non_snake_case)]
@@ -32,12 +48,23 @@ pub struct UserWallet {
     pub id: String,
     pub user_id: String, //foreign key ref
     pub wallet_address: Option<String>,
-    pub network_used_last: Option<String>,
+    pub network: Option<String>,
     pub controller_info: Option<String>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
+    pub label: Option<String>,
+    /// The wallet payouts and deposits on its network default to.
+    pub is_primary: bool,
+    /// Archived wallets are kept for history but no longer resolve.
+    pub archived_at: Option<DateTime<Utc>>,
+    /// When the user proved they hold the wallet's key by signing a
+    /// challenge.
+    pub verified_at: Option<DateTime<Utc>>,
+    /// The outstanding ownership challenge, until it is answered.
+    pub challenge_nonce: Option<String>,
+    pub challenge_expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, AsChangeset, Insertable)]
@@ -45,14 +72,18 @@ pub struct UserWallet {
 pub struct NewUserWallet {
     pub user_id: String,
     pub wallet_address: Option<String>,
-    pub network_used_last: Option<String>,
+    pub network: Option<String>,
+    pub label: Option<String>,
+    /// Made primary anyway when the user has no primary wallet on the
+    /// network.
+    pub is_primary: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Queryable, AsChangeset, Insertable)]
 #[diesel(table_name=crate::models::schema::user_security_logs)]
 pub struct UserSecurityLog {
     pub log_id: uuid::Uuid,
-    pub user_id: String,
+    pub user_id: Option<String>,
     pub ip_address: String,
     pub city: String,
     pub country: String,
@@ -60,37 +91,579 @@ pub struct UserSecurityLog {
     pub flagged_for_review: bool,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
+    pub event_type: String,
+    pub user_agent: Option<String>,
+    pub path: String,
+    pub method: String,
+    pub status: i32,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub asn: Option<i64>,
+    /// Comma-separated risk reason codes when `flagged_for_review` is set.
+    pub flag_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, AsChangeset, Insertable)]
 #[diesel(table_name=crate::models::schema::user_security_logs)]
 pub struct NewUserSecurityLog {
-    pub user_id: String,
+    pub user_id: Option<String>,
     pub ip_address: String,
     pub city: String,
     pub country: String,
     pub failed_login_attempts: i32,
     pub flagged_for_review: bool,
     pub created_at: DateTime<Utc>,
+    pub event_type: String,
+    pub user_agent: Option<String>,
+    pub path: String,
+    pub method: String,
+    pub status: i32,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub asn: Option<i64>,
+    /// Comma-separated risk reason codes when `flagged_for_review` is set.
+    pub flag_reason: Option<String>,
+}
+
+/// What a security log record is about. Stored as its `as_str` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+    LoginSuccess,
+    LoginFailure,
+    OtpSent,
+    OtpFailed,
+    BankAdded,
+    TokenRefreshed,
+    ApiKeyRejected,
+    AccountLocked,
+    AccountUnlocked,
+    NewDevice,
+    DeviceRevoked,
+    PinSet,
+    PinChanged,
+    PinReset,
+    PinVerified,
+    PinFailed,
+    PinLocked,
+    TotpEnabled,
+    TotpDisabled,
+    TotpFailed,
+    RecoveryCodeUsed,
+    RecoveryCodesRegenerated,
+    AccountCreated,
+}
+
+impl SecurityEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEventType::LoginSuccess => "login_success",
+            SecurityEventType::LoginFailure => "login_failure",
+            SecurityEventType::OtpSent => "otp_sent",
+            SecurityEventType::OtpFailed => "otp_failed",
+            SecurityEventType::BankAdded => "bank_added",
+            SecurityEventType::TokenRefreshed => "token_refreshed",
+            SecurityEventType::ApiKeyRejected => "api_key_rejected",
+            SecurityEventType::AccountLocked => "account_locked",
+            SecurityEventType::AccountUnlocked => "account_unlocked",
+            SecurityEventType::NewDevice => "new_device",
+            SecurityEventType::DeviceRevoked => "device_revoked",
+            SecurityEventType::PinSet => "pin_set",
+            SecurityEventType::PinChanged => "pin_changed",
+            SecurityEventType::PinReset => "pin_reset",
+            SecurityEventType::PinVerified => "pin_verified",
+            SecurityEventType::PinFailed => "pin_failed",
+            SecurityEventType::PinLocked => "pin_locked",
+            SecurityEventType::TotpEnabled => "totp_enabled",
+            SecurityEventType::TotpDisabled => "totp_disabled",
+            SecurityEventType::TotpFailed => "totp_failed",
+            SecurityEventType::RecoveryCodeUsed => "recovery_code_used",
+            SecurityEventType::RecoveryCodesRegenerated => "recovery_codes_regenerated",
+            SecurityEventType::AccountCreated => "account_created",
+        }
+    }
+
+    /// Events that count towards a user's failed attempts. Wrong PINs are
+    /// not among them: the PIN has its own attempt limit.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            SecurityEventType::LoginFailure
+                | SecurityEventType::OtpFailed
+                | SecurityEventType::TotpFailed
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Queryable)]
+pub struct UserPin {
+    pub user_id: String,
+    /// Argon2 hash in PHC string format.
+    #[serde(skip_serializing)]
+    pub pin_hash: String,
+    /// Wrong PINs since the last correct one or lock.
+    pub failed_attempts: i32,
+    #[serde(rename = "lockedUntil")]
+    pub locked_until: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Where a WhatsApp user is in the onboarding conversation. `state` holds a
+/// serialized `ConversationState`. Only the columns the webhook reads are
+/// loaded.
+#[derive(Debug, Clone, Queryable)]
+pub struct WhatsappConversation {
+    pub state: serde_json::Value,
+    /// Meta retries webhooks, so the last handled message is skipped if it
+    /// arrives again.
+    pub last_message_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable, AsChangeset)]
+#[diesel(table_name=crate::models::schema::whatsapp_conversations)]
+pub struct NewWhatsappConversation {
+    pub phone: String,
+    pub user_id: String,
+    pub state: serde_json::Value,
+    pub last_message_id: Option<String>,
+}
+
+/// What an outbox event announces. Stored, and sent to subscribers, as its
+/// `as_str` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutboxEventType {
+    #[serde(rename = "user.created")]
+    UserCreated,
+    #[serde(rename = "user.verified")]
+    UserVerified,
+    #[serde(rename = "bank_account.added")]
+    BankAccountAdded,
+    /// Sent once the user has proved they own the wallet, since until then
+    /// anyone could have linked the address.
+    #[serde(rename = "wallet.linked")]
+    WalletLinked,
+}
+
+impl OutboxEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxEventType::UserCreated => "user.created",
+            OutboxEventType::UserVerified => "user.verified",
+            OutboxEventType::BankAccountAdded => "bank_account.added",
+            OutboxEventType::WalletLinked => "wallet.linked",
+        }
+    }
+}
+
+/// A change other services are told about, written in the same transaction
+/// as the change itself.
+#[derive(Debug, Clone, Queryable, Serialize)]
+pub struct OutboxEvent {
+    pub id: uuid::Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub fanned_out_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=crate::models::schema::outbox_events)]
+pub struct NewOutboxEvent {
+    pub event_type: String,
+    pub payload: serde_json::Value,
+}
+
+/// An outbox event on its way to one subscriber.
+#[derive(Debug, Clone, Queryable, Serialize)]
+pub struct OutboxDelivery {
+    pub id: uuid::Uuid,
+    pub event_id: uuid::Uuid,
+    pub subscriber: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Set when the delivery ran out of attempts.
+    pub dead_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=crate::models::schema::outbox_deliveries)]
+pub struct NewOutboxDelivery {
+    pub event_id: uuid::Uuid,
+    pub subscriber: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    pub limit: Option<i64>,
+}
+
+/// A webhook from Flutterwave, kept as received so it can be replayed.
+#[derive(Debug, Clone, Queryable, Serialize)]
+pub struct FlutterwaveEvent {
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+    /// Set once a handler has run without error.
+    pub processed_at: Option<DateTime<Utc>>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=crate::models::schema::flutterwave_events)]
+pub struct NewFlutterwaveEvent {
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=crate::models::schema::user_pins)]
+pub struct NewUserPin {
+    pub user_id: String,
+    pub pin_hash: String,
+}
+
+#[derive(Debug, Serialize, Clone, Queryable)]
+pub struct UserTotp {
+    pub user_id: String,
+    /// The shared secret, encrypted with AES-256-GCM under `totp.encryption_key`.
+    #[serde(skip_serializing)]
+    pub secret_ciphertext: Vec<u8>,
+    #[serde(skip_serializing)]
+    pub secret_nonce: Vec<u8>,
+    /// Unset while enrolment is waiting for its first code.
+    #[serde(rename = "enabledAt")]
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// The last time step a code was accepted for; codes are single use.
+    #[serde(rename = "lastUsedStep")]
+    pub last_used_step: Option<i64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Insertable, AsChangeset)]
+#[diesel(table_name=crate::models::schema::user_totp)]
+pub struct NewUserTotp {
+    pub user_id: String,
+    pub secret_ciphertext: Vec<u8>,
+    pub secret_nonce: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=crate::models::schema::user_recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: String,
+    /// SHA-256 of the normalised code, hex encoded.
+    pub code_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpEnrolSchema {
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpQuery {
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpConfirmSchema {
+    pub phone: String,
+    pub code: String,
+}
+
+/// An admin confirming their own enrolment; who they are comes from
+/// `x-admin-phone`.
+#[derive(Debug, Deserialize)]
+pub struct AdminTotpConfirmSchema {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinSchema {
+    pub phone: String,
+    pub pin: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePinSchema {
+    pub phone: String,
+    pub old_pin: String,
+    pub new_pin: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinResetRequestSchema {
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPinResetSchema {
+    pub phone: String,
+    pub otp: i32,
+    pub new_pin: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Queryable, AsChangeset, Insertable)]
+#[diesel(table_name=crate::models::schema::user_devices)]
+pub struct UserDevice {
+    pub id: uuid::Uuid,
+    pub user_id: String,
+    /// Identifier the client sends in `X-Device-Id`.
+    pub device_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: String,
+    pub city: String,
+    pub country: String,
+    /// Set once the device has passed an OTP check.
+    pub trusted: bool,
+    #[serde(rename = "firstSeenAt")]
+    pub first_seen_at: DateTime<Utc>,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: DateTime<Utc>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, AsChangeset, Insertable)]
+#[diesel(table_name=crate::models::schema::user_devices)]
+pub struct NewUserDevice {
+    pub user_id: String,
+    pub device_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: String,
+    pub city: String,
+    pub country: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Queryable, AsChangeset, Insertable)]
 #[diesel(table_name=crate::models::schema::otp)]
 pub struct Otp {
     pub otp_id: uuid::Uuid,
-    pub otp_code: i32,
     pub user_id: String,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
     #[serde(rename = "expiresAt")]
     pub expires_at: DateTime<Utc>,
+    /// HMAC-SHA256 of the code under `otp.hash_key`, hex encoded.
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    pub purpose: String,
+    /// Wrong guesses so far.
+    pub attempts: i32,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, AsChangeset, Insertable)]
 #[diesel(table_name=crate::models::schema::otp)]
 pub struct NewOtp {
-    pub otp_code: i32,
     pub user_id: String,
+    pub code_hash: String,
+    pub purpose: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The flow a code was issued for; a code only works for its own flow.
+/// Stored as its `as_str` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtpPurpose {
+    Login,
+    PhoneChange,
+    PinReset,
+    BankAdd,
+    /// Changing the notification channel or email, sent on the current one.
+    ContactChange,
+    /// Sent to a new email address, which is used once this is confirmed.
+    EmailConfirm,
+}
+
+impl OtpPurpose {
+    pub const ALL: [OtpPurpose; 6] = [
+        OtpPurpose::Login,
+        OtpPurpose::PhoneChange,
+        OtpPurpose::PinReset,
+        OtpPurpose::BankAdd,
+        OtpPurpose::ContactChange,
+        OtpPurpose::EmailConfirm,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpPurpose::Login => "login",
+            OtpPurpose::PhoneChange => "phone_change",
+            OtpPurpose::PinReset => "pin_reset",
+            OtpPurpose::BankAdd => "bank_add",
+            OtpPurpose::ContactChange => "contact_change",
+            OtpPurpose::EmailConfirm => "email_confirm",
+        }
+    }
+}
+
+/// A way of reaching a user. Stored as its `as_str` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Sms,
+    Whatsapp,
+    Email,
+}
+
+impl ChannelKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelKind::Sms => "sms",
+            ChannelKind::Whatsapp => "whatsapp",
+            ChannelKind::Email => "email",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ChannelKind> {
+        match value {
+            "sms" => Some(ChannelKind::Sms),
+            "whatsapp" => Some(ChannelKind::Whatsapp),
+            "email" => Some(ChannelKind::Email),
+            _ => None,
+        }
+    }
+}
+
+/// Languages messages to users are written in, by ISO 639 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[serde(rename = "en")]
+    English,
+    /// Nigerian Pidgin.
+    #[serde(rename = "pcm")]
+    Pidgin,
+    #[serde(rename = "yo")]
+    Yoruba,
+    #[serde(rename = "ha")]
+    Hausa,
+    #[serde(rename = "ig")]
+    Igbo,
+}
+
+impl Language {
+    pub const ALL: [Language; 5] = [
+        Language::English,
+        Language::Pidgin,
+        Language::Yoruba,
+        Language::Hausa,
+        Language::Igbo,
+    ];
+
+    /// Also the name of the language's template directory.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Pidgin => "pcm",
+            Language::Yoruba => "yo",
+            Language::Hausa => "ha",
+            Language::Igbo => "ig",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Language> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.as_str() == value)
+    }
+}
+
+/// A chain wallets can be linked on. Stored in `network` as its
+/// `as_str` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Network {
+    Starknet,
+    Ethereum,
+    Base,
+    Arbitrum,
+}
+
+impl Network {
+    pub const ALL: [Network; 4] = [
+        Network::Starknet,
+        Network::Ethereum,
+        Network::Base,
+        Network::Arbitrum,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Starknet => "starknet",
+            Network::Ethereum => "ethereum",
+            Network::Base => "base",
+            Network::Arbitrum => "arbitrum",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Network> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.as_str() == value)
+    }
+
+    /// Uses 20-byte EVM addresses rather than Starknet felts.
+    pub fn is_evm(&self) -> bool {
+        !matches!(self, Network::Starknet)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationPreferencesSchema {
+    pub phone: String,
+    pub channel: Option<ChannelKind>,
+    /// Becomes the stored address once confirmed with a code sent to it.
+    pub email: Option<String>,
+    pub language: Option<Language>,
+}
+
+/// Preference columns to change; `None` leaves a column as it is.
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name=crate::models::schema::users)]
+pub struct UserNotificationPreferences {
+    pub notification_channel: Option<String>,
+    pub pending_email: Option<String>,
+    pub preferred_language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WhatsappSubscriptionQuery {
+    #[serde(rename = "hub.mode")]
+    pub mode: String,
+    #[serde(rename = "hub.verify_token")]
+    pub verify_token: String,
+    #[serde(rename = "hub.challenge")]
+    pub challenge: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailSchema {
+    pub phone: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SendOtpSchema {
+    pub phone: String,
+    pub purpose: OtpPurpose,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyOtpSchema {
+    pub phone: String,
+    pub purpose: OtpPurpose,
+    pub code: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
@@ -127,6 +700,20 @@ pub struct UserBankAccount {
     pub updated_at: Option<DateTime<Utc>>,
     pub phone: Option<String>,
     pub account_name: Option<String>,
+    /// The account payouts go to. Each user has at most one.
+    pub is_default: bool,
+}
+
+impl UserBankAccount {
+    /// The last four digits, enough to recognise the account by when the
+    /// full number should not be shown or sent.
+    pub fn account_last4(&self) -> String {
+        let digits = self.account_number.chars().count();
+        self.account_number
+            .chars()
+            .skip(digits.saturating_sub(4))
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -134,6 +721,11 @@ pub struct GetBankAccountQuery {
     pub phone: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UserDevicesQuery {
+    pub phone: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, AsChangeset, Insertable)]
 #[diesel(table_name=crate::models::schema::user_bank_account)]
 pub struct NewUserBankAccount {
@@ -149,6 +741,7 @@ pub struct NewUserBankAccount {
 pub struct NewToken {
     pub user_id: String,
     pub token: String,
+    pub device_id: Option<uuid::Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -174,8 +767,58 @@ pub struct CreateUserSchema {
 
 #[derive(Debug, Deserialize)]
 pub struct UserWalletSchema {
+    pub phone: String,
     pub wallet_address: String,
-    pub network: String,
+    pub network: Network,
+    pub label: Option<String>,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWalletSchema {
+    pub phone: String,
+    /// Replaces the label; an empty one removes it.
+    pub label: Option<String>,
+    /// `true` makes this the primary wallet on its network.
+    pub primary: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserWalletsQuery {
+    pub phone: String,
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserWalletQuery {
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalletChallengeSchema {
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalletProofSchema {
+    pub phone: String,
+    /// The 65-byte `personal_sign` signature in hex for EVM wallets; the
+    /// felts the account returned for Starknet, as `signMessage` gave them.
+    /// See `verify_starknet_signature` for the layouts understood.
+    pub signature: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalletLookupSchema {
+    pub addresses: Vec<WalletLookupItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WalletLookupItem {
+    pub network: Network,
+    pub address: String,
 }
 
 // #[derive(Debug, Deserialize)]
@@ -223,6 +866,8 @@ pub struct Token {
     pub token: String,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
+    /// The device the token was issued to, if known.
+    pub device_id: Option<uuid::Uuid>,
 }
 
 // /*  DISPLAY IMPLEMENTATION FOR ENUMS */
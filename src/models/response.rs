@@ -11,6 +11,11 @@ pub struct FilteredUser {
     pub role: String,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
+    pub email: Option<String>,
+    #[serde(rename = "notificationChannel")]
+    pub notification_channel: String,
+    #[serde(rename = "preferredLanguage")]
+    pub preferred_language: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -20,13 +25,21 @@ pub struct UserData {
 
 #[derive(Debug, Serialize)]
 pub struct FilteredWallet {
+    pub wallet_id: String,
     pub user_id: String,
     pub wallet_address: String,
-    pub network_used_last: String,
+    pub network: String,
+    pub label: Option<String>,
+    pub primary: bool,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
-    pub updated_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "archivedAt")]
+    pub archived_at: Option<DateTime<Utc>>,
+    pub verified: bool,
+    #[serde(rename = "verifiedAt")]
+    pub verified_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -36,6 +49,22 @@ pub struct FilteredBankDetails {
     pub bank_name: String,
     pub bank_account_number: String,
     pub account_name: Option<String>,
+    pub default: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FilteredDevice {
+    pub id: String,
+    pub device_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: String,
+    pub city: String,
+    pub country: String,
+    pub trusted: bool,
+    #[serde(rename = "firstSeenAt")]
+    pub first_seen_at: DateTime<Utc>,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
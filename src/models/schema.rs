@@ -1,13 +1,57 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    flutterwave_events (event_id) {
+        #[max_length = 128]
+        event_id -> Varchar,
+        #[max_length = 64]
+        event_type -> Varchar,
+        payload -> Jsonb,
+        received_at -> Timestamptz,
+        processed_at -> Nullable<Timestamptz>,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     otp (otp_id) {
         otp_id -> Uuid,
-        otp_code -> Int4,
         #[max_length = 50]
         user_id -> Varchar,
         created_at -> Timestamptz,
         expires_at -> Timestamptz,
+        #[max_length = 64]
+        code_hash -> Varchar,
+        #[max_length = 20]
+        purpose -> Varchar,
+        attempts -> Int4,
+    }
+}
+
+diesel::table! {
+    outbox_deliveries (id) {
+        id -> Uuid,
+        event_id -> Uuid,
+        #[max_length = 100]
+        subscriber -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+        dead_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    outbox_events (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        event_type -> Varchar,
+        payload -> Jsonb,
+        created_at -> Timestamptz,
+        fanned_out_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -90,6 +134,29 @@ diesel::table! {
         phone -> Nullable<Varchar>,
         #[max_length = 255]
         account_name -> Nullable<Varchar>,
+        is_default -> Bool,
+    }
+}
+
+diesel::table! {
+    user_devices (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        user_id -> Varchar,
+        #[max_length = 128]
+        device_id -> Varchar,
+        #[max_length = 512]
+        user_agent -> Nullable<Varchar>,
+        #[max_length = 50]
+        ip_address -> Varchar,
+        #[max_length = 50]
+        city -> Varchar,
+        #[max_length = 50]
+        country -> Varchar,
+        trusted -> Bool,
+        first_seen_at -> Timestamptz,
+        last_seen_at -> Timestamptz,
+        revoked_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -100,6 +167,32 @@ diesel::table! {
         user_id -> Varchar,
         token -> Text,
         created_at -> Nullable<Timestamptz>,
+        device_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    user_pins (user_id) {
+        #[max_length = 50]
+        user_id -> Varchar,
+        #[max_length = 255]
+        pin_hash -> Varchar,
+        failed_attempts -> Int4,
+        locked_until -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_recovery_codes (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        user_id -> Varchar,
+        #[max_length = 64]
+        code_hash -> Varchar,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
     }
 }
 
@@ -107,7 +200,7 @@ diesel::table! {
     user_security_logs (log_id) {
         log_id -> Uuid,
         #[max_length = 50]
-        user_id -> Varchar,
+        user_id -> Nullable<Varchar>,
         #[max_length = 50]
         ip_address -> Varchar,
         #[max_length = 50]
@@ -117,6 +210,32 @@ diesel::table! {
         failed_login_attempts -> Int4,
         flagged_for_review -> Bool,
         created_at -> Nullable<Timestamptz>,
+        #[max_length = 32]
+        event_type -> Varchar,
+        #[max_length = 512]
+        user_agent -> Nullable<Varchar>,
+        #[max_length = 255]
+        path -> Varchar,
+        #[max_length = 10]
+        method -> Varchar,
+        status -> Int4,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        asn -> Nullable<Int8>,
+        #[max_length = 64]
+        flag_reason -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        #[max_length = 50]
+        user_id -> Varchar,
+        secret_ciphertext -> Bytea,
+        secret_nonce -> Bytea,
+        enabled_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamptz,
     }
 }
 
@@ -129,10 +248,18 @@ diesel::table! {
         #[max_length = 100]
         wallet_address -> Nullable<Varchar>,
         #[max_length = 50]
-        network_used_last -> Nullable<Varchar>,
+        network -> Nullable<Varchar>,
         controller_info -> Nullable<Text>,
         created_at -> Nullable<Timestamptz>,
         updated_at -> Nullable<Timestamptz>,
+        #[max_length = 50]
+        label -> Nullable<Varchar>,
+        is_primary -> Bool,
+        archived_at -> Nullable<Timestamptz>,
+        verified_at -> Nullable<Timestamptz>,
+        #[max_length = 66]
+        challenge_nonce -> Nullable<Varchar>,
+        challenge_expires_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -147,24 +274,64 @@ diesel::table! {
         #[max_length = 10]
         role -> Varchar,
         created_at -> Nullable<Timestamptz>,
+        locked_until -> Nullable<Timestamptz>,
+        #[max_length = 255]
+        email -> Nullable<Varchar>,
+        #[max_length = 10]
+        notification_channel -> Varchar,
+        #[max_length = 5]
+        preferred_language -> Varchar,
+        #[max_length = 10]
+        kyc_status -> Varchar,
+        #[max_length = 255]
+        pending_email -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    whatsapp_conversations (phone) {
+        #[max_length = 20]
+        phone -> Varchar,
+        #[max_length = 50]
+        user_id -> Varchar,
+        state -> Jsonb,
+        #[max_length = 128]
+        last_message_id -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
 diesel::joinable!(otp -> users (user_id));
+diesel::joinable!(outbox_deliveries -> outbox_events (event_id));
 diesel::joinable!(transactions -> users (user_id));
 diesel::joinable!(user_bank_account -> users (user_id));
+diesel::joinable!(user_devices -> users (user_id));
+diesel::joinable!(user_jwt_tokens -> user_devices (device_id));
 diesel::joinable!(user_jwt_tokens -> users (user_id));
+diesel::joinable!(user_pins -> users (user_id));
+diesel::joinable!(user_recovery_codes -> users (user_id));
 diesel::joinable!(user_security_logs -> users (user_id));
+diesel::joinable!(user_totp -> users (user_id));
 diesel::joinable!(user_wallet -> users (user_id));
+diesel::joinable!(whatsapp_conversations -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    flutterwave_events,
     otp,
+    outbox_deliveries,
+    outbox_events,
     payments,
     session_controller_info,
     transactions,
     user_bank_account,
+    user_devices,
     user_jwt_tokens,
+    user_pins,
+    user_recovery_codes,
     user_security_logs,
+    user_totp,
     user_wallet,
     users,
+    whatsapp_conversations,
 );
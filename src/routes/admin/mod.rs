@@ -0,0 +1,4 @@
+pub mod outbox;
+pub mod totp;
+pub mod users;
+pub mod webhooks;
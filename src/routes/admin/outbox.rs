@@ -0,0 +1,73 @@
+use crate::{
+    AppState, database::outbox_db::OutboxImpl, helpers::admin_helpers::authorize_admin,
+    models::models::DeadLetterQuery,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use serde_json::json;
+
+/// Outbox deliveries that ran out of attempts, most recent first.
+#[get("/admin/outbox/dead-letters")]
+async fn list_dead_letters_handler(
+    req: HttpRequest,
+    query: web::Query<DeadLetterQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = authorize_admin(&req, &data) {
+        return response;
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    match data.db.get_dead_outbox_deliveries(limit) {
+        Ok(dead_letters) => {
+            let dead_letters: Vec<_> = dead_letters
+                .into_iter()
+                .map(|(delivery, event)| json!({ "delivery": delivery, "event": event }))
+                .collect();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "data": { "dead_letters": dead_letters }
+            }))
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to fetch dead letters");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to fetch dead letters"
+            }))
+        }
+    }
+}
+
+/// Queues a dead letter for delivery again, with a fresh set of attempts.
+#[post("/admin/outbox/dead-letters/{delivery_id}/retry")]
+async fn retry_dead_letter_handler(
+    req: HttpRequest,
+    path: web::Path<uuid::Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let admin = match authorize_admin(&req, &data) {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let delivery_id = path.into_inner();
+    tracing::info!(admin_id = %admin.id, %delivery_id, "Admin retrying outbox delivery");
+
+    match data.db.requeue_outbox_delivery(delivery_id) {
+        Ok(true) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Delivery queued"
+        })),
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "No dead letter with that ID"
+        })),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to requeue outbox delivery");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to requeue delivery"
+            }))
+        }
+    }
+}
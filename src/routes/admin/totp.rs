@@ -0,0 +1,42 @@
+use crate::{
+    AppState,
+    helpers::{
+        admin_helpers::admin_user,
+        otp_helpers::require_otp,
+        totp_helpers::{confirm_totp, enrol_totp},
+    },
+    models::models::{AdminTotpConfirmSchema, OtpPurpose},
+};
+use actix_web::{HttpRequest, Responder, post, web};
+
+/// Starts TOTP enrolment for the admin in `x-admin-phone`. Admins cannot use
+/// any other admin route until it is confirmed, so this needs the admin key
+/// and a `contact_change` code sent to the admin rather than a TOTP code.
+#[post("/admin/totp/enrol")]
+async fn enrol_admin_totp_handler(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    let admin = match admin_user(&req, &data) {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_otp(&req, &data, &admin, OtpPurpose::ContactChange) {
+        return response;
+    }
+
+    enrol_totp(&data, &admin)
+}
+
+/// Turns TOTP on for the admin in `x-admin-phone` with a code from the
+/// authenticator app.
+#[post("/admin/totp/confirm")]
+async fn confirm_admin_totp_handler(
+    req: HttpRequest,
+    body: web::Json<AdminTotpConfirmSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let admin = match admin_user(&req, &data) {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    confirm_totp(&req, &data, &admin, &body.code)
+}
@@ -0,0 +1,42 @@
+use crate::{
+    AppState, helpers::admin_helpers::authorize_admin,
+    middleware::security_log::record_security_event, models::models::SecurityEventType,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use serde_json::json;
+
+#[post("/admin/users/{user_id}/unlock")]
+async fn unlock_user_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let admin = match authorize_admin(&req, &data) {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let user_id = path.into_inner();
+    tracing::info!(admin_id = %admin.id, user_id = %user_id, "Admin unlocking account");
+
+    match data.lockout.unlock(&user_id) {
+        Ok(true) => {
+            record_security_event(&req, SecurityEventType::AccountUnlocked, Some(user_id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Account unlocked"
+            }))
+        }
+        Ok(false) => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": "Account is not locked"
+        })),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to unlock account");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to unlock account"
+            }))
+        }
+    }
+}
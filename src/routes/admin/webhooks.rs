@@ -0,0 +1,59 @@
+use crate::{
+    AppState,
+    database::{db::AppError, flutterwave_event_db::FlutterwaveEventImpl},
+    helpers::admin_helpers::authorize_admin,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use serde_json::json;
+
+/// Runs a stored Flutterwave event's handler again, e.g. after a transfer
+/// arrived before its transaction was recorded. Processed events are run
+/// again too; settling a transaction twice is harmless.
+#[post("/admin/webhooks/flutterwave/{event_id}/replay")]
+async fn replay_flutterwave_event_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let admin = match authorize_admin(&req, &data) {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let event_id = path.into_inner();
+    let event = match data.db.get_flutterwave_event(&event_id) {
+        Ok(event) => event,
+        Err(AppError::DieselError(diesel::result::Error::NotFound)) => {
+            return HttpResponse::NotFound().json(json!({
+                "status": "error",
+                "message": "Event not found"
+            }));
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to fetch Flutterwave event");
+            return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to fetch event"
+            }));
+        }
+    };
+    tracing::info!(admin_id = %admin.id, event_id = %event_id, "Admin replaying Flutterwave event");
+
+    match data.flutterwave.process(&event) {
+        Ok(event) => HttpResponse::Ok().json(json!({
+            "status": if event.last_error.is_none() { "success" } else { "error" },
+            "message": match &event.last_error {
+                None => "Event processed".to_string(),
+                Some(error) => format!("Event failed: {}", error),
+            },
+            "data": event
+        })),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to replay Flutterwave event");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to replay event"
+            }))
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    AppState, database::user_wallet_db::UserWalletImpl,
+    helpers::request_helpers::check_indexer_key, models::models::WalletLookupSchema,
+    routes::users::profile::filtered_bank_record, services::wallets::address::normalize,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use serde_json::json;
+
+const MAX_LOOKUP_ADDRESSES: usize = 100;
+
+/// Resolves wallet addresses to their owners for the indexer, which sees
+/// deposits on chain and needs to know who to pay out and where. Results
+/// are in request order; an address no verified wallet is linked at has a
+/// null `user`, and one that is not valid for its network has an `error`.
+#[post("/internal/wallets/lookup")]
+async fn lookup_wallets_handler(
+    req: HttpRequest,
+    body: web::Json<WalletLookupSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_indexer_key(&req, &data) {
+        return response;
+    }
+
+    if body.addresses.is_empty() || body.addresses.len() > MAX_LOOKUP_ADDRESSES {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("Send between 1 and {} addresses", MAX_LOOKUP_ADDRESSES)
+        }));
+    }
+
+    let normalized: Vec<_> = body
+        .addresses
+        .iter()
+        .map(|item| normalize(item.network, &item.address))
+        .collect();
+    let addresses: Vec<String> = normalized
+        .iter()
+        .filter_map(|address| address.as_ref().ok().cloned())
+        .collect();
+
+    let owners = match data.db.resolve_wallet_owners(&addresses) {
+        Ok(owners) => owners,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to look up wallets");
+            return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to look up wallets"
+            }));
+        }
+    };
+
+    let results: Vec<serde_json::Value> = body
+        .addresses
+        .iter()
+        .zip(normalized)
+        .map(|(item, normalized)| {
+            let network = item.network.as_str();
+            let address = match normalized {
+                Ok(address) => address,
+                Err(e) => {
+                    return json!({
+                        "network": network,
+                        "address": item.address,
+                        "error": e.to_string(),
+                    });
+                }
+            };
+
+            let owner = owners.iter().find(|(wallet, _, _)| {
+                wallet.network.as_deref() == Some(network)
+                    && wallet.wallet_address.as_deref() == Some(address.as_str())
+            });
+            match owner {
+                Some((wallet, user, bank)) => json!({
+                    "network": network,
+                    "address": address,
+                    "walletId": wallet.id,
+                    "walletVerified": wallet.verified_at.is_some(),
+                    "user": {
+                        "id": user.id,
+                        "phone": user.phone,
+                        "verified": user.verified,
+                        "kycStatus": user.kyc_status,
+                    },
+                    "bankAccount": bank.as_ref().map(filtered_bank_record),
+                }),
+                None => json!({
+                    "network": network,
+                    "address": address,
+                    "user": null,
+                }),
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "data": { "results": results }
+    }))
+}
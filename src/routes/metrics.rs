@@ -0,0 +1,17 @@
+use crate::AppState;
+use actix_web::{HttpResponse, Responder, get, web};
+
+#[get("/metrics")]
+pub async fn metrics_handler(data: web::Data<AppState>) -> impl Responder {
+    data.metrics.record_pool_state(data.db.pool.state());
+
+    match data.metrics.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to render metrics");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
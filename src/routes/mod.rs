@@ -1,2 +1,6 @@
+pub mod admin;
 pub mod healthz;
+pub mod internal;
+pub mod metrics;
 pub mod users;
+pub mod webhooks;
@@ -0,0 +1,105 @@
+use crate::{
+    AppState,
+    database::user_db::UserImpl,
+    helpers::{
+        request_helpers::{check_api_key, user_lookup_error},
+        totp_helpers::require_totp,
+    },
+    middleware::security_log::record_security_event,
+    models::{
+        models::{SecurityEventType, UserDevice, UserDevicesQuery},
+        response::FilteredDevice,
+    },
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, web};
+use serde_json::json;
+
+fn filtered_device_record(device: &UserDevice) -> FilteredDevice {
+    FilteredDevice {
+        id: device.id.to_string(),
+        device_id: device.device_id.clone(),
+        user_agent: device.user_agent.clone(),
+        ip_address: device.ip_address.clone(),
+        city: device.city.clone(),
+        country: device.country.clone(),
+        trusted: device.trusted,
+        first_seen_at: device.first_seen_at,
+        last_seen_at: device.last_seen_at,
+    }
+}
+
+#[get("/users/me/devices")]
+async fn list_user_devices_handler(
+    req: HttpRequest,
+    query: web::Query<UserDevicesQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&query.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.devices.list(&user.id) {
+        Ok(devices) => {
+            let devices: Vec<FilteredDevice> = devices.iter().map(filtered_device_record).collect();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "data": { "devices": devices }
+            }))
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to fetch devices");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to fetch devices"
+            }))
+        }
+    }
+}
+
+/// Revokes a device and signs it out; its next sign-in needs an OTP again.
+#[delete("/users/me/devices/{id}")]
+async fn revoke_user_device_handler(
+    req: HttpRequest,
+    path: web::Path<uuid::Uuid>,
+    query: web::Query<UserDevicesQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&query.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+
+    match data.devices.revoke(&user.id, path.into_inner()) {
+        Ok(true) => {
+            record_security_event(&req, SecurityEventType::DeviceRevoked, Some(user.id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Device revoked"
+            }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "Device not found"
+        })),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to revoke device");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to revoke device"
+            }))
+        }
+    }
+}
@@ -1 +1,7 @@
+pub mod devices;
+pub mod notifications;
+pub mod otp;
+pub mod pin;
 pub mod profile;
+pub mod totp;
+pub mod wallets;
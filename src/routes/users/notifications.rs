@@ -0,0 +1,172 @@
+use crate::{
+    AppState,
+    database::user_db::UserImpl,
+    helpers::{
+        otp_helpers::{otp_error_response, record_otp_error, require_otp},
+        request_helpers::{check_api_key, user_lookup_error},
+        totp_helpers::require_totp,
+    },
+    middleware::lockout::lockout_response,
+    models::models::{
+        ChannelKind, ConfirmEmailSchema, NotificationPreferencesSchema, OtpPurpose, User,
+        UserNotificationPreferences,
+    },
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, put, web};
+use lettre::Address;
+use serde_json::json;
+
+fn preferences_record(user: &User) -> serde_json::Value {
+    json!({
+        "channel": user.notification_channel,
+        "email": user.email,
+        "pendingEmail": user.pending_email,
+        "language": user.preferred_language
+    })
+}
+
+/// Sets any of the channel OTPs and alerts are sent on first, the email
+/// address and the language messages are written in. Other enabled channels
+/// are still used as fallbacks.
+///
+/// Changing the channel or email needs a `contact_change` code, which goes
+/// out on the current channel, so whoever holds the account cannot quietly
+/// redirect its codes. A new email is only used once it is confirmed at
+/// `/users/me/notifications/email/confirm` with the code sent to it.
+#[put("/users/me/notifications")]
+async fn update_notification_preferences_handler(
+    req: HttpRequest,
+    body: web::Json<NotificationPreferencesSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if body.channel.is_none() && body.email.is_none() && body.language.is_none() {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Nothing to update"
+        }));
+    }
+
+    let email = body.email.as_deref().map(str::trim);
+    if let Some(email) = email
+        && email.parse::<Address>().is_err()
+    {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Invalid email address"
+        }));
+    }
+    if body.channel == Some(ChannelKind::Email) && user.email.is_none() {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "Confirm an email address before choosing email notifications"
+        }));
+    }
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+    if (body.channel.is_some() || email.is_some())
+        && let Err(response) = require_otp(&req, &data, &user, OtpPurpose::ContactChange)
+    {
+        return response;
+    }
+
+    let preferences = UserNotificationPreferences {
+        notification_channel: body.channel.map(|channel| channel.as_str().to_string()),
+        pending_email: email.map(str::to_string),
+        preferred_language: body.language.map(|language| language.as_str().to_string()),
+    };
+    let user = match data
+        .db
+        .update_user_notification_preferences(&user.id, preferences)
+    {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to update notification preferences");
+            return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to update notification preferences"
+            }));
+        }
+    };
+
+    // A code sent to an earlier pending address must not confirm this one.
+    if email.is_some()
+        && let Err(e) = data
+            .otp
+            .revoke(&user.id, OtpPurpose::EmailConfirm)
+            .and_then(|()| data.otp.issue(&user, OtpPurpose::EmailConfirm))
+    {
+        tracing::error!(error = ?e, "Failed to send email confirmation code");
+    }
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Notification preferences updated",
+        "data": preferences_record(&user),
+        "email_confirmation_required": user.pending_email.is_some()
+    }))
+}
+
+/// Makes the pending email the user's address, using the `email_confirm`
+/// code sent to it. A new code can be requested through `/users/me/otp`.
+#[post("/users/me/notifications/email/confirm")]
+async fn confirm_notification_email_handler(
+    req: HttpRequest,
+    body: web::Json<ConfirmEmailSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+    if user.pending_email.is_none() {
+        return HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "No email address is waiting to be confirmed"
+        }));
+    }
+
+    if let Err(rejection) = data.lockout.check_user(&user) {
+        return lockout_response(&rejection);
+    }
+    if let Err(e) = data
+        .otp
+        .verify(&user.id, OtpPurpose::EmailConfirm, &body.code)
+    {
+        record_otp_error(&req, &user.id, &e);
+        return otp_error_response(&e);
+    }
+
+    match data.db.confirm_pending_email(&user.id) {
+        Ok(Some(user)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "Email address confirmed",
+            "data": preferences_record(&user)
+        })),
+        Ok(None) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "No email address is waiting to be confirmed"
+        })),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to confirm email address");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to confirm email address"
+            }))
+        }
+    }
+}
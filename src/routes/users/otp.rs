@@ -0,0 +1,99 @@
+use crate::{
+    AppState,
+    database::user_db::UserImpl,
+    helpers::{
+        device_helpers::sign_in_device,
+        otp_helpers::{otp_error_response, record_otp_error},
+        request_helpers::{check_api_key, user_lookup_error},
+    },
+    middleware::{lockout::lockout_response, security_log::record_security_event},
+    models::models::{OtpPurpose, SecurityEventType, SendOtpSchema, VerifyOtpSchema},
+    routes::users::profile::filtered_user_record,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use serde_json::json;
+
+/// Sends the user a code for `purpose`, replacing any earlier one for it.
+/// PIN resets go through `/users/me/pin/reset`, which also checks a PIN is
+/// set.
+#[post("/users/me/otp")]
+async fn send_otp_handler(
+    req: HttpRequest,
+    body: web::Json<SendOtpSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.otp.issue(&user, body.purpose) {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::OtpSent, Some(user.id));
+            HttpResponse::Accepted().json(json!({
+                "status": "success",
+                "message": "OTP sent"
+            }))
+        }
+        Err(e) => otp_error_response(&e),
+    }
+}
+
+/// Checks a code for `purpose`, using it up. A login code completes the
+/// sign-in, returns the user and trusts the `X-Device-Id` device, so it
+/// skips the OTP next time.
+#[post("/users/me/otp/verify")]
+async fn verify_otp_handler(
+    req: HttpRequest,
+    body: web::Json<VerifyOtpSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    // Wrong codes count towards the account lockout, so a locked account
+    // cannot keep guessing.
+    if let Err(rejection) = data.lockout.check_user(&user) {
+        return lockout_response(&rejection);
+    }
+
+    if let Err(e) = data.otp.verify(&user.id, body.purpose, &body.code) {
+        record_otp_error(&req, &user.id, &e);
+        return otp_error_response(&e);
+    }
+
+    // A login code proves the user holds the phone.
+    if body.purpose == OtpPurpose::Login
+        && !user.verified
+        && let Err(e) = data.db.mark_user_verified(&user.id)
+    {
+        tracing::error!(error = ?e, "Failed to mark user verified");
+    }
+
+    if body.purpose != OtpPurpose::Login {
+        return HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": "OTP verified"
+        }));
+    }
+
+    // The sign-in is complete, so the device is trusted before saying so.
+    record_security_event(&req, SecurityEventType::LoginSuccess, Some(user.id.clone()));
+    let device_trusted = sign_in_device(&req, &data, &user.id).await;
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "OTP verified",
+        "data": filtered_user_record(&user),
+        "device_trusted": device_trusted
+    }))
+}
@@ -0,0 +1,244 @@
+use crate::{
+    AppState,
+    database::user_db::UserImpl,
+    helpers::{
+        otp_helpers::{otp_error_response, record_otp_error},
+        request_helpers::{check_api_key, user_lookup_error},
+        time_helpers::retry_after_secs,
+        totp_helpers::require_totp,
+    },
+    middleware::{lockout::lockout_response, security_log::record_security_event},
+    models::models::{
+        ChangePinSchema, ConfirmPinResetSchema, PinResetRequestSchema, PinSchema, SecurityEventType,
+    },
+    services::pin::manager::PinError,
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    http::{StatusCode, header},
+    post, put, web,
+};
+use serde_json::json;
+
+fn pin_error_response(e: &PinError) -> HttpResponse {
+    match e {
+        PinError::InvalidFormat => HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "PIN must be 4 to 6 digits"
+        })),
+        PinError::NotSet => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "Transaction PIN not set"
+        })),
+        PinError::AlreadySet => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": "Transaction PIN already set"
+        })),
+        PinError::Mismatch { attempts_left } => HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Incorrect PIN",
+            "attempts_left": attempts_left
+        })),
+        PinError::Locked { retry_after, .. } => {
+            let retry_after_secs = retry_after_secs(*retry_after);
+
+            HttpResponse::build(StatusCode::LOCKED)
+                .insert_header((header::RETRY_AFTER, retry_after_secs))
+                .json(json!({
+                    "status": "error",
+                    "message": "Transaction PIN locked after too many wrong attempts",
+                    "retry_after_secs": retry_after_secs
+                }))
+        }
+        PinError::Otp(e) => otp_error_response(e),
+        PinError::Hash(_) | PinError::Task(_) | PinError::Db(_) => {
+            tracing::error!(error = ?e, "Failed to process transaction PIN");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to process transaction PIN"
+            }))
+        }
+    }
+}
+
+/// Records the security event for a failed PIN operation, if it is one.
+fn record_pin_error(req: &HttpRequest, user_id: &str, e: &PinError) {
+    let event_type = match e {
+        PinError::Locked {
+            just_locked: true, ..
+        } => SecurityEventType::PinLocked,
+        PinError::Mismatch { .. } | PinError::Locked { .. } => SecurityEventType::PinFailed,
+        PinError::Otp(e) => return record_otp_error(req, user_id, e),
+        _ => return,
+    };
+    record_security_event(req, event_type, Some(user_id.to_string()));
+}
+
+#[post("/users/me/pin")]
+async fn set_pin_handler(
+    req: HttpRequest,
+    body: web::Json<PinSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.pins.set(&user.id, &body.pin).await {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::PinSet, Some(user.id));
+            HttpResponse::Created().json(json!({
+                "status": "success",
+                "message": "Transaction PIN set"
+            }))
+        }
+        Err(e) => pin_error_response(&e),
+    }
+}
+
+/// Called by the off-ramp before it pays out to one of the user's bank
+/// accounts.
+#[post("/users/me/pin/verify")]
+async fn verify_pin_handler(
+    req: HttpRequest,
+    body: web::Json<PinSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.pins.verify(&user.id, &body.pin).await {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::PinVerified, Some(user.id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Transaction PIN verified"
+            }))
+        }
+        Err(e) => {
+            record_pin_error(&req, &user.id, &e);
+            pin_error_response(&e)
+        }
+    }
+}
+
+#[put("/users/me/pin")]
+async fn change_pin_handler(
+    req: HttpRequest,
+    body: web::Json<ChangePinSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+
+    match data
+        .pins
+        .change(&user.id, &body.old_pin, &body.new_pin)
+        .await
+    {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::PinChanged, Some(user.id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Transaction PIN changed"
+            }))
+        }
+        Err(e) => {
+            record_pin_error(&req, &user.id, &e);
+            pin_error_response(&e)
+        }
+    }
+}
+
+/// Sends an OTP for resetting a forgotten PIN.
+#[post("/users/me/pin/reset")]
+async fn request_pin_reset_handler(
+    req: HttpRequest,
+    body: web::Json<PinResetRequestSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.pins.request_reset(&user) {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::OtpSent, Some(user.id));
+            HttpResponse::Accepted().json(json!({
+                "status": "success",
+                "message": "OTP sent"
+            }))
+        }
+        Err(e) => pin_error_response(&e),
+    }
+}
+
+#[post("/users/me/pin/reset/confirm")]
+async fn confirm_pin_reset_handler(
+    req: HttpRequest,
+    body: web::Json<ConfirmPinResetSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    // Wrong codes count towards the account lockout, so a locked account
+    // cannot keep guessing.
+    if let Err(rejection) = data.lockout.check_user(&user) {
+        return lockout_response(&rejection);
+    }
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+
+    match data
+        .pins
+        .reset(&user.id, &body.otp.to_string(), &body.new_pin)
+        .await
+    {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::PinReset, Some(user.id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Transaction PIN reset"
+            }))
+        }
+        Err(e) => {
+            record_pin_error(&req, &user.id, &e);
+            pin_error_response(&e)
+        }
+    }
+}
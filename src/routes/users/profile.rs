@@ -1,37 +1,41 @@
 use crate::{
     AppState,
-    database::token_db::TokenImpl,
-    helpers::bank_helpers::get_bank_code_and_verify_account,
+    helpers::{
+        bank_helpers::get_bank_code_and_verify_account, device_helpers::sign_in_device,
+        otp_helpers::require_otp, request_helpers::check_api_key, totp_helpers::require_totp,
+    },
+    middleware::{lockout::lockout_response, security_log::record_security_event},
     models::{
         models::{
             BankAccountDetails, GetBankAccountQuery, NewUserBankAccount, NewUserBankAccountRequest,
-            UserBankAccount,
+            OtpPurpose, SecurityEventType, UserBankAccount,
         },
         response::FilteredBankDetails,
     },
+    services::{
+        devices::registry::device_id_from_header, notifications::templates::BankAddedMessage,
+    },
 };
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, get, post, web};
-use chrono::{Duration, Utc};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
 use serde_json::json;
-use sqlx::query;
-use std::usize;
 
 use crate::database::{db::AppError, user_bank_account_db::UserBankImpl, user_db::UserImpl};
-use crate::models::models::{CreateUserSchema, NewUser, User, UserWallet};
+use crate::models::models::{CreateUserSchema, NewUser, User};
 
 use crate::models::response::FilteredUser;
 
-fn filtered_bank_record(bank: &UserBankAccount) -> FilteredBankDetails {
+pub fn filtered_bank_record(bank: &UserBankAccount) -> FilteredBankDetails {
     FilteredBankDetails {
         bank_details_id: bank.id.to_string(),
         user_id: bank.user_id.to_string(),
         bank_name: bank.bank_name.to_string(),
         bank_account_number: bank.account_number.to_string(),
         account_name: bank.account_name.clone(),
+        default: bank.is_default,
     }
 }
 
-fn filtered_user_record(user: &User) -> FilteredUser {
+pub fn filtered_user_record(user: &User) -> FilteredUser {
     FilteredUser {
         id: user.id.to_string(),
         phone: user.phone.clone(),
@@ -39,6 +43,9 @@ fn filtered_user_record(user: &User) -> FilteredUser {
         verified: user.verified,
         role: user.role.clone(),
         created_at: user.created_at.unwrap(),
+        email: user.email.clone(),
+        notification_channel: user.notification_channel.clone(),
+        preferred_language: user.preferred_language.clone(),
     }
 }
 
@@ -48,23 +55,8 @@ async fn create_user_handler(
     body: web::Json<CreateUserSchema>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let expected_api_key = &data.env.hmac_key;
-
-    match req.headers().get("x-api-key") {
-        Some(provided_key) => {
-            if *provided_key != *expected_api_key {
-                return HttpResponse::Unauthorized().json(json!({
-                    "status": "error",
-                    "message": "Invalid API key"
-                }));
-            }
-        }
-        None => {
-            return HttpResponse::Unauthorized().json(json!({
-                "status": "error",
-                "message": "API key missing"
-            }));
-        }
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
     }
 
     let phone: String = body.phone.clone();
@@ -72,14 +64,44 @@ async fn create_user_handler(
     // Check if user with phone already exists
     match data.db.get_user_by_phone(phone.clone().as_str()) {
         Ok(existing_user) => {
+            if let Err(rejection) = data.lockout.check_user(&existing_user) {
+                return lockout_response(&rejection);
+            }
+            let device_id = device_id_from_header(
+                req.headers()
+                    .get("x-device-id")
+                    .and_then(|value| value.to_str().ok()),
+            );
+            let otp_required = match data
+                .devices
+                .requires_otp(&existing_user.id, device_id.as_deref())
+            {
+                Ok(required) => required,
+                Err(e) => {
+                    tracing::error!(error = ?e, "Failed to check device");
+                    true
+                }
+            };
+            // While `otp_required` is set the sign-in only completes once a
+            // login code is verified at /users/me/otp/verify. `data` is
+            // still returned, as existing callers read it.
+            if !otp_required {
+                record_security_event(
+                    &req,
+                    SecurityEventType::LoginSuccess,
+                    Some(existing_user.id.clone()),
+                );
+                sign_in_device(&req, &data, &existing_user.id).await;
+            }
             return HttpResponse::Ok().json(json!({
                 "status": "success",
                 "message": "User already exists",
-                "data": filtered_user_record(&existing_user)
+                "data": filtered_user_record(&existing_user),
+                "otp_required": otp_required
             }));
         }
         Err(AppError::DbConnectionError(e)) => {
-            eprintln!("DB connection error: {:?}", e);
+            tracing::error!(error = ?e, "DB connection error");
             return HttpResponse::InternalServerError().json(json!({
                 "status": "error",
                 "message": "Database connection failed"
@@ -89,7 +111,7 @@ async fn create_user_handler(
             // User not found, continue to create new user
         }
         Err(AppError::DieselError(e)) => {
-            eprintln!("Query error: {:?}", e);
+            tracing::error!(error = ?e, "Query error");
             return HttpResponse::InternalServerError().json(json!({
                 "status": "error",
                 "message": "Database query failed"
@@ -106,13 +128,21 @@ async fn create_user_handler(
     };
 
     match data.db.create_user(new_user.clone()) {
-        Ok(user) => HttpResponse::Created().json(json!({
-            "status": "success",
-            "message": "User created successfully",
-            "data": filtered_user_record(&user)
-        })),
+        Ok(user) => {
+            record_security_event(
+                &req,
+                SecurityEventType::AccountCreated,
+                Some(user.id.clone()),
+            );
+            HttpResponse::Created().json(json!({
+                "status": "success",
+                "message": "User created successfully",
+                "data": filtered_user_record(&user),
+                "otp_required": true
+            }))
+        }
         Err(e) => {
-            eprintln!("Failed to create user: {:?}", e);
+            tracing::error!(error = ?e, "Failed to create user");
             HttpResponse::InternalServerError().json(json!({
                 "status": "error",
                 "message": "Failed to create user"
@@ -127,23 +157,8 @@ async fn verify_user_bank_account_handler(
     query: web::Query<NewUserBankAccountRequest>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let expected_api_key = &data.env.hmac_key;
-
-    match req.headers().get("x-api-key") {
-        Some(provided_key) => {
-            if *provided_key != *expected_api_key {
-                return HttpResponse::Unauthorized().json(json!({
-                    "status": "error",
-                    "message": "Invalid API key"
-                }));
-            }
-        }
-        None => {
-            return HttpResponse::Unauthorized().json(json!({
-                "status": "error",
-                "message": "API key missing"
-            }));
-        }
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
     }
 
     let account_number = query.account_number.clone();
@@ -161,7 +176,7 @@ async fn verify_user_bank_account_handler(
             {
                 Ok(details) => details,
                 Err(e) => {
-                    eprintln!("Failed to verify bank account: {:?}", e);
+                    tracing::error!(error = ?e, "Failed to verify bank account");
                     return e;
                 }
             };
@@ -188,7 +203,7 @@ async fn verify_user_bank_account_handler(
                     }));
                 }
                 _ => {
-                    eprintln!("Failed to verify bank account: {:?}", e);
+                    tracing::error!(error = ?e, "Failed to verify bank account");
                     return HttpResponse::InternalServerError().json(json!({
                         "status": "error",
                         "message": format!("Failed to verify bank account: {:?}", e)
@@ -205,23 +220,8 @@ async fn confirm_user_bank_account_handler(
     body: web::Json<BankAccountDetails>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let expected_api_key = &data.env.hmac_key;
-
-    match req.headers().get("x-api-key") {
-        Some(provided_key) => {
-            if *provided_key != *expected_api_key {
-                return HttpResponse::Unauthorized().json(json!({
-                    "status": "error",
-                    "message": "Invalid API key"
-                }));
-            }
-        }
-        None => {
-            return HttpResponse::Unauthorized().json(json!({
-                "status": "error",
-                "message": "API key missing"
-            }));
-        }
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
     }
 
     let user_phone = body.phone.clone();
@@ -230,6 +230,13 @@ async fn confirm_user_bank_account_handler(
 
     match data.db.get_user_by_phone(&user_phone.as_str()) {
         Ok(user) => {
+            if let Err(response) = require_totp(&req, &data, &user) {
+                return response;
+            }
+            if let Err(response) = require_otp(&req, &data, &user, OtpPurpose::BankAdd) {
+                return response;
+            }
+
             let bank_details = NewUserBankAccount {
                 user_id: user.id.clone(),
                 account_number: account_number.clone(),
@@ -237,15 +244,31 @@ async fn confirm_user_bank_account_handler(
                 account_name: Some(body.account_name.clone()),
                 phone: Some(user_phone.clone()),
             };
-            println!("Confirming bank details: {:?}", bank_details);
+            tracing::info!(
+                user_id = %bank_details.user_id,
+                bank_name = %bank_details.bank_name,
+                "Confirming bank details"
+            );
 
             match data.db.create_user_bank(bank_details) {
                 Ok(bank) => {
+                    record_security_event(
+                        &req,
+                        SecurityEventType::BankAdded,
+                        Some(bank.user_id.clone()),
+                    );
+                    data.notifier.dispatch(
+                        &user,
+                        BankAddedMessage {
+                            bank_name: bank.bank_name.clone(),
+                            account_last4: bank.account_last4(),
+                        },
+                    );
                     let filtered_bank_details = filtered_bank_record(&bank);
                     HttpResponse::Created().json(filtered_bank_details)
                 }
                 Err(e) => {
-                    eprintln!("Failed to create bank details: {:?}", e);
+                    tracing::error!(error = ?e, "Failed to create bank details");
                     return HttpResponse::InternalServerError().json(json!({
                         "status": "error",
                         "message": format!("Failed to create bank details: {:?}", e)
@@ -262,7 +285,7 @@ async fn confirm_user_bank_account_handler(
                     }));
                 }
                 _ => {
-                    eprintln!("Failed to create wallet: {:?}", e);
+                    tracing::error!(error = ?e, "Failed to create bank details");
                     return HttpResponse::InternalServerError().json(json!({
                         "status": "error",
                         "message": format!("Failed to create bank details: {:?}", e)
@@ -279,22 +302,8 @@ async fn get_user_bank_accounts_handler(
     data: web::Data<AppState>,
     query: web::Query<GetBankAccountQuery>,
 ) -> impl Responder {
-    let expected_api_key = &data.env.hmac_key;
-    match req.headers().get("x-api-key") {
-        Some(provided_key) => {
-            if *provided_key != *expected_api_key {
-                return HttpResponse::Unauthorized().json(json!({
-                    "status": "error",
-                    "message": "Invalid API key"
-                }));
-            }
-        }
-        None => {
-            return HttpResponse::Unauthorized().json(json!({
-                "status": "error",
-                "message": "API key missing"
-            }));
-        }
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
     }
 
     let user_phone = query.phone.clone();
@@ -316,7 +325,7 @@ async fn get_user_bank_accounts_handler(
             HttpResponse::Ok().json(json_response)
         }
         Err(e) => {
-            eprintln!("Error fetching user bank accounts: {:?}", e);
+            tracing::error!(error = ?e, "Error fetching user bank accounts");
             HttpResponse::InternalServerError().json("Error fetching user bank accounts")
         }
     }
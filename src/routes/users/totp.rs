@@ -0,0 +1,148 @@
+use crate::{
+    AppState,
+    database::user_db::UserImpl,
+    helpers::{
+        otp_helpers::require_otp,
+        request_helpers::{check_api_key, user_lookup_error},
+        totp_helpers::{confirm_totp, enrol_totp, require_totp, totp_error_response},
+    },
+    middleware::security_log::record_security_event,
+    models::models::{
+        OtpPurpose, SecurityEventType, TotpConfirmSchema, TotpEnrolSchema, TotpQuery, User,
+    },
+    services::totp::authenticator::is_admin,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, post, web};
+use serde_json::json;
+
+/// Admins set up their second factor through `/admin/totp`, which needs the
+/// admin key, so the API key alone cannot bind an authenticator to them.
+fn refuse_admin(user: &User) -> Result<(), HttpResponse> {
+    if is_admin(user) {
+        return Err(HttpResponse::Forbidden().json(json!({
+            "status": "error",
+            "message": "Admins enrol two-factor authentication through /admin/totp"
+        })));
+    }
+    Ok(())
+}
+
+/// Starts enrolment. The secret is only confirmed, and TOTP only required,
+/// once a code from the authenticator app is sent to `/confirm`.
+///
+/// Needs a `contact_change` code, so only whoever holds the phone can bind
+/// an authenticator to the account.
+#[post("/users/me/totp/enrol")]
+async fn enrol_totp_handler(
+    req: HttpRequest,
+    body: web::Json<TotpEnrolSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = refuse_admin(&user) {
+        return response;
+    }
+    if let Err(response) = require_otp(&req, &data, &user, OtpPurpose::ContactChange) {
+        return response;
+    }
+
+    enrol_totp(&data, &user)
+}
+
+/// Turns TOTP on and returns the recovery codes, which are not shown again.
+#[post("/users/me/totp/confirm")]
+async fn confirm_totp_handler(
+    req: HttpRequest,
+    body: web::Json<TotpConfirmSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = refuse_admin(&user) {
+        return response;
+    }
+
+    confirm_totp(&req, &data, &user, &body.code)
+}
+
+#[delete("/users/me/totp")]
+async fn disable_totp_handler(
+    req: HttpRequest,
+    query: web::Query<TotpQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&query.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+
+    match data.totp.disable(&user) {
+        Ok(()) => {
+            record_security_event(&req, SecurityEventType::TotpDisabled, Some(user.id));
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Two-factor authentication disabled"
+            }))
+        }
+        Err(e) => totp_error_response(&e),
+    }
+}
+
+/// Replaces all recovery codes, for when they run low or may have leaked.
+#[post("/users/me/totp/recovery-codes")]
+async fn regenerate_recovery_codes_handler(
+    req: HttpRequest,
+    body: web::Json<TotpEnrolSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    if let Err(response) = require_totp(&req, &data, &user) {
+        return response;
+    }
+
+    match data.totp.regenerate_recovery_codes(&user) {
+        Ok(recovery_codes) => {
+            record_security_event(
+                &req,
+                SecurityEventType::RecoveryCodesRegenerated,
+                Some(user.id),
+            );
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "data": { "recovery_codes": recovery_codes }
+            }))
+        }
+        Err(e) => totp_error_response(&e),
+    }
+}
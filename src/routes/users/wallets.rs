@@ -0,0 +1,388 @@
+use crate::{
+    AppState,
+    database::{db::AppError, user_db::UserImpl, user_wallet_db::UserWalletImpl},
+    helpers::request_helpers::{check_api_key, user_lookup_error},
+    models::{
+        models::{
+            NewUserWallet, UpdateWalletSchema, UserWallet, UserWalletQuery, UserWalletSchema,
+            UserWalletsQuery, WalletChallengeSchema, WalletProofSchema,
+        },
+        response::FilteredWallet,
+    },
+    services::wallets::{address::normalize, ownership::OwnershipError},
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put, web};
+use diesel::result::DatabaseErrorKind;
+use serde_json::json;
+
+const MAX_LABEL_LENGTH: usize = 50;
+
+fn filtered_wallet_record(wallet: UserWallet) -> FilteredWallet {
+    FilteredWallet {
+        wallet_id: wallet.id,
+        user_id: wallet.user_id,
+        wallet_address: wallet.wallet_address.unwrap_or_default(),
+        network: wallet.network.unwrap_or_default(),
+        label: wallet.label,
+        primary: wallet.is_primary,
+        created_at: wallet.created_at,
+        updated_at: wallet.updated_at,
+        archived_at: wallet.archived_at,
+        verified: wallet.verified_at.is_some(),
+        verified_at: wallet.verified_at,
+    }
+}
+
+/// Trims a label, treating an empty one as none. Errs with the response to
+/// send when it is too long.
+fn clean_label(label: Option<&str>) -> Result<Option<String>, HttpResponse> {
+    let label = label.map(str::trim).filter(|label| !label.is_empty());
+    if label.is_some_and(|label| label.chars().count() > MAX_LABEL_LENGTH) {
+        return Err(HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("Label must be at most {} characters", MAX_LABEL_LENGTH)
+        })));
+    }
+    Ok(label.map(str::to_string))
+}
+
+fn wallet_not_found(e: AppError) -> HttpResponse {
+    match e {
+        AppError::DieselError(diesel::result::Error::NotFound) => {
+            HttpResponse::NotFound().json(json!({
+                "status": "error",
+                "message": "Wallet not found"
+            }))
+        }
+        e => {
+            tracing::error!(error = ?e, "Failed to update wallet");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to update wallet"
+            }))
+        }
+    }
+}
+
+/// Links a wallet to the user. The address is checked for its network and
+/// stored normalized, so the same wallet cannot be linked twice by writing
+/// it differently. A user may link any number of wallets; the first on each
+/// network becomes its primary one.
+#[post("/users/me/wallets")]
+async fn link_wallet_handler(
+    req: HttpRequest,
+    body: web::Json<UserWalletSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    let wallet_address = match normalize(body.network, &body.wallet_address) {
+        Ok(address) => address,
+        Err(e) => {
+            return HttpResponse::UnprocessableEntity().json(json!({
+                "status": "error",
+                "message": format!("Invalid {} address: {}", body.network.as_str(), e)
+            }));
+        }
+    };
+    let label = match clean_label(body.label.as_deref()) {
+        Ok(label) => label,
+        Err(response) => return response,
+    };
+
+    let network = body.network.as_str();
+    match data
+        .db
+        .get_conflicting_wallet(network, &wallet_address, &user.id)
+    {
+        Ok(None) => {}
+        Ok(Some(existing)) => return already_linked(existing.user_id == user.id),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to look up wallet");
+            return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to link wallet"
+            }));
+        }
+    }
+
+    let wallet = NewUserWallet {
+        user_id: user.id.clone(),
+        wallet_address: Some(wallet_address.clone()),
+        network: Some(network.to_string()),
+        label,
+        is_primary: body.primary,
+    };
+    match data.db.create_user_wallet(wallet) {
+        Ok(wallet) => HttpResponse::Created().json(json!({
+            "status": "success",
+            "data": { "wallet": filtered_wallet_record(wallet) }
+        })),
+        // Lost a race with another link of the same address.
+        Err(AppError::DieselError(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        ))) => match data
+            .db
+            .get_conflicting_wallet(network, &wallet_address, &user.id)
+        {
+            Ok(Some(existing)) => already_linked(existing.user_id == user.id),
+            _ => already_linked(false),
+        },
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to link wallet");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to link wallet"
+            }))
+        }
+    }
+}
+
+fn already_linked(to_this_user: bool) -> HttpResponse {
+    let message = if to_this_user {
+        "Wallet is already linked"
+    } else {
+        "Wallet is linked to another account"
+    };
+    HttpResponse::Conflict().json(json!({
+        "status": "error",
+        "message": message
+    }))
+}
+
+#[get("/users/me/wallets")]
+async fn list_wallets_handler(
+    req: HttpRequest,
+    query: web::Query<UserWalletsQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&query.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data
+        .db
+        .get_wallets_by_user_id(&user.id, query.include_archived)
+    {
+        Ok(wallets) => {
+            let wallets: Vec<FilteredWallet> =
+                wallets.into_iter().map(filtered_wallet_record).collect();
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "data": { "wallets": wallets }
+            }))
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to fetch wallets");
+            HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to fetch wallets"
+            }))
+        }
+    }
+}
+
+/// Relabels a wallet and, with `primary: true`, makes it the primary one on
+/// its network. Archived wallets cannot be made primary.
+#[put("/users/me/wallets/{id}")]
+async fn update_wallet_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateWalletSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+    let wallet_id = path.into_inner();
+
+    let mut wallet = match data.db.get_user_wallet(&user.id, &wallet_id) {
+        Ok(wallet) => wallet,
+        Err(e) => return wallet_not_found(e),
+    };
+
+    if body.label.is_some() {
+        let label = match clean_label(body.label.as_deref()) {
+            Ok(label) => label,
+            Err(response) => return response,
+        };
+        wallet = match data.db.update_wallet_label(&user.id, &wallet_id, label) {
+            Ok(wallet) => wallet,
+            Err(e) => return wallet_not_found(e),
+        };
+    }
+
+    if body.primary == Some(true) {
+        if wallet.archived_at.is_some() {
+            return HttpResponse::Conflict().json(json!({
+                "status": "error",
+                "message": "Archived wallets cannot be primary"
+            }));
+        }
+        wallet = match data.db.set_primary_wallet(&user.id, &wallet_id) {
+            Ok(wallet) => wallet,
+            Err(e) => return wallet_not_found(e),
+        };
+    }
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "data": { "wallet": filtered_wallet_record(wallet) }
+    }))
+}
+
+/// Archives a wallet. It stays in the user's history but no longer resolves
+/// to them, and its address can be linked again.
+#[delete("/users/me/wallets/{id}")]
+async fn archive_wallet_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<UserWalletQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&query.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+
+    match data.db.archive_user_wallet(&user.id, &path.into_inner()) {
+        Ok(wallet) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "data": { "wallet": filtered_wallet_record(wallet) }
+        })),
+        Err(e) => wallet_not_found(e),
+    }
+}
+
+fn ownership_error(e: OwnershipError) -> HttpResponse {
+    match e {
+        OwnershipError::AlreadyVerified => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": "Wallet is already verified"
+        })),
+        OwnershipError::NoChallenge => HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "No active challenge for this wallet; request a new one"
+        })),
+        OwnershipError::Signature(e) => HttpResponse::UnprocessableEntity().json(json!({
+            "status": "error",
+            "message": format!("Invalid signature: {}", e)
+        })),
+        OwnershipError::NoPublicKey => HttpResponse::UnprocessableEntity().json(json!({
+            "status": "error",
+            "message": "Could not read the account's public key; is it deployed?"
+        })),
+        OwnershipError::Unavailable => HttpResponse::ServiceUnavailable().json(json!({
+            "status": "error",
+            "message": "Verifying wallets on this network is not available"
+        })),
+        OwnershipError::Rpc(e) => {
+            tracing::error!(error = %e, "Starknet node request failed");
+            HttpResponse::BadGateway().json(json!({
+                "status": "error",
+                "message": "Failed to verify wallet"
+            }))
+        }
+        OwnershipError::Db(e) => wallet_not_found(e),
+    }
+}
+
+/// The user's wallet if it is not archived, else the response to send.
+fn active_wallet(
+    data: &AppState,
+    user_id: &str,
+    wallet_id: &str,
+) -> Result<UserWallet, HttpResponse> {
+    match data.db.get_user_wallet(user_id, wallet_id) {
+        Ok(wallet) if wallet.archived_at.is_none() => Ok(wallet),
+        Ok(_) => Err(HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": "Wallet not found"
+        }))),
+        Err(e) => Err(wallet_not_found(e)),
+    }
+}
+
+/// Issues a challenge for the wallet to sign, replacing any earlier one.
+/// EVM wallets `personal_sign` the returned message; Starknet accounts sign
+/// the returned typed data with `signMessage`.
+#[post("/users/me/wallets/{id}/challenge")]
+async fn wallet_challenge_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<WalletChallengeSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+    let wallet = match active_wallet(&data, &user.id, &path.into_inner()) {
+        Ok(wallet) => wallet,
+        Err(response) => return response,
+    };
+
+    match data.wallets.challenge(&wallet) {
+        Ok((challenge, expires_at)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "data": { "challenge": challenge, "expiresAt": expires_at }
+        })),
+        Err(e) => ownership_error(e),
+    }
+}
+
+/// Marks the wallet verified if the signature answers its challenge.
+#[post("/users/me/wallets/{id}/verify")]
+async fn verify_wallet_handler(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<WalletProofSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(response) = check_api_key(&req, &data) {
+        return response;
+    }
+
+    let user = match data.db.get_user_by_phone(&body.phone) {
+        Ok(user) => user,
+        Err(e) => return user_lookup_error(e),
+    };
+    let wallet = match active_wallet(&data, &user.id, &path.into_inner()) {
+        Ok(wallet) => wallet,
+        Err(response) => return response,
+    };
+
+    match data.wallets.verify(&wallet, &body.signature).await {
+        Ok(wallet) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "data": { "wallet": filtered_wallet_record(wallet) }
+        })),
+        Err(e) => ownership_error(e),
+    }
+}
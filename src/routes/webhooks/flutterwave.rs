@@ -0,0 +1,76 @@
+use crate::{
+    AppState,
+    services::flutterwave::webhooks::{WebhookError, verify_hash},
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use serde_json::json;
+
+/// Flutterwave events. Each is stored before it is handled, and one already
+/// received is acknowledged without being handled again.
+#[post("/webhooks/flutterwave")]
+pub async fn flutterwave_webhook_handler(
+    req: HttpRequest,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let secret_hash = data
+        .env
+        .providers
+        .flutterwave
+        .secret_hash
+        .as_ref()
+        .filter(|hash| !hash.is_empty());
+    let Some(secret_hash) = secret_hash else {
+        return HttpResponse::ServiceUnavailable().json(json!({
+            "status": "error",
+            "message": "Flutterwave webhook is not enabled"
+        }));
+    };
+
+    let provided = req
+        .headers()
+        .get("verif-hash")
+        .and_then(|value| value.to_str().ok());
+    if !verify_hash(secret_hash.expose(), provided) {
+        return HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid verif-hash"
+        }));
+    }
+
+    let event = match data.flutterwave.receive(&body) {
+        Ok(Some(event)) => event,
+        Ok(None) => {
+            return HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": "Event already received"
+            }));
+        }
+        Err(WebhookError::Payload(e)) => {
+            tracing::warn!(error = %e, "Unreadable Flutterwave webhook");
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Invalid payload"
+            }));
+        }
+        Err(e) => {
+            // Not stored, so Flutterwave's retry will be handled.
+            tracing::error!(error = %e, "Failed to store Flutterwave webhook");
+            return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "Failed to store event"
+            }));
+        }
+    };
+
+    // Handler failures stay on the stored event for a replay; a non-2xx
+    // would only have Flutterwave resend an event we already hold.
+    if let Err(e) = data.flutterwave.process(&event) {
+        tracing::error!(event_id = %event.event_id, error = ?e, "Failed to process Flutterwave webhook");
+    }
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "Event received"
+    }))
+}
@@ -0,0 +1,2 @@
+pub mod flutterwave;
+pub mod whatsapp;
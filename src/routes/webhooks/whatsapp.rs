@@ -0,0 +1,537 @@
+use crate::{
+    AppState,
+    database::{
+        db::AppError, user_bank_account_db::UserBankImpl, user_db::UserImpl,
+        whatsapp_conversation_db::WhatsappConversationImpl,
+    },
+    helpers::bank_helpers::{fetch_banks_via_flutterwave, verify_account_via_flutterwave},
+    middleware::security_log::record_whatsapp_event,
+    models::models::{
+        ChannelKind, NewUser, NewUserBankAccount, NewWhatsappConversation, OtpPurpose,
+        SecurityEventType, User, WhatsappSubscriptionQuery,
+    },
+    services::{
+        notifications::{
+            channel::{Notification, msisdn},
+            templates::BankAddedMessage,
+        },
+        otp::issuer::OtpError,
+        totp::authenticator::{TotpCheck, TotpError},
+        whatsapp::{
+            conversation::{self, Command, ConversationState, find_bank, parse_bank_details},
+            webhook::{TextMessage, UnsupportedMessage, WebhookPayload, verify_signature},
+        },
+    },
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use serde_json::json;
+use subtle::ConstantTimeEq;
+
+/// Meta's check when the webhook is subscribed: echo `hub.challenge` if the
+/// verify token matches.
+#[get("/webhooks/whatsapp")]
+pub async fn whatsapp_subscription_handler(
+    query: web::Query<WhatsappSubscriptionQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Some(verify_token) = data.env.notifications.whatsapp.verify_token.as_ref() else {
+        return webhook_disabled();
+    };
+
+    let token_matches: bool = query
+        .verify_token
+        .as_bytes()
+        .ct_eq(verify_token.expose().as_bytes())
+        .into();
+    if query.mode != "subscribe" || !token_matches {
+        return HttpResponse::Forbidden().json(json!({
+            "status": "error",
+            "message": "Invalid verify token"
+        }));
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(query.challenge.clone())
+}
+
+/// Inbound WhatsApp messages. Each moves its sender's conversation on one
+/// step and gets a reply over WhatsApp.
+#[post("/webhooks/whatsapp")]
+pub async fn whatsapp_webhook_handler(
+    req: HttpRequest,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let Some(app_secret) = data.env.notifications.whatsapp.app_secret.as_ref() else {
+        return webhook_disabled();
+    };
+
+    let signature = req
+        .headers()
+        .get("x-hub-signature-256")
+        .and_then(|value| value.to_str().ok());
+    if !verify_signature(app_secret.expose(), signature, &body) {
+        return HttpResponse::Unauthorized().json(json!({
+            "status": "error",
+            "message": "Invalid signature"
+        }));
+    }
+
+    let payload: WebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!(error = %e, "Unreadable WhatsApp webhook");
+            return HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": "Invalid payload"
+            }));
+        }
+    };
+
+    for message in payload.messages() {
+        handle_message(&req, &data, message).await;
+    }
+
+    // Failures are answered in the chat; a non-2xx would only make Meta
+    // redeliver messages that were already handled.
+    HttpResponse::Ok().finish()
+}
+
+fn webhook_disabled() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(json!({
+        "status": "error",
+        "message": "WhatsApp webhook is not enabled"
+    }))
+}
+
+async fn handle_message(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    message: Result<TextMessage, UnsupportedMessage>,
+) {
+    let (from, message_id, text) = match message {
+        Ok(message) => (message.from, message.id, Some(message.body)),
+        Err(message) => (message.from, message.id, None),
+    };
+    let phone = format!("+{}", msisdn(&from));
+
+    let state = match data.db.get_whatsapp_conversation(&phone) {
+        Ok(conversation) if conversation.last_message_id.as_ref() == Some(&message_id) => {
+            return;
+        }
+        Ok(conversation) => serde_json::from_value(conversation.state)
+            .inspect_err(
+                |e| tracing::warn!(error = %e, "Discarding unreadable WhatsApp conversation state"),
+            )
+            .ok(),
+        Err(AppError::DieselError(diesel::result::Error::NotFound)) => None,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to load WhatsApp conversation");
+            return;
+        }
+    };
+
+    let user = match find_or_create_user(req, data, &phone) {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to load user for WhatsApp message");
+            return;
+        }
+    };
+
+    let (state, reply) = match text {
+        Some(text) => step(req, data, &user, state, &text).await,
+        None => (
+            state.unwrap_or(ConversationState::Menu),
+            conversation::UNSUPPORTED.to_string(),
+        ),
+    };
+
+    let saved = serde_json::to_value(&state)
+        .map_err(|e| e.to_string())
+        .and_then(|state| {
+            data.db
+                .save_whatsapp_conversation(NewWhatsappConversation {
+                    phone: phone.clone(),
+                    user_id: user.id.clone(),
+                    state,
+                    last_message_id: Some(message_id),
+                })
+                .map_err(|e| format!("{:?}", e))
+        });
+    if let Err(e) = saved {
+        tracing::error!(error = %e, "Failed to save WhatsApp conversation");
+    }
+
+    // The user just wrote to us, so a free-text reply is allowed.
+    let reply = Notification {
+        subject: String::new(),
+        body: reply,
+        whatsapp: None,
+    };
+    if let Err(e) = data
+        .notifier
+        .send_on(ChannelKind::Whatsapp, &user, &reply)
+        .await
+    {
+        tracing::error!(user_id = %user.id, error = %e, "Failed to send WhatsApp reply");
+    }
+}
+
+/// The sender's account, created unverified on their first message.
+fn find_or_create_user(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    phone: &str,
+) -> Result<User, AppError> {
+    match data.db.get_user_by_phone(phone) {
+        Err(AppError::DieselError(diesel::result::Error::NotFound)) => {
+            let user = data.db.create_user(NewUser {
+                id: uuid::Uuid::new_v4().simple().to_string(),
+                phone: phone.to_string(),
+                verified: false,
+                role: String::from("user"),
+            })?;
+            record_whatsapp_event(
+                req,
+                SecurityEventType::AccountCreated,
+                Some(user.id.clone()),
+            );
+            Ok(user)
+        }
+        result => result,
+    }
+}
+
+/// Moves the conversation on by one message, returning the new state and
+/// the reply.
+async fn step(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    user: &User,
+    state: Option<ConversationState>,
+    text: &str,
+) -> (ConversationState, String) {
+    let command = Command::parse(text);
+
+    if !user.verified {
+        return match (state, command) {
+            (Some(ConversationState::AwaitingOtp), Some(Command::Resend)) => {
+                issue_login_otp(req, data, user, conversation::OTP_RESENT)
+            }
+            (Some(ConversationState::AwaitingOtp), Some(_)) => (
+                ConversationState::AwaitingOtp,
+                conversation::ENTER_OTP.to_string(),
+            ),
+            (Some(ConversationState::AwaitingOtp), None) => verify_login_otp(req, data, user, text),
+            _ => issue_login_otp(req, data, user, conversation::WELCOME),
+        };
+    }
+
+    match (state.unwrap_or(ConversationState::Menu), command) {
+        (_, Some(Command::Menu)) | (ConversationState::AwaitingOtp, _) => menu(),
+        (ConversationState::Menu, Some(Command::AddBank)) => match can_add_bank(data, user) {
+            Ok(()) => (
+                ConversationState::AwaitingBankDetails,
+                conversation::ENTER_BANK_DETAILS.to_string(),
+            ),
+            Err(reply) => (ConversationState::Menu, reply.to_string()),
+        },
+        (ConversationState::Menu, Some(Command::ListBanks)) => {
+            (ConversationState::Menu, list_banks(data, user))
+        }
+        (ConversationState::Menu, _) => menu(),
+        (ConversationState::AwaitingBankDetails, _) => look_up_bank_account(data, text).await,
+        (confirmation @ ConversationState::AwaitingBankConfirmation { .. }, Some(Command::Yes)) => {
+            issue_bank_otp(req, data, user, confirmation, conversation::BANK_OTP_SENT)
+        }
+        (ConversationState::AwaitingBankConfirmation { .. }, Some(Command::No)) => (
+            ConversationState::Menu,
+            format!("{}\n\n{}", conversation::BANK_CANCELLED, conversation::MENU),
+        ),
+        (confirmation @ ConversationState::AwaitingBankConfirmation { .. }, _) => {
+            (confirmation, conversation::CONFIRM_YES_NO.to_string())
+        }
+        (awaiting @ ConversationState::AwaitingBankOtp { .. }, Some(Command::Resend)) => {
+            issue_bank_otp(req, data, user, awaiting, conversation::OTP_RESENT)
+        }
+        (awaiting @ ConversationState::AwaitingBankOtp { .. }, Some(_)) => {
+            (awaiting, conversation::ENTER_BANK_OTP.to_string())
+        }
+        (awaiting @ ConversationState::AwaitingBankOtp { .. }, None) => {
+            add_bank(req, data, user, awaiting, text)
+        }
+    }
+}
+
+fn menu() -> (ConversationState, String) {
+    (ConversationState::Menu, conversation::MENU.to_string())
+}
+
+fn issue_login_otp(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    user: &User,
+    reply: &str,
+) -> (ConversationState, String) {
+    let reply = match data.otp.issue(user, OtpPurpose::Login) {
+        Ok(()) => {
+            record_whatsapp_event(req, SecurityEventType::OtpSent, Some(user.id.clone()));
+            reply
+        }
+        Err(OtpError::ResendTooSoon { .. }) => conversation::OTP_TOO_SOON,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to issue WhatsApp login OTP");
+            conversation::FAILED
+        }
+    };
+    (ConversationState::AwaitingOtp, reply.to_string())
+}
+
+fn verify_login_otp(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    user: &User,
+    code: &str,
+) -> (ConversationState, String) {
+    // Wrong codes count towards the account lockout here as in the app.
+    if data.lockout.check_user(user).is_err() {
+        return (
+            ConversationState::AwaitingOtp,
+            conversation::LOCKED.to_string(),
+        );
+    }
+
+    let reply = match data.otp.verify(&user.id, OtpPurpose::Login, code) {
+        Ok(()) => match data.db.mark_user_verified(&user.id) {
+            Ok(_) => {
+                return (
+                    ConversationState::Menu,
+                    format!("{}\n\n{}", conversation::VERIFIED, conversation::MENU),
+                );
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to mark user verified");
+                conversation::FAILED
+            }
+        },
+        Err(e) => otp_failure_reply(req, user, e),
+    };
+    (ConversationState::AwaitingOtp, reply.to_string())
+}
+
+/// The reply to a code that did not verify. Wrong codes are recorded as
+/// `OtpFailed`, which counts towards the account lockout.
+fn otp_failure_reply(req: &HttpRequest, user: &User, e: OtpError) -> &'static str {
+    match e {
+        OtpError::Invalid | OtpError::AttemptsExhausted => {
+            record_whatsapp_event(req, SecurityEventType::OtpFailed, Some(user.id.clone()));
+            if matches!(e, OtpError::Invalid) {
+                conversation::OTP_INVALID
+            } else {
+                conversation::OTP_EXHAUSTED
+            }
+        }
+        e => {
+            tracing::error!(error = ?e, "Failed to verify WhatsApp OTP");
+            conversation::FAILED
+        }
+    }
+}
+
+/// Banks can only be added here by users without TOTP, as a chat has no
+/// second factor to give, and not while the account is locked.
+fn can_add_bank(data: &web::Data<AppState>, user: &User) -> Result<(), &'static str> {
+    if !data.env.providers.flutterwave.enabled {
+        return Err(conversation::BANK_UNAVAILABLE);
+    }
+    if data.lockout.check_user(user).is_err() {
+        return Err(conversation::LOCKED);
+    }
+
+    match data.totp.check(user, None) {
+        Ok(TotpCheck::NotRequired) => Ok(()),
+        Ok(_) | Err(TotpError::CodeRequired | TotpError::EnrolmentRequired) => {
+            Err(conversation::BANK_NEEDS_APP)
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to check TOTP");
+            Err(conversation::FAILED)
+        }
+    }
+}
+
+async fn look_up_bank_account(
+    data: &web::Data<AppState>,
+    text: &str,
+) -> (ConversationState, String) {
+    let awaiting = |reply: String| (ConversationState::AwaitingBankDetails, reply);
+
+    let Some((account_number, bank_name)) = parse_bank_details(text) else {
+        return awaiting(conversation::BANK_DETAILS_INVALID.to_string());
+    };
+
+    let banks = match fetch_banks_via_flutterwave(data).await {
+        Ok(banks) => banks,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to fetch banks");
+            return (
+                ConversationState::Menu,
+                conversation::BANK_UNAVAILABLE.to_string(),
+            );
+        }
+    };
+    let Some(bank) = find_bank(&banks, &bank_name) else {
+        return awaiting(conversation::bank_not_found(&bank_name));
+    };
+
+    match verify_account_via_flutterwave(data, &account_number, &bank.code).await {
+        Ok(details) => (
+            ConversationState::AwaitingBankConfirmation {
+                bank_name: bank.name.clone(),
+                bank_code: bank.code.clone(),
+                account_number: details.account_number.clone(),
+                account_name: details.account_name.clone(),
+            },
+            conversation::confirm_account(
+                &details.account_name,
+                &details.account_number,
+                &bank.name,
+            ),
+        ),
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to verify bank account");
+            awaiting(conversation::account_not_found(&account_number, &bank.name))
+        }
+    }
+}
+
+/// Sends a `bank_add` code for the confirmed account, as the app asks for
+/// one before adding a bank.
+fn issue_bank_otp(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    user: &User,
+    confirmed: ConversationState,
+    reply: &str,
+) -> (ConversationState, String) {
+    let (ConversationState::AwaitingBankConfirmation {
+        bank_name,
+        bank_code,
+        account_number,
+        account_name,
+    }
+    | ConversationState::AwaitingBankOtp {
+        bank_name,
+        bank_code,
+        account_number,
+        account_name,
+    }) = confirmed
+    else {
+        return menu();
+    };
+
+    if let Err(reply) = can_add_bank(data, user) {
+        return (ConversationState::Menu, reply.to_string());
+    }
+
+    let reply = match data.otp.issue(user, OtpPurpose::BankAdd) {
+        Ok(()) => {
+            record_whatsapp_event(req, SecurityEventType::OtpSent, Some(user.id.clone()));
+            reply
+        }
+        Err(OtpError::ResendTooSoon { .. }) => conversation::OTP_TOO_SOON,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to issue WhatsApp bank OTP");
+            return (ConversationState::Menu, conversation::FAILED.to_string());
+        }
+    };
+    (
+        ConversationState::AwaitingBankOtp {
+            bank_name,
+            bank_code,
+            account_number,
+            account_name,
+        },
+        reply.to_string(),
+    )
+}
+
+fn add_bank(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    user: &User,
+    awaiting: ConversationState,
+    code: &str,
+) -> (ConversationState, String) {
+    if !matches!(awaiting, ConversationState::AwaitingBankOtp { .. }) {
+        return menu();
+    }
+
+    // TOTP may have been turned on, or the account locked, since the
+    // account was looked up.
+    if let Err(reply) = can_add_bank(data, user) {
+        return (ConversationState::Menu, reply.to_string());
+    }
+    if let Err(e) = data.otp.verify(&user.id, OtpPurpose::BankAdd, code) {
+        let reply = otp_failure_reply(req, user, e);
+        return (awaiting, reply.to_string());
+    }
+    let ConversationState::AwaitingBankOtp {
+        bank_name,
+        account_number,
+        account_name,
+        ..
+    } = awaiting
+    else {
+        return menu();
+    };
+
+    let bank = match data.db.create_user_bank(NewUserBankAccount {
+        user_id: user.id.clone(),
+        bank_name,
+        account_number,
+        account_name: Some(account_name),
+        phone: Some(user.phone.clone()),
+    }) {
+        Ok(bank) => bank,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to create bank details");
+            return (ConversationState::Menu, conversation::FAILED.to_string());
+        }
+    };
+
+    record_whatsapp_event(req, SecurityEventType::BankAdded, Some(user.id.clone()));
+    data.notifier.dispatch(
+        user,
+        BankAddedMessage {
+            bank_name: bank.bank_name.clone(),
+            account_last4: bank.account_last4(),
+        },
+    );
+
+    (
+        ConversationState::Menu,
+        conversation::bank_added(&bank.bank_name, &bank.account_number),
+    )
+}
+
+fn list_banks(data: &web::Data<AppState>, user: &User) -> String {
+    match data.db.get_banks_by_user_id(&user.id) {
+        Ok(banks) if banks.is_empty() => conversation::NO_BANKS.to_string(),
+        Ok(banks) => format!(
+            "{}\n\n{}",
+            conversation::bank_list(
+                banks
+                    .iter()
+                    .map(|bank| (bank.bank_name.as_str(), bank.account_number.as_str()))
+            ),
+            conversation::MENU
+        ),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to fetch user bank accounts");
+            conversation::FAILED.to_string()
+        }
+    }
+}
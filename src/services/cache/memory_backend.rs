@@ -0,0 +1,118 @@
+use super::store::{CacheBackend, CacheError, WindowCount};
+use async_trait::async_trait;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How often expired entries are swept out.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    value: String,
+    expires_at: Instant,
+}
+
+/// Per-process fallback used when Redis is not configured. Each replica keeps
+/// its own copy, so limits and caches are not shared between them. Holds at
+/// most `capacity` keys, evicting the least recently used first.
+pub struct MemoryBackend {
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+impl MemoryBackend {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        MemoryBackend {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, LruCache<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Drops every expired entry.
+    fn sweep(&self) {
+        let now = Instant::now();
+        let mut entries = self.entries();
+
+        let expired: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            entries.pop(&key);
+        }
+    }
+
+    /// Sweeps expired entries every `SWEEP_INTERVAL` until the backend is
+    /// dropped.
+    pub fn start_sweeper(self: &Arc<Self>) {
+        let backend = Arc::downgrade(self);
+
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match backend.upgrade() {
+                    Some(backend) => backend.sweep(),
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let now = Instant::now();
+
+        Ok(self
+            .entries()
+            .get(key)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
+        self.entries().push(
+            key.to_string(),
+            Entry {
+                value: value.to_string(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.entries().pop(key);
+
+        Ok(())
+    }
+
+    async fn incr_window(&self, key: &str, window: Duration) -> Result<WindowCount, CacheError> {
+        let now = Instant::now();
+        let mut entries = self.entries();
+
+        let entry = entries.get_or_insert_mut(key.to_string(), || Entry {
+            value: "0".to_string(),
+            expires_at: now + window,
+        });
+        if entry.expires_at <= now {
+            entry.value = "0".to_string();
+            entry.expires_at = now + window;
+        }
+
+        let count = entry.value.parse::<u64>().unwrap_or(0) + 1;
+        entry.value = count.to_string();
+
+        Ok(WindowCount {
+            count,
+            resets_in: entry.expires_at - now,
+        })
+    }
+}
@@ -0,0 +1,3 @@
+pub mod memory_backend;
+pub mod redis_backend;
+pub mod store;
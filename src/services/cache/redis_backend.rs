@@ -0,0 +1,76 @@
+use super::store::{CacheBackend, CacheError, WindowCount};
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// A fixed pool of multiplexed connections handed out round-robin. Each
+/// `ConnectionManager` reconnects on its own if Redis drops it.
+pub struct RedisBackend {
+    connections: Vec<ConnectionManager>,
+    next: AtomicUsize,
+}
+
+impl RedisBackend {
+    pub async fn connect(url: &str, pool_size: usize) -> Result<Self, CacheError> {
+        let client = redis::Client::open(url)?;
+        let mut connections = Vec::with_capacity(pool_size.max(1));
+
+        for _ in 0..pool_size.max(1) {
+            connections.push(client.get_connection_manager().await?);
+        }
+
+        Ok(RedisBackend {
+            connections,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn conn(&self) -> ConnectionManager {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        self.connections[index].clone()
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisBackend {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self.conn().get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
+        let _: () = self.conn().set_ex(key, value, ttl.as_secs().max(1)).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        let _: () = self.conn().del(key).await?;
+
+        Ok(())
+    }
+
+    async fn incr_window(&self, key: &str, window: Duration) -> Result<WindowCount, CacheError> {
+        // SET NX starts the window with its expiry; INCR then counts the hit,
+        // all inside one MULTI so replicas never race on the TTL.
+        let (count, ttl): (u64, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(0)
+            .arg("EX")
+            .arg(window.as_secs().max(1))
+            .arg("NX")
+            .ignore()
+            .incr(key, 1)
+            .ttl(key)
+            .query_async(&mut self.conn())
+            .await?;
+
+        Ok(WindowCount {
+            count,
+            resets_in: Duration::from_secs(ttl.max(0) as u64),
+        })
+    }
+}
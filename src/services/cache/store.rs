@@ -0,0 +1,107 @@
+use super::{memory_backend::MemoryBackend, redis_backend::RedisBackend};
+use crate::config::config::RedisConfig;
+use async_trait::async_trait;
+use serde::{Serialize, de::DeserializeOwned};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum CacheError {
+    Redis(redis::RedisError),
+    Serialization(serde_json::Error),
+}
+
+impl From<redis::RedisError> for CacheError {
+    fn from(e: redis::RedisError) -> Self {
+        CacheError::Redis(e)
+    }
+}
+
+impl From<serde_json::Error> for CacheError {
+    fn from(e: serde_json::Error) -> Self {
+        CacheError::Serialization(e)
+    }
+}
+
+/// Hits recorded in the current fixed window for one counter.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowCount {
+    pub count: u64,
+    pub resets_in: Duration,
+}
+
+/// Storage behind `Cache`. Implemented by Redis, shared by every replica, and
+/// by an in-process map used when no Redis url is configured.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError>;
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError>;
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError>;
+
+    /// Increments a counter whose window starts on the first hit and lasts
+    /// `window`; the count resets once the window expires.
+    async fn incr_window(&self, key: &str, window: Duration) -> Result<WindowCount, CacheError>;
+}
+
+/// Shared cache and counter store. Cheap to clone; all clones share storage.
+#[derive(Clone)]
+pub struct Cache {
+    backend: Arc<dyn CacheBackend>,
+    key_prefix: String,
+}
+
+impl Cache {
+    pub async fn connect(config: &RedisConfig) -> Result<Self, CacheError> {
+        let backend: Arc<dyn CacheBackend> = match &config.url {
+            Some(url) => Arc::new(RedisBackend::connect(url.expose(), config.pool_size).await?),
+            None => {
+                let capacity =
+                    NonZeroUsize::new(config.memory_capacity).unwrap_or(NonZeroUsize::MIN);
+                let backend = Arc::new(MemoryBackend::new(capacity));
+                backend.start_sweeper();
+                backend
+            }
+        };
+
+        Ok(Cache {
+            backend,
+            key_prefix: config.key_prefix.clone(),
+        })
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.key_prefix, key)
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, CacheError> {
+        match self.backend.get(&self.key(key)).await? {
+            Some(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_json<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<(), CacheError> {
+        let raw = serde_json::to_string(value)?;
+        self.backend.set(&self.key(key), &raw, ttl).await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.backend.delete(&self.key(key)).await
+    }
+
+    pub async fn incr_window(
+        &self,
+        key: &str,
+        window: Duration,
+    ) -> Result<WindowCount, CacheError> {
+        self.backend.incr_window(&self.key(key), window).await
+    }
+}
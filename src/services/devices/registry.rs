@@ -0,0 +1,82 @@
+use crate::database::db::{AppError, Database};
+use crate::database::user_device_db::UserDeviceImpl;
+use crate::models::models::{NewUserDevice, UserDevice};
+use std::sync::Arc;
+
+/// Longest accepted `X-Device-Id`, matching the `user_devices.device_id` column.
+const MAX_DEVICE_ID_LEN: usize = 128;
+
+/// Called when a user signs in from a device they have not used before;
+/// `Notifier` alerts the user.
+pub trait NewDeviceHook: Send + Sync {
+    fn on_new_device(&self, device: &UserDevice);
+}
+
+/// Keeps track of the devices each user signs in from and which of them
+/// have been trusted.
+#[derive(Clone)]
+pub struct DeviceRegistry {
+    db: Database,
+    hook: Arc<dyn NewDeviceHook>,
+}
+
+impl DeviceRegistry {
+    pub fn new(db: Database, hook: Arc<dyn NewDeviceHook>) -> Self {
+        DeviceRegistry { db, hook }
+    }
+
+    /// Records a completed sign-in from `device` and trusts it, returning it
+    /// and whether it is new to the user. Sign-ins only complete from a
+    /// device that is already trusted or once the user has confirmed an OTP
+    /// on it, so later sign-ins from it skip the OTP. The hook runs for new
+    /// devices.
+    pub fn sign_in(&self, device: NewUserDevice) -> Result<(UserDevice, bool), AppError> {
+        let (mut device, is_new) = self.db.touch_user_device(device)?;
+        if !device.trusted {
+            self.db
+                .trust_user_device(&device.user_id, &device.device_id)?;
+            device.trusted = true;
+        }
+        if is_new {
+            self.hook.on_new_device(&device);
+        }
+        Ok((device, is_new))
+    }
+
+    /// Whether a sign-in from `device_id` must be confirmed with an OTP,
+    /// whatever else (a refresh token, say) the client presents. Only a
+    /// known, trusted, unrevoked device is exempt.
+    pub fn requires_otp(&self, user_id: &str, device_id: Option<&str>) -> Result<bool, AppError> {
+        let Some(device_id) = device_id else {
+            return Ok(true);
+        };
+
+        match self.db.get_user_device(user_id, device_id) {
+            Ok(device) => Ok(!device.trusted || device.revoked_at.is_some()),
+            Err(AppError::DieselError(diesel::result::Error::NotFound)) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn list(&self, user_id: &str) -> Result<Vec<UserDevice>, AppError> {
+        self.db.get_user_devices(user_id)
+    }
+
+    /// Revokes the device and the tokens issued to it.
+    pub fn revoke(&self, user_id: &str, id: uuid::Uuid) -> Result<bool, AppError> {
+        self.db.revoke_user_device(user_id, id)
+    }
+}
+
+/// The client's device identifier from `X-Device-Id`, if it is present and
+/// well formed: up to 128 ASCII letters, digits, `-`, `_` or `.`.
+pub fn device_id_from_header(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_DEVICE_ID_LEN)
+        .filter(|id| {
+            id.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        })
+        .map(str::to_string)
+}
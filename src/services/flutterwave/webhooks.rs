@@ -0,0 +1,188 @@
+use crate::database::db::{AppError, Database};
+use crate::database::flutterwave_event_db::FlutterwaveEventImpl;
+use crate::database::transaction_db::TransactionImpl;
+use crate::models::models::{FlutterwaveEvent, NewFlutterwaveEvent};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+
+/// Checks the `verif-hash` header, which Flutterwave sets to the secret hash
+/// configured in its dashboard. The comparison is constant time.
+pub fn verify_hash(secret_hash: &str, provided: Option<&str>) -> bool {
+    provided.is_some_and(|provided| provided.as_bytes().ct_eq(secret_hash.as_bytes()).into())
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    /// Not a v3 webhook, or one without a `data.id` to key it by.
+    Payload(String),
+    /// A completed or failed transfer with no transaction to settle. Left
+    /// unprocessed so it can be replayed once the transaction exists.
+    UnknownTransfer(String),
+    /// A transfer event in a state other than `SUCCESSFUL` or `FAILED`.
+    UnexpectedStatus(String),
+    Db(AppError),
+}
+
+impl From<AppError> for WebhookError {
+    fn from(e: AppError) -> Self {
+        WebhookError::Db(e)
+    }
+}
+
+impl std::fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::Payload(e) => write!(f, "invalid payload: {}", e),
+            WebhookError::UnknownTransfer(reference) => {
+                write!(f, "no transaction has reference {}", reference)
+            }
+            WebhookError::UnexpectedStatus(status) => {
+                write!(f, "unexpected transfer status {}", status)
+            }
+            WebhookError::Db(e) => write!(f, "database error: {:?}", e),
+        }
+    }
+}
+
+/// What a handler did with an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventOutcome {
+    TransferCompleted,
+    TransferFailed,
+    /// A transfer event for a transaction that has already completed.
+    AlreadySettled,
+    /// An event type nothing here handles.
+    Ignored,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    event: String,
+    data: EnvelopeData,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnvelopeData {
+    id: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferData {
+    reference: String,
+    status: String,
+    complete_message: Option<String>,
+}
+
+/// Flutterwave webhooks: stored as received, keyed by event type and the ID
+/// of the object they are about, then handed to a handler.
+///
+/// Flutterwave retries a webhook until it gets a 200 and may send the same
+/// event more than once, so a stored event is never handled again on
+/// receipt. One that failed stays unprocessed with its error, for an admin
+/// to replay.
+#[derive(Clone)]
+pub struct FlutterwaveWebhooks {
+    db: Database,
+}
+
+impl FlutterwaveWebhooks {
+    pub fn new(db: Database) -> Self {
+        FlutterwaveWebhooks { db }
+    }
+
+    /// Stores an event, returning `None` if it was already received.
+    pub fn receive(&self, body: &[u8]) -> Result<Option<FlutterwaveEvent>, WebhookError> {
+        let payload: serde_json::Value =
+            serde_json::from_slice(body).map_err(|e| WebhookError::Payload(e.to_string()))?;
+        let envelope: Envelope = serde_json::from_value(payload.clone())
+            .map_err(|e| WebhookError::Payload(e.to_string()))?;
+
+        let object_id = match envelope.data.id {
+            serde_json::Value::Number(id) => id.to_string(),
+            serde_json::Value::String(id) if !id.is_empty() => id,
+            _ => return Err(WebhookError::Payload("data.id is missing".to_string())),
+        };
+
+        Ok(self.db.store_flutterwave_event(NewFlutterwaveEvent {
+            event_id: format!("{}:{}", envelope.event, object_id),
+            event_type: envelope.event,
+            payload,
+        })?)
+    }
+
+    /// Runs the event's handler and records the attempt, returning the
+    /// event as updated: processed, or with the handler's error.
+    pub fn process(&self, event: &FlutterwaveEvent) -> Result<FlutterwaveEvent, AppError> {
+        let error = match self.handle(event) {
+            Ok(outcome) => {
+                tracing::info!(event_id = %event.event_id, ?outcome, "Processed Flutterwave event");
+                None
+            }
+            Err(e) => {
+                tracing::warn!(event_id = %event.event_id, error = %e, "Flutterwave event failed");
+                Some(e.to_string())
+            }
+        };
+
+        self.db
+            .record_flutterwave_event_attempt(&event.event_id, error)
+    }
+
+    fn handle(&self, event: &FlutterwaveEvent) -> Result<EventOutcome, WebhookError> {
+        match event.event_type.as_str() {
+            "transfer.completed" => {
+                let transfer: TransferData = serde_json::from_value(event.payload["data"].clone())
+                    .map_err(|e| WebhookError::Payload(e.to_string()))?;
+
+                match transfer.status.as_str() {
+                    "SUCCESSFUL" => self.on_transfer_completed(&transfer),
+                    "FAILED" => self.on_transfer_failed(&transfer),
+                    status => Err(WebhookError::UnexpectedStatus(status.to_string())),
+                }
+            }
+            _ => Ok(EventOutcome::Ignored),
+        }
+    }
+
+    /// The payout reached the user's bank.
+    fn on_transfer_completed(&self, transfer: &TransferData) -> Result<EventOutcome, WebhookError> {
+        if !self.settle(transfer, "completed", Some(Utc::now()))? {
+            return Ok(EventOutcome::AlreadySettled);
+        }
+        tracing::info!(reference = %transfer.reference, "Flutterwave transfer completed");
+        Ok(EventOutcome::TransferCompleted)
+    }
+
+    fn on_transfer_failed(&self, transfer: &TransferData) -> Result<EventOutcome, WebhookError> {
+        if !self.settle(transfer, "failed", None)? {
+            return Ok(EventOutcome::AlreadySettled);
+        }
+        tracing::warn!(
+            reference = %transfer.reference,
+            reason = transfer.complete_message.as_deref().unwrap_or_default(),
+            "Flutterwave transfer failed"
+        );
+        Ok(EventOutcome::TransferFailed)
+    }
+
+    /// Returns false if the transaction had already completed and was left
+    /// as it was.
+    fn settle(
+        &self,
+        transfer: &TransferData,
+        status: &str,
+        settled_at: Option<DateTime<Utc>>,
+    ) -> Result<bool, WebhookError> {
+        let settled = self
+            .db
+            .settle_transaction(&transfer.reference, status, settled_at)?;
+        if settled == 0 {
+            if self.db.transaction_reference_exists(&transfer.reference)? {
+                return Ok(false);
+            }
+            return Err(WebhookError::UnknownTransfer(transfer.reference.clone()));
+        }
+        Ok(true)
+    }
+}
@@ -1,38 +1,173 @@
-// use awc::Client;
-use ipinfo::{IpInfo, IpInfoConfig};
-use serde::Deserialize;
-
-#[derive(Debug, Deserialize, Default)]
-pub struct IpInfoResponse {
-    pub city: Option<String>,
-    pub country: Option<String>,
-}
+use super::ipinfo_provider::IpInfoProvider;
+use super::mmdb_provider::MmdbProvider;
+use super::provider::{GeoLocation, GeoLookupError, GeoProvider};
+use crate::config::config::{GeoProviderKind, GeolocationConfig};
+use crate::services::cache::store::Cache;
+use crate::services::metrics::collector::Metrics;
+use lru::LruCache;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct GeoLocator {
-    token: String,
+    /// `None` when geolocation is disabled; lookups then resolve to unknown.
+    provider: Option<Arc<dyn GeoProvider>>,
+    recent: Arc<Mutex<LruCache<IpAddr, (GeoLocation, Instant)>>>,
+    cache: Cache,
+    metrics: Metrics,
+    timeout: Duration,
+    ttl: Duration,
 }
 
 impl GeoLocator {
-    pub fn new(token: String) -> Self {
-        Self { token }
+    pub fn new(
+        config: &GeolocationConfig,
+        cache: Cache,
+        metrics: Metrics,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let timeout = Duration::from_millis(config.timeout_ms);
+
+        let provider: Option<Arc<dyn GeoProvider>> = match config.provider {
+            _ if !config.enabled => None,
+            GeoProviderKind::Ipinfo => match &config.ipinfo_token {
+                Some(token) => Some(Arc::new(IpInfoProvider::new(token.expose(), timeout)?)),
+                None => None,
+            },
+            GeoProviderKind::Mmdb => match &config.mmdb_city_path {
+                Some(city_path) => Some(Arc::new(MmdbProvider::open(
+                    city_path,
+                    config.mmdb_asn_path.as_deref(),
+                )?)),
+                None => None,
+            },
+        };
+
+        let capacity = NonZeroUsize::new(config.cache_capacity).unwrap_or(NonZeroUsize::MIN);
+
+        Ok(Self {
+            provider,
+            recent: Arc::new(Mutex::new(LruCache::new(capacity))),
+            cache,
+            metrics,
+            timeout,
+            ttl: Duration::from_secs(config.cache_ttl_secs),
+        })
     }
 
-    pub async fn lookup(&self, ip: &str) -> Result<IpInfoResponse, Box<dyn std::error::Error>> {
-        let config = IpInfoConfig {
-            token: Some(self.token.clone()),
-            ..Default::default()
+    /// Resolves an address to a city, country and ASN. Answers from remote
+    /// providers are served from the in-process cache, then the shared cache,
+    /// before a request is made. Private, loopback and unparseable addresses
+    /// resolve to unknown without a lookup.
+    #[tracing::instrument(name = "geolocation.lookup", skip_all, fields(otel.kind = "client"))]
+    pub async fn lookup(&self, ip: &str) -> Result<GeoLocation, GeoLookupError> {
+        let Some(provider) = &self.provider else {
+            return Ok(GeoLocation::default());
         };
 
-        let mut ipinfo = IpInfo::new(config).expect("should construct ipInfo");
+        let Some(addr) = ip.parse::<IpAddr>().ok().filter(is_public) else {
+            self.record("skipped");
+            return Ok(GeoLocation::default());
+        };
+
+        if !provider.is_remote() {
+            let result = provider.lookup(addr).await;
+            self.record(if result.is_ok() { "success" } else { "error" });
+            return result;
+        }
+
+        if let Some(geo) = self.recent_lookup(addr) {
+            self.record("cached");
+            return Ok(geo);
+        }
 
-        let res = ipinfo.lookup(ip).await?;
+        let cache_key = format!("geo:{}", addr);
+        match self.cache.get_json::<GeoLocation>(&cache_key).await {
+            Ok(Some(geo)) => {
+                self.record("cached");
+                self.remember(addr, &geo);
+                return Ok(geo);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = ?e, "Geolocation cache read failed"),
+        }
 
-        let geo = IpInfoResponse {
-            city: Some(res.city),
-            country: Some(res.country),
+        // The timeout covers waiting for a shared client as well as the
+        // request, so a slow provider cannot queue up lookups.
+        let result = tokio::time::timeout(self.timeout, provider.lookup(addr)).await;
+
+        let geo = match result {
+            Ok(Ok(geo)) => geo,
+            Ok(Err(e)) => {
+                self.record("error");
+                return Err(e);
+            }
+            Err(_) => {
+                self.record("timeout");
+                return Err(GeoLookupError::Timeout);
+            }
         };
+        self.record("success");
+
+        self.remember(addr, &geo);
+        if let Err(e) = self.cache.set_json(&cache_key, &geo, self.ttl).await {
+            tracing::warn!(error = ?e, "Geolocation cache write failed");
+        }
 
         Ok(geo)
     }
+
+    fn recent_lookup(&self, addr: IpAddr) -> Option<GeoLocation> {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+
+        match recent.get(&addr) {
+            Some((geo, stored_at)) if stored_at.elapsed() < self.ttl => Some(geo.clone()),
+            Some(_) => {
+                recent.pop(&addr);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn remember(&self, addr: IpAddr, geo: &GeoLocation) {
+        self.recent
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .put(addr, (geo.clone(), Instant::now()));
+    }
+
+    fn record(&self, outcome: &str) {
+        self.metrics
+            .geolocation_lookups_total
+            .with_label_values(&[outcome])
+            .inc();
+    }
+}
+
+/// Addresses worth asking a provider about: not private, loopback,
+/// link-local or otherwise reserved for local use.
+fn is_public(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(v4) => {
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                // 100.64.0.0/10, carrier-grade NAT.
+                || (v4.octets()[0] == 100 && v4.octets()[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public(&IpAddr::V4(v4)),
+            None => {
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_unique_local()
+                    || v6.is_unicast_link_local())
+            }
+        },
+    }
 }
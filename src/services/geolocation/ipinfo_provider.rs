@@ -0,0 +1,85 @@
+use super::provider::{GeoLocation, GeoLookupError, GeoProvider};
+use crate::services::telemetry::tracer::inject_trace_context;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+const IPINFO_URL: &str = "https://ipinfo.io";
+
+/// The ipinfo.io API. `reqwest::Client` pools its connections and is safe to
+/// share, so concurrent lookups run in parallel.
+pub struct IpInfoProvider {
+    client: reqwest::Client,
+    token: String,
+}
+
+/// The fields of an ipinfo.io answer we use. Bogon and reserved addresses
+/// come back without them.
+#[derive(Deserialize)]
+struct IpDetails {
+    #[serde(default)]
+    city: Option<String>,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    loc: Option<String>,
+    #[serde(default)]
+    org: Option<String>,
+}
+
+impl IpInfoProvider {
+    pub fn new(token: &str, timeout: Duration) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+        Ok(IpInfoProvider {
+            client,
+            token: token.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl GeoProvider for IpInfoProvider {
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    async fn lookup(&self, addr: IpAddr) -> Result<GeoLocation, GeoLookupError> {
+        let url = format!("{}/{}/json", IPINFO_URL, addr);
+        let details: IpDetails = inject_trace_context(self.client.get(url))
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(GeoLookupError::IpInfo)?
+            .json()
+            .await
+            .map_err(GeoLookupError::IpInfo)?;
+
+        // `org` is "AS15169 Google LLC".
+        let (asn, asn_org) = match details.org.as_deref().and_then(|org| org.split_once(' ')) {
+            Some((asn, org)) => (
+                asn.strip_prefix("AS").and_then(|n| n.parse().ok()),
+                Some(org.to_string()),
+            ),
+            None => (None, details.org),
+        };
+
+        // `loc` is "6.4541,3.3947".
+        let coordinates = details
+            .loc
+            .as_deref()
+            .and_then(|loc| loc.split_once(','))
+            .and_then(|(lat, long)| Some((lat.trim().parse().ok()?, long.trim().parse().ok()?)));
+
+        Ok(GeoLocation {
+            city: details.city.filter(|city| !city.is_empty()),
+            country: details.country.filter(|country| !country.is_empty()),
+            latitude: coordinates.map(|(lat, _)| lat),
+            longitude: coordinates.map(|(_, long)| long),
+            asn,
+            asn_org,
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use super::provider::{GeoLocation, GeoLookupError, GeoProvider};
+use async_trait::async_trait;
+use maxminddb::{MaxMindDBError, Reader, geoip2};
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug)]
+pub enum MmdbError {
+    Open(PathBuf, MaxMindDBError),
+    Watch(notify::Error),
+}
+
+impl std::fmt::Display for MmdbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MmdbError::Open(path, e) => write!(f, "failed to open {}: {}", path.display(), e),
+            MmdbError::Watch(e) => write!(f, "failed to watch mmdb files: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MmdbError {}
+
+/// One `.mmdb` file, swapped out in place when the file on disk changes.
+struct ReloadableReader {
+    path: PathBuf,
+    reader: RwLock<Arc<Reader<Vec<u8>>>>,
+}
+
+impl ReloadableReader {
+    fn open(path: &Path) -> Result<Self, MmdbError> {
+        // Watch events carry absolute paths, so compare against one too.
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let reader = Reader::open_readfile(&path).map_err(|e| MmdbError::Open(path.clone(), e))?;
+
+        Ok(ReloadableReader {
+            path,
+            reader: RwLock::new(Arc::new(reader)),
+        })
+    }
+
+    fn current(&self) -> Arc<Reader<Vec<u8>>> {
+        self.reader
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// A file that fails to open (usually one still being written) leaves
+    /// the previous database in place.
+    fn reload(&self) {
+        match Reader::open_readfile(&self.path) {
+            Ok(reader) => {
+                *self.reader.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(reader);
+                tracing::info!(path = %self.path.display(), "Reloaded geolocation database");
+            }
+            Err(e) => tracing::warn!(
+                path = %self.path.display(),
+                error = %e,
+                "Failed to reload geolocation database, keeping the previous one"
+            ),
+        }
+    }
+}
+
+/// MaxMind-format databases on local disk: a City (or Country) database and,
+/// optionally, an ASN database. Both are reloaded when their files change.
+pub struct MmdbProvider {
+    city: Arc<ReloadableReader>,
+    asn: Option<Arc<ReloadableReader>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl MmdbProvider {
+    pub fn open(city_path: &str, asn_path: Option<&str>) -> Result<Self, MmdbError> {
+        let city = Arc::new(ReloadableReader::open(Path::new(city_path))?);
+        let asn = asn_path
+            .map(|path| ReloadableReader::open(Path::new(path)).map(Arc::new))
+            .transpose()?;
+
+        let readers: Vec<Arc<ReloadableReader>> =
+            std::iter::once(city.clone()).chain(asn.clone()).collect();
+        let watcher = watch(readers).map_err(MmdbError::Watch)?;
+
+        Ok(MmdbProvider {
+            city,
+            asn,
+            _watcher: watcher,
+        })
+    }
+}
+
+/// Watches the directories rather than the files: updates usually land as a
+/// new file renamed over the old one, which a watch on the file itself misses.
+fn watch(readers: Vec<Arc<ReloadableReader>>) -> notify::Result<RecommendedWatcher> {
+    let directories: HashSet<PathBuf> = readers
+        .iter()
+        .filter_map(|reader| reader.path.parent().map(Path::to_path_buf))
+        .collect();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // Renames and finished writes only; reacting to every data write
+        // would reload a half-copied file many times over.
+        let finished = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Name(_))
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+        if !finished {
+            return;
+        }
+
+        for reader in &readers {
+            if event.paths.contains(&reader.path) {
+                reader.reload();
+            }
+        }
+    })?;
+
+    for directory in directories {
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(watcher)
+}
+
+#[async_trait]
+impl GeoProvider for MmdbProvider {
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn lookup(&self, addr: IpAddr) -> Result<GeoLocation, GeoLookupError> {
+        let mut geo = GeoLocation::default();
+
+        let reader = self.city.current();
+        match reader.lookup::<geoip2::City>(addr) {
+            Ok(record) => {
+                geo.city = record
+                    .city
+                    .and_then(|city| city.names)
+                    .and_then(|names| names.get("en").map(|name| name.to_string()));
+                geo.country = record
+                    .country
+                    .and_then(|country| country.iso_code)
+                    .map(str::to_string);
+                if let Some(location) = record.location {
+                    geo.latitude = location.latitude;
+                    geo.longitude = location.longitude;
+                }
+            }
+            Err(MaxMindDBError::AddressNotFoundError(_)) => {}
+            Err(e) => return Err(GeoLookupError::Mmdb(e)),
+        }
+
+        if let Some(asn) = &self.asn {
+            let reader = asn.current();
+            match reader.lookup::<geoip2::Asn>(addr) {
+                Ok(record) => {
+                    geo.asn = record.autonomous_system_number;
+                    geo.asn_org = record.autonomous_system_organization.map(str::to_string);
+                }
+                Err(MaxMindDBError::AddressNotFoundError(_)) => {}
+                Err(e) => return Err(GeoLookupError::Mmdb(e)),
+            }
+        }
+
+        Ok(geo)
+    }
+}
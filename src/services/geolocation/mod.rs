@@ -1 +1,4 @@
 pub mod geolocator;
+pub mod ipinfo_provider;
+pub mod mmdb_provider;
+pub mod provider;
@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Where an address is. Every field is optional: providers differ in coverage
+/// and a missing record is not an error.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GeoLocation {
+    pub city: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `NG`.
+    pub country: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub asn: Option<u32>,
+    pub asn_org: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum GeoLookupError {
+    IpInfo(reqwest::Error),
+    Mmdb(maxminddb::MaxMindDBError),
+    Timeout,
+}
+
+impl std::fmt::Display for GeoLookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeoLookupError::IpInfo(e) => write!(f, "ipinfo lookup failed: {}", e),
+            GeoLookupError::Mmdb(e) => write!(f, "mmdb lookup failed: {}", e),
+            GeoLookupError::Timeout => write!(f, "geolocation lookup timed out"),
+        }
+    }
+}
+
+impl std::error::Error for GeoLookupError {}
+
+/// A source of geolocation data behind `GeoLocator`.
+#[async_trait]
+pub trait GeoProvider: Send + Sync {
+    /// Remote providers cost a request per lookup, so `GeoLocator` caches
+    /// their answers. Local databases are faster than the caches.
+    fn is_remote(&self) -> bool;
+
+    async fn lookup(&self, addr: IpAddr) -> Result<GeoLocation, GeoLookupError>;
+}
@@ -0,0 +1,174 @@
+use crate::config::config::LockoutConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::user_db::UserImpl;
+use crate::database::user_security_log_db::UserSecurityLogsImpl;
+use crate::helpers::time_helpers::{secs_after, secs_before};
+use crate::models::models::{NewUserSecurityLog, SecurityEventType, User};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::Duration;
+
+/// Why an attempt was turned away before it was checked.
+#[derive(Debug, Clone, Copy)]
+pub enum LockoutRejection {
+    /// The account is locked until the cooldown ends or an admin unlocks it.
+    Locked { retry_after: Duration },
+    /// Too soon after the previous failure.
+    Backoff { retry_after: Duration },
+}
+
+/// Applies `LockoutConfig` to the failures recorded in the security log.
+///
+/// Failures are counted over a sliding window, per user and per IP. Each
+/// failure doubles the wait before the next attempt is accepted, and
+/// `max_failures` within the window locks the account for `lock_secs`.
+#[derive(Clone)]
+pub struct LockoutPolicy {
+    config: LockoutConfig,
+    db: Database,
+}
+
+impl LockoutPolicy {
+    pub fn new(config: &LockoutConfig, db: Database) -> Self {
+        LockoutPolicy {
+            config: config.clone(),
+            db,
+        }
+    }
+
+    /// Slows down an address that keeps failing. Errors let the attempt
+    /// through: the security log is not worth an outage.
+    pub fn check_ip(&self, ip: &str) -> Result<(), LockoutRejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        match self.db.get_ip_failures_since(ip, self.window_start()) {
+            Ok((failures, latest)) => {
+                match self.backoff(failures - self.config.ip_free_failures, latest) {
+                    Some(retry_after) => Err(LockoutRejection::Backoff { retry_after }),
+                    None => Ok(()),
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, "Failed to count IP failures");
+                Ok(())
+            }
+        }
+    }
+
+    /// Rejects attempts on a locked account, or one still backing off.
+    pub fn check_user(&self, user: &User) -> Result<(), LockoutRejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        if let Some(locked_until) = user.locked_until.filter(|until| *until > now) {
+            return Err(LockoutRejection::Locked {
+                retry_after: (locked_until - now).to_std().unwrap_or_default(),
+            });
+        }
+
+        match self.user_failures(&user.id) {
+            Ok((failures, latest)) => match self.backoff(failures, latest) {
+                Some(retry_after) => Err(LockoutRejection::Backoff { retry_after }),
+                None => Ok(()),
+            },
+            Err(e) => {
+                tracing::warn!(error = ?e, "Failed to count user failures");
+                Ok(())
+            }
+        }
+    }
+
+    /// Call once a failure has been written to the security log. Locks the
+    /// account if that failure reached the limit and returns when the lock
+    /// ends; `None` if no new lock was placed.
+    pub fn after_failure(&self, user_id: &str) -> Result<Option<DateTime<Utc>>, AppError> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+
+        let (failures, _) = self.user_failures(user_id)?;
+        if failures < self.config.max_failures {
+            return Ok(None);
+        }
+
+        let until = secs_after(Utc::now(), self.config.lock_secs);
+        if self.db.lock_user(user_id, until)? {
+            tracing::warn!(user_id, failures, "Account locked after repeated failures");
+            Ok(Some(until))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Lifts a lock early. Failures before the unlock stop counting.
+    pub fn unlock(&self, user_id: &str) -> Result<bool, AppError> {
+        self.db.unlock_user(user_id)
+    }
+
+    /// Lifts locks whose cooldown has ended and logs an unlock for each.
+    pub fn unlock_expired(&self) -> Result<usize, AppError> {
+        let unlocked = self.db.unlock_expired_users()?;
+
+        for user_id in &unlocked {
+            tracing::info!(user_id, "Account lock expired");
+
+            let event = NewUserSecurityLog {
+                user_id: Some(user_id.clone()),
+                ip_address: "system".to_string(),
+                city: "unknown".to_string(),
+                country: "unknown".to_string(),
+                failed_login_attempts: 0,
+                flagged_for_review: false,
+                created_at: Utc::now(),
+                event_type: SecurityEventType::AccountUnlocked.as_str().to_string(),
+                user_agent: None,
+                path: String::new(),
+                method: String::new(),
+                status: 0,
+                latitude: None,
+                longitude: None,
+                asn: None,
+                flag_reason: None,
+            };
+            if let Err(e) = self.db.create_user_security_log(event) {
+                tracing::error!(error = ?e, user_id, "Failed to log account unlock");
+            }
+        }
+
+        Ok(unlocked.len())
+    }
+
+    fn window_start(&self) -> DateTime<Utc> {
+        secs_before(Utc::now(), self.config.window_secs)
+    }
+
+    /// Failures in the window that started after the last unlock, so an
+    /// unlocked account starts with a clean slate.
+    fn user_failures(&self, user_id: &str) -> Result<(i64, Option<DateTime<Utc>>), AppError> {
+        let last_unlock = self
+            .db
+            .get_last_event_at(user_id, SecurityEventType::AccountUnlocked)?;
+        let since = last_unlock
+            .into_iter()
+            .fold(self.window_start(), DateTime::max);
+
+        self.db.get_user_failures_since(user_id, since)
+    }
+
+    /// Time left before another attempt is accepted after `failures` counted
+    /// failures, the latest at `latest`.
+    fn backoff(&self, failures: i64, latest: Option<DateTime<Utc>>) -> Option<Duration> {
+        let latest = latest.filter(|_| failures > 0)?;
+
+        let exponent = u32::try_from(failures - 1).unwrap_or(u32::MAX).min(20);
+        let delay =
+            Duration::from_millis(self.config.backoff_base_ms.saturating_mul(1 << exponent))
+                .min(Duration::from_secs(self.config.backoff_max_secs));
+
+        let ready_at = latest + TimeDelta::from_std(delay).ok()?;
+        (ready_at - Utc::now()).to_std().ok()
+    }
+}
@@ -0,0 +1,168 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use r2d2::HandleEvent;
+use r2d2::event::{CheckoutEvent, TimeoutEvent};
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub db_pool_wait_seconds: Histogram,
+    pub db_pool_timeouts_total: IntCounter,
+    pub flutterwave_requests_total: IntCounterVec,
+    pub flutterwave_request_duration_seconds: HistogramVec,
+    pub geolocation_lookups_total: IntCounterVec,
+    pub security_log_write_failures_total: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total HTTP requests handled"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency in seconds",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "route", "status"],
+        )?;
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        )?;
+        let db_pool_wait_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "db_pool_wait_seconds",
+                "Time spent waiting to check out a database connection",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        )?;
+        let db_pool_timeouts_total = IntCounter::new(
+            "db_pool_timeouts_total",
+            "Database connection checkouts that timed out",
+        )?;
+        let flutterwave_requests_total = IntCounterVec::new(
+            Opts::new("flutterwave_requests_total", "Total Flutterwave API calls"),
+            &["operation", "outcome"],
+        )?;
+        let flutterwave_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "flutterwave_request_duration_seconds",
+                "Flutterwave API call latency in seconds",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["operation"],
+        )?;
+        let geolocation_lookups_total = IntCounterVec::new(
+            Opts::new(
+                "geolocation_lookups_total",
+                "Geolocation lookups by outcome",
+            ),
+            &["outcome"],
+        )?;
+        let security_log_write_failures_total = IntCounter::new(
+            "security_log_write_failures_total",
+            "Security log records that failed to persist",
+        )?;
+
+        registry.register(Box::new(http_requests_total.clone()))?;
+        registry.register(Box::new(http_request_duration_seconds.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(db_pool_wait_seconds.clone()))?;
+        registry.register(Box::new(db_pool_timeouts_total.clone()))?;
+        registry.register(Box::new(flutterwave_requests_total.clone()))?;
+        registry.register(Box::new(flutterwave_request_duration_seconds.clone()))?;
+        registry.register(Box::new(geolocation_lookups_total.clone()))?;
+        registry.register(Box::new(security_log_write_failures_total.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_pool_connections,
+            db_pool_wait_seconds,
+            db_pool_timeouts_total,
+            flutterwave_requests_total,
+            flutterwave_request_duration_seconds,
+            geolocation_lookups_total,
+            security_log_write_failures_total,
+        })
+    }
+
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(seconds);
+    }
+
+    pub fn observe_flutterwave_call(&self, operation: &str, success: bool, seconds: f64) {
+        let outcome = if success { "success" } else { "error" };
+
+        self.flutterwave_requests_total
+            .with_label_values(&[operation, outcome])
+            .inc();
+        self.flutterwave_request_duration_seconds
+            .with_label_values(&[operation])
+            .observe(seconds);
+    }
+
+    pub fn record_pool_state(&self, state: r2d2::State) {
+        let idle = i64::from(state.idle_connections);
+        let total = i64::from(state.connections);
+
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(total - idle);
+    }
+
+    pub fn pool_event_handler(&self) -> PoolMetricsHandler {
+        PoolMetricsHandler {
+            wait_seconds: self.db_pool_wait_seconds.clone(),
+            timeouts_total: self.db_pool_timeouts_total.clone(),
+        }
+    }
+
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Feeds r2d2 checkout events into the pool wait-time metrics.
+#[derive(Debug)]
+pub struct PoolMetricsHandler {
+    wait_seconds: Histogram,
+    timeouts_total: IntCounter,
+}
+
+impl HandleEvent for PoolMetricsHandler {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        self.wait_seconds.observe(event.duration().as_secs_f64());
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.timeouts_total.inc();
+    }
+}
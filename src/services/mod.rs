@@ -1 +1,16 @@
+pub mod cache;
+pub mod devices;
+pub mod flutterwave;
 pub mod geolocation;
+pub mod lockout;
+pub mod metrics;
+pub mod notifications;
+pub mod otp;
+pub mod outbox;
+pub mod pin;
+pub mod rate_limit;
+pub mod risk;
+pub mod telemetry;
+pub mod totp;
+pub mod wallets;
+pub mod whatsapp;
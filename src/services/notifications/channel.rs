@@ -0,0 +1,91 @@
+use crate::models::models::{ChannelKind, Language, User};
+use async_trait::async_trait;
+
+/// A message for one user, independent of the channel it goes out on.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// Email subject; other channels only send `body`.
+    pub subject: String,
+    pub body: String,
+    /// Sent on WhatsApp instead of `body`. Only replies within a
+    /// conversation the user started can be free text there.
+    pub whatsapp: Option<WhatsappTemplate>,
+}
+
+/// A message template approved for the business number in Meta's manager,
+/// named after the `MessageTemplate` it mirrors.
+#[derive(Debug, Clone)]
+pub struct WhatsappTemplate {
+    pub name: &'static str,
+    pub language: Language,
+    /// Fills `{{1}}`, `{{2}}` and so on in order.
+    pub parameters: Vec<String>,
+    /// An authentication template, whose copy-code button also takes the
+    /// code as its parameter.
+    pub authentication: bool,
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    /// The user has no address on this channel, e.g. no email.
+    NoAddress,
+    /// Every channel was off, unreachable for the user, or failed.
+    NoChannel,
+    Http(reqwest::Error),
+    /// The provider answered with an error status.
+    Rejected {
+        status: u16,
+        body: String,
+    },
+    Email(lettre::error::Error),
+    Address(lettre::address::AddressError),
+    Smtp(lettre::transport::smtp::Error),
+    Template(handlebars::RenderError),
+    Io(std::io::Error),
+    /// The blocking SMTP task panicked or was cancelled.
+    Task(tokio::task::JoinError),
+}
+
+impl std::fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyError::NoAddress => write!(f, "user has no address on this channel"),
+            NotifyError::NoChannel => write!(f, "no channel could deliver the message"),
+            NotifyError::Http(e) => write!(f, "provider request failed: {}", e),
+            NotifyError::Rejected { status, body } => {
+                write!(f, "provider rejected the message ({}): {}", status, body)
+            }
+            NotifyError::Email(e) => write!(f, "failed to build email: {}", e),
+            NotifyError::Address(e) => write!(f, "invalid email address: {}", e),
+            NotifyError::Smtp(e) => write!(f, "SMTP delivery failed: {}", e),
+            NotifyError::Template(e) => write!(f, "failed to render template: {}", e),
+            NotifyError::Io(e) => write!(f, "failed to write message: {}", e),
+            NotifyError::Task(e) => write!(f, "delivery task failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for NotifyError {}
+
+/// One way of reaching users, behind `Notifier`.
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn kind(&self) -> ChannelKind;
+
+    async fn send(&self, user: &User, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+/// Where `kind` reaches the user, if anywhere. SMS and WhatsApp use the
+/// phone number; email needs an address on file.
+pub fn address(kind: ChannelKind, user: &User) -> Option<&str> {
+    match kind {
+        ChannelKind::Sms | ChannelKind::Whatsapp => Some(user.phone.as_str()),
+        ChannelKind::Email => user.email.as_deref().filter(|email| !email.is_empty()),
+    }
+}
+
+/// Phone numbers as SMS and WhatsApp APIs take them: international format
+/// without the leading `+`.
+pub fn msisdn(phone: &str) -> String {
+    phone.chars().filter(char::is_ascii_digit).collect()
+}
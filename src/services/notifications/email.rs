@@ -0,0 +1,100 @@
+use super::channel::{Notification, NotificationChannel, NotifyError, address};
+use crate::config::config::EmailConfig;
+use crate::models::models::{ChannelKind, User};
+use async_trait::async_trait;
+use handlebars::Handlebars;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_json::json;
+use std::time::Duration;
+
+const LAYOUT: &str = "layout";
+
+/// Email over SMTP. Messages go out as plain text with an HTML alternative
+/// rendered from `templates/email/layout.hbs`.
+pub struct SmtpEmail {
+    transport: SmtpTransport,
+    from: Mailbox,
+    templates: Handlebars<'static>,
+}
+
+impl SmtpEmail {
+    pub fn new(config: &EmailConfig, timeout: Duration) -> Result<Self, NotifyError> {
+        let mut builder = SmtpTransport::starttls_relay(&config.smtp_host)
+            .map_err(NotifyError::Smtp)?
+            .port(config.smtp_port)
+            .timeout(Some(timeout));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose().to_string(),
+            ));
+        }
+
+        Ok(SmtpEmail {
+            transport: builder.build(),
+            from: sender(config)?,
+            templates: templates(),
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SmtpEmail {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Email
+    }
+
+    async fn send(&self, user: &User, notification: &Notification) -> Result<(), NotifyError> {
+        let to: Mailbox = address(ChannelKind::Email, user)
+            .ok_or(NotifyError::NoAddress)?
+            .parse()
+            .map_err(NotifyError::Address)?;
+
+        let html = self
+            .templates
+            .render(
+                LAYOUT,
+                &json!({ "subject": notification.subject, "body": notification.body }),
+            )
+            .map_err(NotifyError::Template)?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(notification.subject.clone())
+            .multipart(MultiPart::alternative_plain_html(
+                notification.body.clone(),
+                html,
+            ))
+            .map_err(NotifyError::Email)?;
+
+        // `SmtpTransport` blocks; it is cheap to clone and shares its pool.
+        let transport = self.transport.clone();
+        tokio::task::spawn_blocking(move || transport.send(&message))
+            .await
+            .map_err(NotifyError::Task)?
+            .map_err(NotifyError::Smtp)?;
+        Ok(())
+    }
+}
+
+/// The configured `from` mailbox. `Config::validate` has already checked it.
+pub fn sender(config: &EmailConfig) -> Result<Mailbox, NotifyError> {
+    config
+        .from
+        .as_deref()
+        .unwrap_or_default()
+        .parse()
+        .map_err(NotifyError::Address)
+}
+
+fn templates() -> Handlebars<'static> {
+    let mut templates = Handlebars::new();
+    templates.set_strict_mode(true);
+    templates
+        .register_template_string(LAYOUT, include_str!("../../../templates/email/layout.hbs"))
+        .expect("templates/email/layout.hbs is a valid template");
+    templates
+}
@@ -0,0 +1,7 @@
+pub mod channel;
+pub mod email;
+pub mod notifier;
+pub mod sink;
+pub mod sms;
+pub mod templates;
+pub mod whatsapp;
@@ -0,0 +1,268 @@
+use super::channel::{Notification, NotificationChannel, NotifyError, address};
+use super::email::SmtpEmail;
+use super::sink::{DevSink, SinkTarget};
+use super::sms::TermiiSms;
+use super::templates::{
+    MessageTemplate, NewDeviceMessage, OtpMessage, TemplateRegistry, message_time,
+};
+use super::whatsapp::WhatsappCloud;
+use crate::config::config::{ChannelMode, NotificationsConfig};
+use crate::database::db::Database;
+use crate::database::user_db::UserImpl;
+use crate::models::models::{ChannelKind, Language, OtpPurpose, User, UserDevice};
+use crate::services::devices::registry::NewDeviceHook;
+use crate::services::otp::issuer::OtpDelivery;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Sends messages in the user's language on their preferred channel,
+/// falling back to the others in `fallback_order` when a channel is off, has
+/// no address for the user, or fails to deliver.
+#[derive(Clone)]
+pub struct Notifier {
+    channels: Vec<Arc<dyn NotificationChannel>>,
+    fallback_order: Vec<ChannelKind>,
+    templates: TemplateRegistry,
+    db: Database,
+}
+
+impl Notifier {
+    pub fn new(
+        config: &NotificationsConfig,
+        templates: TemplateRegistry,
+        db: Database,
+    ) -> Result<Self, NotifyError> {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(NotifyError::Http)?;
+
+        let mut channels: Vec<Arc<dyn NotificationChannel>> = Vec::new();
+        let modes = [
+            (ChannelKind::Sms, config.sms.mode, &config.sms.file_path),
+            (
+                ChannelKind::Whatsapp,
+                config.whatsapp.mode,
+                &config.whatsapp.file_path,
+            ),
+            (
+                ChannelKind::Email,
+                config.email.mode,
+                &config.email.file_path,
+            ),
+        ];
+        for (kind, mode, file_path) in modes {
+            let channel: Arc<dyn NotificationChannel> = match (mode, kind) {
+                (ChannelMode::Off, _) => continue,
+                (ChannelMode::Log, _) => Arc::new(DevSink::new(kind, SinkTarget::Log)),
+                (ChannelMode::File, _) => Arc::new(DevSink::new(
+                    kind,
+                    SinkTarget::File(PathBuf::from(file_path.clone().unwrap_or_default())),
+                )),
+                (ChannelMode::Live, ChannelKind::Sms) => {
+                    Arc::new(TermiiSms::new(&config.sms, client.clone()))
+                }
+                (ChannelMode::Live, ChannelKind::Whatsapp) => {
+                    Arc::new(WhatsappCloud::new(&config.whatsapp, client.clone()))
+                }
+                (ChannelMode::Live, ChannelKind::Email) => {
+                    Arc::new(SmtpEmail::new(&config.email, timeout)?)
+                }
+            };
+            channels.push(channel);
+        }
+
+        if channels.is_empty() {
+            tracing::warn!("No notification channel is enabled; OTPs and alerts will not be sent");
+        }
+
+        Ok(Notifier {
+            channels,
+            fallback_order: config.fallback_order.clone(),
+            templates,
+            db,
+        })
+    }
+
+    /// Renders `message` in the user's language and delivers it.
+    pub async fn send<M: MessageTemplate>(
+        &self,
+        user: &User,
+        message: &M,
+    ) -> Result<ChannelKind, NotifyError> {
+        let language = Language::parse(&user.preferred_language).unwrap_or(Language::English);
+        let notification = self
+            .templates
+            .render(language, message)
+            .map_err(NotifyError::Template)?;
+
+        self.notify(user, &notification).await
+    }
+
+    /// Sends in the background so the caller does not wait on providers.
+    /// Failures are logged.
+    pub fn dispatch<M: MessageTemplate>(&self, user: &User, message: M) {
+        let notifier = self.clone();
+        let user = user.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = notifier.send(&user, &message).await {
+                tracing::error!(
+                    user_id = %user.id,
+                    message = M::NAME,
+                    error = %e,
+                    "Failed to deliver notification"
+                );
+            }
+        });
+    }
+
+    /// `dispatch` for callers that only have the user's ID.
+    pub fn dispatch_to<M: MessageTemplate>(&self, user_id: &str, message: M) {
+        let notifier = self.clone();
+        let user_id = user_id.to_string();
+        actix_web::rt::spawn(async move {
+            let user = match notifier.db.get_user_by_id(&user_id) {
+                Ok(user) => user,
+                Err(e) => {
+                    tracing::error!(user_id, error = ?e, "Failed to load user to notify");
+                    return;
+                }
+            };
+            notifier.dispatch(&user, message);
+        });
+    }
+
+    /// Delivers on the first channel that succeeds and returns which one.
+    pub async fn notify(
+        &self,
+        user: &User,
+        notification: &Notification,
+    ) -> Result<ChannelKind, NotifyError> {
+        let mut last_error = NotifyError::NoChannel;
+
+        for kind in self.channel_order(user) {
+            let Some(channel) = self.channels.iter().find(|channel| channel.kind() == kind) else {
+                continue;
+            };
+            if address(kind, user).is_none() {
+                continue;
+            }
+
+            match channel.send(user, notification).await {
+                Ok(()) => return Ok(kind),
+                Err(e) => {
+                    tracing::warn!(
+                        user_id = %user.id,
+                        channel = kind.as_str(),
+                        error = %e,
+                        "Notification delivery failed, trying the next channel"
+                    );
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Delivers on `kind` only, for replies that must arrive where the user
+    /// wrote from.
+    pub async fn send_on(
+        &self,
+        kind: ChannelKind,
+        user: &User,
+        notification: &Notification,
+    ) -> Result<(), NotifyError> {
+        let channel = self
+            .channels
+            .iter()
+            .find(|channel| channel.kind() == kind)
+            .ok_or(NotifyError::NoChannel)?;
+        if address(kind, user).is_none() {
+            return Err(NotifyError::NoAddress);
+        }
+
+        channel.send(user, notification).await
+    }
+
+    /// The user's preferred channel, then the fallbacks, each once.
+    fn channel_order(&self, user: &User) -> Vec<ChannelKind> {
+        let preferred = ChannelKind::parse(&user.notification_channel);
+        let mut order: Vec<ChannelKind> = preferred.into_iter().collect();
+        for kind in &self.fallback_order {
+            if !order.contains(kind) {
+                order.push(*kind);
+            }
+        }
+        order
+    }
+}
+
+/// A failed delivery is logged; the user can ask for a new code once the
+/// resend interval has passed. Email confirmation codes only go to the
+/// pending address, since receiving one there is what they prove.
+impl OtpDelivery for Notifier {
+    fn deliver(&self, user: &User, purpose: OtpPurpose, code: &str, valid_for: Duration) {
+        let message = OtpMessage {
+            purpose: purpose.as_str(),
+            code: code.to_string(),
+            valid_minutes: valid_for.as_secs().div_ceil(60),
+        };
+        if purpose != OtpPurpose::EmailConfirm {
+            self.dispatch(user, message);
+            return;
+        }
+
+        let notifier = self.clone();
+        let recipient = User {
+            email: user.pending_email.clone(),
+            ..user.clone()
+        };
+        actix_web::rt::spawn(async move {
+            let language =
+                Language::parse(&recipient.preferred_language).unwrap_or(Language::English);
+            let sent = match notifier.templates.render(language, &message) {
+                Ok(notification) => {
+                    notifier
+                        .send_on(ChannelKind::Email, &recipient, &notification)
+                        .await
+                }
+                Err(e) => Err(NotifyError::Template(e)),
+            };
+            if let Err(e) = sent {
+                tracing::error!(
+                    user_id = %recipient.id,
+                    error = %e,
+                    "Failed to deliver email confirmation code"
+                );
+            }
+        });
+    }
+}
+
+impl NewDeviceHook for Notifier {
+    fn on_new_device(&self, device: &UserDevice) {
+        tracing::info!(
+            user_id = %device.user_id,
+            device_id = %device.id,
+            country = %device.country,
+            "Sign-in from a new device"
+        );
+
+        self.dispatch_to(
+            &device.user_id,
+            NewDeviceMessage {
+                device: device
+                    .user_agent
+                    .as_deref()
+                    .map(|agent| agent.chars().take(60).collect())
+                    .unwrap_or_else(|| device.device_id.clone()),
+                city: device.city.clone(),
+                country: device.country.clone(),
+                time: message_time(device.last_seen_at),
+            },
+        );
+    }
+}
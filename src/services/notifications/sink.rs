@@ -0,0 +1,73 @@
+use super::channel::{Notification, NotificationChannel, NotifyError, address};
+use crate::models::models::{ChannelKind, User};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Where a development sink puts messages.
+pub enum SinkTarget {
+    Log,
+    /// Appended to as JSON lines.
+    File(PathBuf),
+}
+
+/// Stands in for a channel's provider during development, so codes can be
+/// read without an SMS, WhatsApp or SMTP account. Messages are written in
+/// the clear; `Config::validate` keeps sinks out of production.
+pub struct DevSink {
+    kind: ChannelKind,
+    target: SinkTarget,
+}
+
+impl DevSink {
+    pub fn new(kind: ChannelKind, target: SinkTarget) -> Self {
+        DevSink { kind, target }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for DevSink {
+    fn kind(&self) -> ChannelKind {
+        self.kind
+    }
+
+    async fn send(&self, user: &User, notification: &Notification) -> Result<(), NotifyError> {
+        let to = address(self.kind, user).ok_or(NotifyError::NoAddress)?;
+
+        match &self.target {
+            SinkTarget::Log => {
+                tracing::info!(
+                    channel = self.kind.as_str(),
+                    to,
+                    subject = %notification.subject,
+                    body = %notification.body,
+                    "Notification written to development sink"
+                );
+            }
+            SinkTarget::File(path) => {
+                let mut line = json!({
+                    "sent_at": Utc::now(),
+                    "channel": self.kind.as_str(),
+                    "to": to,
+                    "subject": notification.subject,
+                    "body": notification.body
+                })
+                .to_string();
+                line.push('\n');
+
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(NotifyError::Io)?;
+                file.write_all(line.as_bytes())
+                    .await
+                    .map_err(NotifyError::Io)?;
+            }
+        }
+        Ok(())
+    }
+}
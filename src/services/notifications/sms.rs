@@ -0,0 +1,65 @@
+use super::channel::{Notification, NotificationChannel, NotifyError, msisdn};
+use crate::config::config::SmsConfig;
+use crate::models::models::{ChannelKind, User};
+use crate::services::telemetry::tracer::inject_trace_context;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// SMS through Termii's messaging API.
+pub struct TermiiSms {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    sender_id: String,
+}
+
+impl TermiiSms {
+    pub fn new(config: &SmsConfig, client: reqwest::Client) -> Self {
+        TermiiSms {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config
+                .api_key
+                .as_ref()
+                .map(|key| key.expose().to_string())
+                .unwrap_or_default(),
+            sender_id: config.sender_id.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TermiiSms {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Sms
+    }
+
+    async fn send(&self, user: &User, notification: &Notification) -> Result<(), NotifyError> {
+        // The "dnd" route is for transactional messages and still reaches
+        // numbers on the Do-Not-Disturb register, unlike "generic".
+        let payload = json!({
+            "api_key": self.api_key,
+            "to": msisdn(&user.phone),
+            "from": self.sender_id,
+            "sms": notification.body,
+            "type": "plain",
+            "channel": "dnd"
+        });
+
+        let url = format!("{}/api/sms/send", self.base_url);
+        let response = inject_trace_context(self.client.post(&url))
+            .json(&payload)
+            .send()
+            .await
+            .map_err(NotifyError::Http)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NotifyError::Rejected {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use super::channel::{Notification, WhatsappTemplate};
+use crate::models::models::{Language, OtpPurpose};
+use chrono::{DateTime, Utc};
+use handlebars::{Handlebars, RenderError};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// A message with a template in every language. Its fields are the
+/// template's variables, so a caller cannot leave one out, and every
+/// template is rendered against each of `samples` before it is used.
+pub trait MessageTemplate: Serialize + Send + Sync + Sized + 'static {
+    /// File name, without `.hbs`, in each language directory.
+    const NAME: &'static str;
+    /// Whether the WhatsApp template is an authentication one.
+    const WHATSAPP_AUTHENTICATION: bool = false;
+
+    /// One per branch the templates take, e.g. per OTP purpose.
+    fn samples() -> Vec<Self>;
+
+    /// The variables of the approved WhatsApp template, in order.
+    fn whatsapp_parameters(&self) -> Vec<String>;
+}
+
+/// A one-time code. `purpose` is an `OtpPurpose::as_str` value.
+#[derive(Debug, Serialize)]
+pub struct OtpMessage {
+    pub purpose: &'static str,
+    pub code: String,
+    pub valid_minutes: u64,
+}
+
+impl MessageTemplate for OtpMessage {
+    const NAME: &'static str = "otp";
+    const WHATSAPP_AUTHENTICATION: bool = true;
+
+    fn samples() -> Vec<Self> {
+        OtpPurpose::ALL
+            .into_iter()
+            .map(|purpose| OtpMessage {
+                purpose: purpose.as_str(),
+                code: "123456".to_string(),
+                valid_minutes: 10,
+            })
+            .collect()
+    }
+
+    /// Authentication templates have fixed wording around the code.
+    fn whatsapp_parameters(&self) -> Vec<String> {
+        vec![self.code.clone()]
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewDeviceMessage {
+    pub device: String,
+    pub city: String,
+    pub country: String,
+    pub time: String,
+}
+
+impl MessageTemplate for NewDeviceMessage {
+    const NAME: &'static str = "new_device";
+
+    fn samples() -> Vec<Self> {
+        vec![NewDeviceMessage {
+            device: "Mozilla/5.0 (Linux; Android 14)".to_string(),
+            city: "Lagos".to_string(),
+            country: "NG".to_string(),
+            time: "19 Oct 2026 14:05 UTC".to_string(),
+        }]
+    }
+
+    fn whatsapp_parameters(&self) -> Vec<String> {
+        vec![
+            self.device.clone(),
+            self.city.clone(),
+            self.country.clone(),
+            self.time.clone(),
+        ]
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BankAddedMessage {
+    pub bank_name: String,
+    pub account_last4: String,
+}
+
+impl MessageTemplate for BankAddedMessage {
+    const NAME: &'static str = "bank_added";
+
+    fn samples() -> Vec<Self> {
+        vec![BankAddedMessage {
+            bank_name: "Access Bank".to_string(),
+            account_last4: "6789".to_string(),
+        }]
+    }
+
+    fn whatsapp_parameters(&self) -> Vec<String> {
+        vec![self.bank_name.clone(), self.account_last4.clone()]
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountLockedMessage {
+    pub minutes: i64,
+    pub until: String,
+}
+
+impl MessageTemplate for AccountLockedMessage {
+    const NAME: &'static str = "account_locked";
+
+    fn samples() -> Vec<Self> {
+        vec![AccountLockedMessage {
+            minutes: 15,
+            until: "14:20 UTC".to_string(),
+        }]
+    }
+
+    fn whatsapp_parameters(&self) -> Vec<String> {
+        vec![self.minutes.to_string(), self.until.clone()]
+    }
+}
+
+/// How times appear in messages.
+pub fn message_time(time: DateTime<Utc>) -> String {
+    time.format("%d %b %Y %H:%M UTC").to_string()
+}
+
+/// Every template, for every language, as built into the binary. A missing
+/// file fails the build.
+macro_rules! embedded_templates {
+    ($($language:literal),*) => {
+        [$(
+            ($language, OtpMessage::NAME, include_str!(concat!("../../../templates/messages/", $language, "/otp.hbs"))),
+            ($language, NewDeviceMessage::NAME, include_str!(concat!("../../../templates/messages/", $language, "/new_device.hbs"))),
+            ($language, BankAddedMessage::NAME, include_str!(concat!("../../../templates/messages/", $language, "/bank_added.hbs"))),
+            ($language, AccountLockedMessage::NAME, include_str!(concat!("../../../templates/messages/", $language, "/account_locked.hbs"))),
+        )*]
+    };
+}
+
+const EMBEDDED: [(&str, &str, &str); 20] = embedded_templates!("en", "pcm", "yo", "ha", "ig");
+
+/// Renders every message against its samples in every language, so a
+/// template using a variable its message lacks is caught when it is loaded
+/// rather than when a user is waiting for a code.
+fn check(templates: &Handlebars<'static>) -> Result<(), TemplateError> {
+    fn check_message<M: MessageTemplate>(
+        templates: &Handlebars<'static>,
+    ) -> Result<(), TemplateError> {
+        for sample in M::samples() {
+            for language in Language::ALL {
+                for part in ["subject", "body"] {
+                    let name = template_name(language.as_str(), M::NAME, part);
+                    templates
+                        .render(&name, &sample)
+                        .map_err(|e| TemplateError::Render(name, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    check_message::<OtpMessage>(templates)?;
+    check_message::<NewDeviceMessage>(templates)?;
+    check_message::<BankAddedMessage>(templates)?;
+    check_message::<AccountLockedMessage>(templates)
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    /// No `---` line between the subject and the body.
+    Format(String),
+    Parse(String, Box<handlebars::TemplateError>),
+    Render(String, RenderError),
+    Read(PathBuf, std::io::Error),
+    Watch(notify::Error),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::Format(name) => write!(
+                f,
+                "template {} needs a subject line, a '---' line, then the body",
+                name
+            ),
+            TemplateError::Parse(name, e) => write!(f, "template {} is invalid: {}", name, e),
+            TemplateError::Render(name, e) => {
+                write!(f, "template {} failed to render: {}", name, e)
+            }
+            TemplateError::Read(path, e) => {
+                write!(f, "failed to read {}: {}", path.display(), e)
+            }
+            TemplateError::Watch(e) => write!(f, "failed to watch templates: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Localized message templates. Each is a file at `<language>/<name>.hbs`
+/// holding the subject, a `---` line and the body. Files under the
+/// templates directory override the built-in copies; with `watch` the
+/// directory is reloaded on change, keeping the previous set if the new one
+/// fails its checks.
+#[derive(Clone)]
+pub struct TemplateRegistry {
+    current: Arc<RwLock<Arc<Handlebars<'static>>>>,
+    _watcher: Option<Arc<RecommendedWatcher>>,
+}
+
+impl TemplateRegistry {
+    pub fn load(dir: &Path, watch: bool) -> Result<Self, TemplateError> {
+        let current = Arc::new(RwLock::new(Arc::new(build(dir)?)));
+
+        let watcher = if watch && dir.is_dir() {
+            Some(Arc::new(
+                watch_dir(dir, current.clone()).map_err(TemplateError::Watch)?,
+            ))
+        } else {
+            None
+        };
+
+        Ok(TemplateRegistry {
+            current,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn render<M: MessageTemplate>(
+        &self,
+        language: Language,
+        message: &M,
+    ) -> Result<Notification, RenderError> {
+        let templates = self
+            .current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        Ok(Notification {
+            subject: templates.render(
+                &template_name(language.as_str(), M::NAME, "subject"),
+                message,
+            )?,
+            body: templates.render(&template_name(language.as_str(), M::NAME, "body"), message)?,
+            whatsapp: Some(WhatsappTemplate {
+                name: M::NAME,
+                language,
+                parameters: message.whatsapp_parameters(),
+                authentication: M::WHATSAPP_AUTHENTICATION,
+            }),
+        })
+    }
+}
+
+fn template_name(language: &str, name: &str, part: &str) -> String {
+    format!("{}/{}/{}", language, name, part)
+}
+
+fn build(dir: &Path) -> Result<Handlebars<'static>, TemplateError> {
+    let mut templates = Handlebars::new();
+    templates.set_strict_mode(true);
+    // Messages are plain text; the email layout escapes them itself.
+    templates.register_escape_fn(handlebars::no_escape);
+
+    for (language, name, embedded) in EMBEDDED {
+        let path = dir.join(language).join(format!("{}.hbs", name));
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => embedded.to_string(),
+            Err(e) => return Err(TemplateError::Read(path, e)),
+        };
+
+        let name = format!("{}/{}", language, name);
+        let (subject, body) = source
+            .split_once("\n---\n")
+            .ok_or_else(|| TemplateError::Format(name.clone()))?;
+        for (part, source) in [("subject", subject), ("body", body)] {
+            let part_name = format!("{}/{}", name, part);
+            templates
+                .register_template_string(&part_name, source.trim())
+                .map_err(|e| TemplateError::Parse(part_name, Box::new(e)))?;
+        }
+    }
+
+    check(&templates)?;
+    Ok(templates)
+}
+
+fn watch_dir(
+    dir: &Path,
+    current: Arc<RwLock<Arc<Handlebars<'static>>>>,
+) -> notify::Result<RecommendedWatcher> {
+    let root = dir.to_path_buf();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            return;
+        }
+
+        match build(&root) {
+            Ok(templates) => {
+                *current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(templates);
+                tracing::info!(path = %root.display(), "Reloaded message templates");
+            }
+            Err(e) => tracing::warn!(
+                error = %e,
+                "Failed to reload message templates, keeping the previous ones"
+            ),
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::Recursive)?;
+
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The built-in templates alone, as no override directory exists.
+    fn embedded() -> TemplateRegistry {
+        TemplateRegistry::load(Path::new("templates/does-not-exist"), false).unwrap()
+    }
+
+    #[test]
+    fn renders_every_embedded_template() {
+        let templates = embedded();
+        for language in Language::ALL {
+            for message in OtpMessage::samples() {
+                let rendered = templates.render(language, &message).unwrap();
+                assert!(rendered.body.contains(&message.code), "{:?}", language);
+            }
+            for message in NewDeviceMessage::samples() {
+                templates.render(language, &message).unwrap();
+            }
+            for message in BankAddedMessage::samples() {
+                let rendered = templates.render(language, &message).unwrap();
+                assert!(rendered.body.contains(&message.account_last4));
+            }
+            for message in AccountLockedMessage::samples() {
+                templates.render(language, &message).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn names_each_otp_purpose() {
+        let templates = embedded();
+        for language in Language::ALL {
+            let mut bodies: Vec<String> = OtpMessage::samples()
+                .iter()
+                .map(|message| templates.render(language, message).unwrap().body)
+                .collect();
+            bodies.sort();
+            bodies.dedup();
+            assert_eq!(bodies.len(), OtpPurpose::ALL.len(), "{:?}", language);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::channel::{Notification, NotificationChannel, NotifyError, WhatsappTemplate, msisdn};
+use crate::config::config::WhatsappConfig;
+use crate::models::models::{ChannelKind, User};
+use crate::services::telemetry::tracer::inject_trace_context;
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Messages through the WhatsApp Cloud API: approved templates for OTPs
+/// and alerts, free text for replies within a conversation.
+pub struct WhatsappCloud {
+    client: reqwest::Client,
+    messages_url: String,
+    access_token: String,
+    template_prefix: String,
+    template_languages: HashMap<String, String>,
+}
+
+impl WhatsappCloud {
+    pub fn new(config: &WhatsappConfig, client: reqwest::Client) -> Self {
+        WhatsappCloud {
+            client,
+            messages_url: format!(
+                "{}/{}/messages",
+                config.base_url.trim_end_matches('/'),
+                config.phone_number_id.as_deref().unwrap_or_default()
+            ),
+            access_token: config
+                .access_token
+                .as_ref()
+                .map(|token| token.expose().to_string())
+                .unwrap_or_default(),
+            template_prefix: config.template_prefix.clone(),
+            template_languages: config.template_languages.clone(),
+        }
+    }
+
+    fn template(&self, template: &WhatsappTemplate) -> serde_json::Value {
+        let language = template.language.as_str();
+        let language = self
+            .template_languages
+            .get(language)
+            .map_or(language, String::as_str);
+        let text = |value: &String| json!({ "type": "text", "text": value });
+
+        let mut components = vec![json!({
+            "type": "body",
+            "parameters": template.parameters.iter().map(text).collect::<Vec<_>>()
+        })];
+        if template.authentication
+            && let Some(code) = template.parameters.first()
+        {
+            components.push(json!({
+                "type": "button",
+                "sub_type": "url",
+                "index": "0",
+                "parameters": [text(code)]
+            }));
+        }
+
+        json!({
+            "name": format!("{}{}", self.template_prefix, template.name),
+            "language": { "code": language },
+            "components": components
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WhatsappCloud {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Whatsapp
+    }
+
+    async fn send(&self, user: &User, notification: &Notification) -> Result<(), NotifyError> {
+        let payload = match &notification.whatsapp {
+            Some(template) => json!({
+                "messaging_product": "whatsapp",
+                "recipient_type": "individual",
+                "to": msisdn(&user.phone),
+                "type": "template",
+                "template": self.template(template)
+            }),
+            None => json!({
+                "messaging_product": "whatsapp",
+                "recipient_type": "individual",
+                "to": msisdn(&user.phone),
+                "type": "text",
+                "text": { "preview_url": false, "body": notification.body }
+            }),
+        };
+
+        let response = inject_trace_context(self.client.post(&self.messages_url))
+            .bearer_auth(&self.access_token)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(NotifyError::Http)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NotifyError::Rejected {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::config::config::OtpConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::otp_db::OtpImpl;
+use crate::helpers::time_helpers::{secs_after, secs_before};
+use crate::models::models::{NewOtp, OtpPurpose, User};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum OtpError {
+    /// Wrong or expired code, or none was issued.
+    Invalid,
+    /// This wrong guess used up the code's attempts; a new one is needed.
+    AttemptsExhausted,
+    /// A code for this purpose was sent less than `resend_interval_secs` ago.
+    ResendTooSoon {
+        retry_after: Duration,
+    },
+    Db(AppError),
+}
+
+impl From<AppError> for OtpError {
+    fn from(e: AppError) -> Self {
+        OtpError::Db(e)
+    }
+}
+
+/// Gets an issued code to the user; `Notifier` is the implementation.
+pub trait OtpDelivery: Send + Sync {
+    fn deliver(&self, user: &User, purpose: OtpPurpose, code: &str, valid_for: Duration);
+}
+
+/// Issues and checks the six-digit one-time codes in the `otp` table.
+///
+/// A user has at most one code per purpose, stored as an HMAC so a database
+/// leak does not expose live codes. Each wrong guess counts against the
+/// code, which is discarded after `max_attempts`, and a new code cannot be
+/// requested within `resend_interval_secs` of the last.
+///
+/// Wrong guesses are counted on the code's own row rather than in `Cache`:
+/// every replica already shares it, and the count is reset and removed
+/// together with the code it belongs to.
+#[derive(Clone)]
+pub struct OtpIssuer {
+    config: OtpConfig,
+    hash_key: Vec<u8>,
+    db: Database,
+    delivery: Arc<dyn OtpDelivery>,
+}
+
+impl OtpIssuer {
+    /// `fallback_key` is used when `otp.hash_key` is unset.
+    pub fn new(
+        config: &OtpConfig,
+        fallback_key: &str,
+        db: Database,
+        delivery: Arc<dyn OtpDelivery>,
+    ) -> Self {
+        let hash_key = config
+            .hash_key
+            .as_ref()
+            .filter(|key| !key.is_empty())
+            .map_or(fallback_key, |key| key.expose());
+
+        OtpIssuer {
+            config: config.clone(),
+            hash_key: hash_key.as_bytes().to_vec(),
+            db,
+            delivery,
+        }
+    }
+
+    /// Issues a new code for `purpose` and delivers it, replacing any the
+    /// user still had for it.
+    pub fn issue(&self, user: &User, purpose: OtpPurpose) -> Result<(), OtpError> {
+        let now = Utc::now();
+        let code = rand::rng().random_range(100_000..=999_999).to_string();
+        // The throttle is part of the write, so concurrent requests cannot
+        // both get past it.
+        let issued = self.db.create_otp(
+            NewOtp {
+                user_id: user.id.clone(),
+                code_hash: self.hash(&user.id, purpose, &code),
+                purpose: purpose.as_str().to_string(),
+                expires_at: secs_after(now, self.config.ttl_secs),
+            },
+            secs_before(now, self.config.resend_interval_secs),
+        )?;
+        if issued.is_none() {
+            let existing = self.db.get_otp(&user.id, purpose.as_str())?;
+            let resend_at = secs_after(existing.created_at, self.config.resend_interval_secs);
+            return Err(OtpError::ResendTooSoon {
+                retry_after: (resend_at - Utc::now()).to_std().unwrap_or_default(),
+            });
+        }
+
+        self.delivery.deliver(
+            user,
+            purpose,
+            &code,
+            Duration::from_secs(self.config.ttl_secs),
+        );
+        Ok(())
+    }
+
+    /// Withdraws any outstanding code for `purpose`, e.g. once what it
+    /// would confirm has changed.
+    pub fn revoke(&self, user_id: &str, purpose: OtpPurpose) -> Result<(), OtpError> {
+        Ok(self.db.delete_otp(user_id, purpose.as_str())?)
+    }
+
+    /// Checks a code, using it up if it matches. A wrong code counts as an
+    /// attempt against the one issued.
+    pub fn verify(&self, user_id: &str, purpose: OtpPurpose, code: &str) -> Result<(), OtpError> {
+        let otp = match self.db.get_otp(user_id, purpose.as_str()) {
+            Ok(otp) => otp,
+            Err(AppError::DieselError(diesel::result::Error::NotFound)) => {
+                return Err(OtpError::Invalid);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if otp.expires_at <= Utc::now() {
+            return Err(OtpError::Invalid);
+        }
+
+        let mut mac = self.mac(user_id, purpose);
+        mac.update(code.trim().as_bytes());
+        let matches = hex::decode(&otp.code_hash).is_ok_and(|hash| mac.verify_slice(&hash).is_ok());
+
+        if matches {
+            self.db.delete_otp_by_id(otp.otp_id)?;
+            return Ok(());
+        }
+
+        let updated = self.db.record_otp_attempt(otp.otp_id)?;
+        if updated.attempts >= self.config.max_attempts {
+            self.db.delete_otp_by_id(otp.otp_id)?;
+            return Err(OtpError::AttemptsExhausted);
+        }
+
+        Err(OtpError::Invalid)
+    }
+
+    /// The code's hash, bound to the user and purpose so a stored hash is
+    /// no use for any other code.
+    fn hash(&self, user_id: &str, purpose: OtpPurpose, code: &str) -> String {
+        let mut mac = self.mac(user_id, purpose);
+        mac.update(code.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn mac(&self, user_id: &str, purpose: OtpPurpose) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.hash_key).expect("HMAC accepts keys of any size");
+        mac.update(user_id.as_bytes());
+        mac.update(b":");
+        mac.update(purpose.as_str().as_bytes());
+        mac.update(b":");
+        mac
+    }
+}
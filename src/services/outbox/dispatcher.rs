@@ -0,0 +1,213 @@
+use crate::config::config::{OutboxConfig, SubscriberConfig};
+use crate::database::db::Database;
+use crate::database::outbox_db::OutboxImpl;
+use crate::models::models::{OutboxDelivery, OutboxEvent};
+use crate::services::telemetry::tracer::inject_trace_context;
+use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde_json::json;
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Sends outbox events to the configured subscribers.
+///
+/// Each poll fans new events out into one delivery per interested
+/// subscriber, then sends the deliveries that are due. A failed delivery is
+/// retried after `retry_delay_secs`, doubling each time, until
+/// `max_attempts`, when it is left as a dead letter. Deliveries are claimed
+/// for longer than a send can take, so several instances can run side by
+/// side.
+#[derive(Clone)]
+pub struct OutboxDispatcher {
+    config: OutboxConfig,
+    db: Database,
+    client: reqwest::Client,
+}
+
+impl OutboxDispatcher {
+    pub fn new(config: &OutboxConfig, db: Database) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+
+        Ok(OutboxDispatcher {
+            config: config.clone(),
+            db,
+            client,
+        })
+    }
+
+    /// Polls until the process exits.
+    pub async fn run(self) {
+        let mut interval =
+            actix_web::rt::time::interval(Duration::from_millis(self.config.poll_interval_ms));
+        loop {
+            interval.tick().await;
+            self.poll().await;
+        }
+    }
+
+    async fn poll(&self) {
+        // Fanning out marks events handled, so with nobody to send them to
+        // they wait until a subscriber is configured.
+        let subscribers = &self.config.subscribers;
+        if subscribers.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self
+            .db
+            .fan_out_outbox_events(self.config.batch_size, |event_type| {
+                subscribers
+                    .iter()
+                    .filter(|subscriber| subscriber.wants(event_type))
+                    .map(|subscriber| subscriber.name.trim().to_string())
+                    .collect()
+            })
+        {
+            tracing::error!(error = ?e, "Failed to fan out outbox events");
+        }
+
+        let lease = TimeDelta::milliseconds(i64::try_from(self.config.timeout_ms).unwrap_or(0))
+            + TimeDelta::seconds(30);
+        let deliveries = match self
+            .db
+            .claim_outbox_deliveries(self.config.batch_size, lease)
+        {
+            Ok(deliveries) => deliveries,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to claim outbox deliveries");
+                return;
+            }
+        };
+
+        let mut sends = JoinSet::new();
+        for (delivery, event) in deliveries {
+            let dispatcher = self.clone();
+            sends.spawn(async move { dispatcher.deliver(delivery, event).await });
+        }
+        while sends.join_next().await.is_some() {}
+    }
+
+    async fn deliver(&self, delivery: OutboxDelivery, event: OutboxEvent) {
+        let Some(subscriber) = self
+            .config
+            .subscribers
+            .iter()
+            .find(|subscriber| subscriber.name.trim() == delivery.subscriber)
+        else {
+            // Removed from the config since the event was fanned out.
+            self.record_failure(
+                &delivery,
+                "subscriber is no longer configured".to_string(),
+                true,
+            );
+            return;
+        };
+
+        match self.send(subscriber, &event).await {
+            Ok(()) => {
+                if let Err(e) = self.db.mark_outbox_delivered(delivery.id) {
+                    tracing::error!(error = ?e, "Failed to mark outbox delivery as delivered");
+                }
+            }
+            Err(error) => {
+                let out_of_attempts = delivery.attempts + 1 >= self.config.max_attempts;
+                self.record_failure(&delivery, error, out_of_attempts);
+            }
+        }
+    }
+
+    async fn send(&self, subscriber: &SubscriberConfig, event: &OutboxEvent) -> Result<(), String> {
+        let body = json!({
+            "id": event.id,
+            "type": event.event_type,
+            "created_at": event.created_at,
+            "data": event.payload,
+        })
+        .to_string();
+        let timestamp = Utc::now().timestamp();
+
+        let response = inject_trace_context(self.client.post(&subscriber.url))
+            .header("Content-Type", "application/json")
+            .header("X-Kharon-Event", &event.event_type)
+            .header("X-Kharon-Event-Id", event.id.to_string())
+            .header(
+                "X-Kharon-Signature",
+                signature(subscriber.secret.expose(), timestamp, &body),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body: String = response
+                .text()
+                .await
+                .unwrap_or_default()
+                .chars()
+                .take(500)
+                .collect();
+            return Err(format!("subscriber answered {}: {}", status.as_u16(), body));
+        }
+        Ok(())
+    }
+
+    fn record_failure(&self, delivery: &OutboxDelivery, error: String, dead: bool) {
+        let retry_at = (!dead).then(|| self.retry_at(delivery.attempts + 1));
+        if dead {
+            tracing::error!(
+                delivery_id = %delivery.id,
+                subscriber = %delivery.subscriber,
+                error = %error,
+                "Outbox delivery moved to dead letters"
+            );
+        } else {
+            tracing::warn!(
+                delivery_id = %delivery.id,
+                subscriber = %delivery.subscriber,
+                error = %error,
+                "Outbox delivery failed, will retry"
+            );
+        }
+
+        if let Err(e) = self.db.record_outbox_failure(delivery.id, error, retry_at) {
+            tracing::error!(error = ?e, "Failed to record outbox delivery failure");
+        }
+    }
+
+    /// When to retry after `failures` failed attempts, with up to a tenth
+    /// added at random so a subscriber coming back is not hit all at once.
+    fn retry_at(&self, failures: i32) -> DateTime<Utc> {
+        let doublings = u32::try_from(failures.saturating_sub(1))
+            .unwrap_or(0)
+            .min(30);
+        let delay = self
+            .config
+            .retry_delay_secs
+            .saturating_mul(1 << doublings)
+            .min(self.config.max_retry_delay_secs);
+        let jitter = rand::rng().random_range(0..=delay / 10);
+
+        Utc::now() + TimeDelta::seconds(i64::try_from(delay + jitter).unwrap_or_default())
+    }
+}
+
+/// `t=<timestamp>,v1=<hex HMAC-SHA256 of "<timestamp>.<body>">`. The
+/// timestamp lets subscribers reject replays of old deliveries.
+fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
@@ -0,0 +1,212 @@
+use crate::config::config::PinConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::user_pin_db::UserPinImpl;
+use crate::helpers::time_helpers::secs_after;
+use crate::models::models::{NewUserPin, OtpPurpose, User};
+use crate::services::otp::issuer::{OtpError, OtpIssuer};
+use argon2::Argon2;
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum PinError {
+    /// Not 4 to 6 digits.
+    InvalidFormat,
+    NotSet,
+    AlreadySet,
+    /// Wrong PIN, with attempts left before the PIN locks.
+    Mismatch {
+        attempts_left: i32,
+    },
+    /// `just_locked` when this attempt was the one that locked it.
+    Locked {
+        retry_after: Duration,
+        just_locked: bool,
+    },
+    /// Requesting or checking the reset code failed.
+    Otp(OtpError),
+    Hash(password_hash::Error),
+    /// The hashing task panicked or was cancelled.
+    Task(tokio::task::JoinError),
+    Db(AppError),
+}
+
+impl From<AppError> for PinError {
+    fn from(e: AppError) -> Self {
+        match e {
+            AppError::DieselError(diesel::result::Error::NotFound) => PinError::NotSet,
+            e => PinError::Db(e),
+        }
+    }
+}
+
+impl From<OtpError> for PinError {
+    fn from(e: OtpError) -> Self {
+        PinError::Otp(e)
+    }
+}
+
+impl From<password_hash::Error> for PinError {
+    fn from(e: password_hash::Error) -> Self {
+        PinError::Hash(e)
+    }
+}
+
+/// Transaction PINs: set once, checked before every payout, changed with the
+/// old PIN or reset with an OTP.
+///
+/// PINs are stored as Argon2id hashes. Every wrong PIN counts against
+/// `max_attempts`, and reaching it locks the PIN for `lock_secs`. Hashing is
+/// deliberately slow and runs on the blocking thread pool.
+#[derive(Clone)]
+pub struct PinManager {
+    config: PinConfig,
+    db: Database,
+    otp: OtpIssuer,
+}
+
+impl PinManager {
+    pub fn new(config: &PinConfig, db: Database, otp: OtpIssuer) -> Self {
+        PinManager {
+            config: config.clone(),
+            db,
+            otp,
+        }
+    }
+
+    pub async fn set(&self, user_id: &str, pin: &str) -> Result<(), PinError> {
+        check_format(pin)?;
+
+        let new_pin = NewUserPin {
+            user_id: user_id.to_string(),
+            pin_hash: hash_pin(pin).await?,
+        };
+        match self.db.create_user_pin(new_pin) {
+            Ok(_) => Ok(()),
+            Err(AppError::DieselError(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            ))) => Err(PinError::AlreadySet),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Checks the PIN, counting a wrong one towards the lock.
+    ///
+    /// The attempt is counted before the slow hash runs and uncounted if
+    /// the PIN matches, so parallel guesses all count.
+    pub async fn verify(&self, user_id: &str, pin: &str) -> Result<(), PinError> {
+        let now = Utc::now();
+        let lock_until = secs_after(now, self.config.lock_secs);
+        let (stored, claimed) =
+            self.db
+                .claim_pin_attempt(user_id, self.config.max_attempts, lock_until)?;
+        if !claimed {
+            return Err(locked(stored.locked_until, false));
+        }
+        // Set only when this attempt used up the last one.
+        let own_lock = stored.locked_until.filter(|until| *until > now);
+
+        // A malformed PIN is still a wrong guess, so it counts like one.
+        let matches = check_format(pin).is_ok() && pin_matches(pin, stored.pin_hash).await?;
+
+        if matches {
+            if self.db.clear_pin_failures(user_id, own_lock)? {
+                return Ok(());
+            }
+            // A wrong guess running alongside this one locked the PIN.
+            let stored = self.db.get_user_pin(user_id)?;
+            return Err(locked(stored.locked_until, false));
+        }
+
+        if own_lock.is_some() {
+            return Err(locked(own_lock, true));
+        }
+        Err(PinError::Mismatch {
+            attempts_left: self.config.max_attempts - stored.failed_attempts,
+        })
+    }
+
+    /// Replaces the PIN after checking the old one, which counts as an attempt.
+    pub async fn change(
+        &self,
+        user_id: &str,
+        old_pin: &str,
+        new_pin: &str,
+    ) -> Result<(), PinError> {
+        check_format(new_pin)?;
+        self.verify(user_id, old_pin).await?;
+
+        self.db
+            .update_user_pin_hash(user_id, &hash_pin(new_pin).await?)?;
+        Ok(())
+    }
+
+    /// Sends the user a code for `reset`.
+    pub fn request_reset(&self, user: &User) -> Result<(), PinError> {
+        self.db.get_user_pin(&user.id)?;
+        self.otp.issue(user, OtpPurpose::PinReset)?;
+        Ok(())
+    }
+
+    /// Replaces a forgotten PIN using a code from `request_reset`. This also
+    /// lifts a lock.
+    pub async fn reset(&self, user_id: &str, code: &str, new_pin: &str) -> Result<(), PinError> {
+        check_format(new_pin)?;
+        self.otp.verify(user_id, OtpPurpose::PinReset, code)?;
+
+        self.db
+            .update_user_pin_hash(user_id, &hash_pin(new_pin).await?)?;
+        Ok(())
+    }
+}
+
+fn locked(locked_until: Option<DateTime<Utc>>, just_locked: bool) -> PinError {
+    let retry_after = locked_until
+        .and_then(|until| (until - Utc::now()).to_std().ok())
+        .unwrap_or_default();
+    PinError::Locked {
+        retry_after,
+        just_locked,
+    }
+}
+
+fn check_format(pin: &str) -> Result<(), PinError> {
+    if (4..=6).contains(&pin.len()) && pin.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(PinError::InvalidFormat)
+    }
+}
+
+async fn hash_pin(pin: &str) -> Result<String, PinError> {
+    let mut salt = [0u8; 16];
+    rand::rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt)?;
+    let pin = pin.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        Ok(Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)?
+            .to_string())
+    })
+    .await
+    .map_err(PinError::Task)?
+}
+
+async fn pin_matches(pin: &str, pin_hash: String) -> Result<bool, PinError> {
+    let pin = pin.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let parsed = PasswordHash::new(&pin_hash)?;
+        match Argon2::default().verify_password(pin.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    })
+    .await
+    .map_err(PinError::Task)?
+}
@@ -0,0 +1,80 @@
+use crate::config::config::{RateLimitConfig, RateLimitRule};
+use crate::services::cache::store::Cache;
+use std::time::Duration;
+
+/// Outcome of counting one request against one rule.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_secs: u64,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    enabled: bool,
+    rules: Vec<RateLimitRule>,
+    cache: Cache,
+}
+
+impl RateLimiter {
+    /// Counters live in the shared cache, so limits hold across replicas
+    /// whenever Redis is configured.
+    pub fn new(config: &RateLimitConfig, cache: Cache) -> Self {
+        RateLimiter {
+            enabled: config.enabled,
+            rules: config.rules.clone(),
+            cache,
+        }
+    }
+
+    pub fn matching_rules(&self, method: &str, path: &str) -> Vec<&RateLimitRule> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.methods.is_empty()
+                    || rule
+                        .methods
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(method))
+            })
+            .filter(|rule| match rule.path.strip_suffix('*') {
+                Some(prefix) => path.starts_with(prefix),
+                None => path == rule.path,
+            })
+            .collect()
+    }
+
+    /// Counts a request for `key` under `rule`. If the store is unreachable
+    /// the request is let through: a Redis outage must not take auth down.
+    pub async fn check(&self, rule: &RateLimitRule, key: &str) -> RateLimitDecision {
+        let window = Duration::from_secs(rule.window_secs);
+        let store_key = format!(
+            "ratelimit:{:?}:{}:{}:{}",
+            rule.key, rule.path, rule.window_secs, key
+        );
+
+        match self.cache.incr_window(&store_key, window).await {
+            Ok(hits) => RateLimitDecision {
+                allowed: hits.count <= rule.limit,
+                limit: rule.limit,
+                remaining: rule.limit.saturating_sub(hits.count),
+                reset_secs: hits.resets_in.as_secs().max(1),
+            },
+            Err(e) => {
+                tracing::warn!(error = ?e, "Rate limit store unavailable, allowing request");
+                RateLimitDecision {
+                    allowed: true,
+                    limit: rule.limit,
+                    remaining: rule.limit,
+                    reset_secs: rule.window_secs,
+                }
+            }
+        }
+    }
+}
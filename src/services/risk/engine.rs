@@ -0,0 +1,285 @@
+use crate::config::config::RiskConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::user_security_log_db::UserSecurityLogsImpl;
+use crate::models::models::{SecurityEventType, UserSecurityLog};
+use crate::services::geolocation::provider::GeoLocation;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::sync::Arc;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Why a login looks risky. `as_str` is the code stored in `flag_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskReason {
+    /// No previous login in the history came from this country.
+    NewCountry,
+    /// Getting here from the previous login's location would need a faster
+    /// journey than `max_travel_speed_kmh`.
+    ImpossibleTravel,
+    /// The address belongs to a hosting, cloud or VPN network.
+    HostingAsn,
+}
+
+impl RiskReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskReason::NewCountry => "new_country",
+            RiskReason::ImpossibleTravel => "impossible_travel",
+            RiskReason::HostingAsn => "hosting_asn",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RiskAssessment {
+    pub reasons: Vec<RiskReason>,
+}
+
+impl RiskAssessment {
+    pub fn is_flagged(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+
+    /// Reason codes joined with commas, or `None` when nothing was flagged.
+    pub fn reason_code(&self) -> Option<String> {
+        self.is_flagged().then(|| {
+            self.reasons
+                .iter()
+                .map(RiskReason::as_str)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+}
+
+/// Called for every flagged login. This is where step-up verification (an
+/// OTP or authenticator challenge before the next sensitive action) plugs in.
+pub trait StepUpHook: Send + Sync {
+    fn on_risky_login(&self, user_id: &str, assessment: &RiskAssessment);
+}
+
+/// Default hook: no challenge yet, the flag is only logged.
+pub struct LogStepUp;
+
+impl StepUpHook for LogStepUp {
+    fn on_risky_login(&self, user_id: &str, assessment: &RiskAssessment) {
+        tracing::warn!(
+            user_id,
+            reasons = assessment.reason_code().unwrap_or_default(),
+            "Risky login flagged for review"
+        );
+    }
+}
+
+/// Compares a login's location with the user's recent successful logins.
+#[derive(Clone)]
+pub struct RiskEngine {
+    config: RiskConfig,
+    db: Database,
+    step_up: Arc<dyn StepUpHook>,
+}
+
+impl RiskEngine {
+    pub fn new(config: &RiskConfig, db: Database, step_up: Arc<dyn StepUpHook>) -> Self {
+        RiskEngine {
+            config: config.clone(),
+            db,
+            step_up,
+        }
+    }
+
+    /// Assesses a login at `at` from `geo`. Call before the login itself is
+    /// written to the security log, so it is not compared with itself.
+    pub fn assess(
+        &self,
+        user_id: &str,
+        geo: &GeoLocation,
+        at: DateTime<Utc>,
+    ) -> Result<RiskAssessment, AppError> {
+        if !self.config.enabled {
+            return Ok(RiskAssessment::default());
+        }
+
+        let since = at - TimeDelta::days(i64::try_from(self.config.history_days).unwrap_or(0));
+        let history = self.db.get_recent_events(
+            user_id,
+            SecurityEventType::LoginSuccess,
+            since,
+            self.config.history_size,
+        )?;
+
+        let mut reasons = Vec::new();
+        if self.is_new_country(geo, &history) {
+            reasons.push(RiskReason::NewCountry);
+        }
+        if self.is_impossible_travel(geo, at, &history) {
+            reasons.push(RiskReason::ImpossibleTravel);
+        }
+        if geo
+            .asn
+            .is_some_and(|asn| self.config.hosting_asns.contains(&asn))
+        {
+            reasons.push(RiskReason::HostingAsn);
+        }
+
+        Ok(RiskAssessment { reasons })
+    }
+
+    /// Hands a flagged login to the step-up hook.
+    pub fn step_up(&self, user_id: &str, assessment: &RiskAssessment) {
+        if assessment.is_flagged() {
+            self.step_up.on_risky_login(user_id, assessment);
+        }
+    }
+
+    /// A first login, or one whose country is unknown, is never "new".
+    fn is_new_country(&self, geo: &GeoLocation, history: &[UserSecurityLog]) -> bool {
+        let Some(country) = geo.country.as_deref().filter(|c| is_known(c)) else {
+            return false;
+        };
+        let previous: Vec<&str> = history
+            .iter()
+            .map(|log| log.country.as_str())
+            .filter(|c| is_known(c))
+            .collect();
+
+        !previous.is_empty()
+            && !previous
+                .iter()
+                .any(|previous| previous.eq_ignore_ascii_case(country))
+    }
+
+    /// Compares against the latest previous login that has coordinates.
+    fn is_impossible_travel(
+        &self,
+        geo: &GeoLocation,
+        at: DateTime<Utc>,
+        history: &[UserSecurityLog],
+    ) -> bool {
+        let (Some(lat), Some(long)) = (geo.latitude, geo.longitude) else {
+            return false;
+        };
+        let Some((previous, previous_lat, previous_long)) = history
+            .iter()
+            .find_map(|log| Some((log, log.latitude?, log.longitude?)))
+        else {
+            return false;
+        };
+        let Some(previous_at) = previous.created_at else {
+            return false;
+        };
+
+        let distance_km = haversine_km(previous_lat, previous_long, lat, long);
+        if distance_km < self.config.min_travel_distance_km {
+            return false;
+        }
+
+        // Floor the interval at a minute so near-simultaneous logins do not
+        // divide by zero.
+        let hours = (at - previous_at).num_seconds().max(60) as f64 / 3600.0;
+        distance_km / hours > self.config.max_travel_speed_kmh
+    }
+}
+
+fn is_known(country: &str) -> bool {
+    !country.is_empty() && country != "unknown"
+}
+
+/// Great-circle distance between two points, in kilometres.
+fn haversine_km(lat1: f64, long1: f64, lat2: f64, long2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_long = (long2 - long1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::r2d2::{ConnectionManager, Pool};
+
+    const LAGOS: (f64, f64) = (6.4541, 3.3947);
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+
+    /// An engine with the default config. The checks under test only look at
+    /// the history they are given, so the pool never connects.
+    fn engine() -> RiskEngine {
+        let pool = Pool::builder()
+            .min_idle(Some(0))
+            .build_unchecked(ConnectionManager::new("postgres://unused"));
+
+        RiskEngine::new(
+            &RiskConfig::default(),
+            Database { pool },
+            Arc::new(LogStepUp),
+        )
+    }
+
+    fn location(country: &str, (lat, long): (f64, f64)) -> GeoLocation {
+        GeoLocation {
+            country: Some(country.to_string()),
+            latitude: Some(lat),
+            longitude: Some(long),
+            ..Default::default()
+        }
+    }
+
+    fn login(country: &str, (lat, long): (f64, f64), at: DateTime<Utc>) -> UserSecurityLog {
+        UserSecurityLog {
+            log_id: uuid::Uuid::new_v4(),
+            user_id: Some("user".to_string()),
+            ip_address: "192.0.2.1".to_string(),
+            city: "unknown".to_string(),
+            country: country.to_string(),
+            failed_login_attempts: 0,
+            flagged_for_review: false,
+            created_at: Some(at),
+            event_type: SecurityEventType::LoginSuccess.as_str().to_string(),
+            user_agent: None,
+            path: "/api/v1/auth/login".to_string(),
+            method: "POST".to_string(),
+            status: 200,
+            latitude: Some(lat),
+            longitude: Some(long),
+            asn: None,
+            flag_reason: None,
+        }
+    }
+
+    #[test]
+    fn measures_great_circle_distance() {
+        // London to Paris is about 343.5 km.
+        let distance = haversine_km(LONDON.0, LONDON.1, 48.8566, 2.3522);
+        assert!((distance - 343.5).abs() < 1.0, "distance {}", distance);
+        assert_eq!(haversine_km(LAGOS.0, LAGOS.1, LAGOS.0, LAGOS.1), 0.0);
+    }
+
+    #[test]
+    fn same_country_is_not_new() {
+        let history = [login("NG", LAGOS, Utc::now())];
+        assert!(!engine().is_new_country(&location("NG", LAGOS), &history));
+        assert!(!engine().is_new_country(&location("ng", LAGOS), &history));
+    }
+
+    #[test]
+    fn flags_a_new_country() {
+        let history = [login("NG", LAGOS, Utc::now())];
+        assert!(engine().is_new_country(&location("GB", LONDON), &history));
+        // A first login has nothing to compare with.
+        assert!(!engine().is_new_country(&location("GB", LONDON), &[]));
+    }
+
+    #[test]
+    fn flags_travel_faster_than_the_limit() {
+        // Lagos to London is about 5,000 km.
+        let left = Utc::now() - TimeDelta::hours(12);
+        let history = [login("NG", LAGOS, left)];
+        let london = location("GB", LONDON);
+        let engine = engine();
+
+        assert!(engine.is_impossible_travel(&london, left + TimeDelta::hours(1), &history));
+        assert!(!engine.is_impossible_travel(&london, left + TimeDelta::hours(10), &history));
+    }
+}
@@ -0,0 +1,26 @@
+use super::{redaction::RedactingMakeWriter, tracer::SERVICE_NAME};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Installs the global JSON subscriber. `RUST_LOG` overrides the default
+/// `info` filter; records from crates still on `log` are bridged in too.
+/// Spans are also handed to OpenTelemetry through `tracer_provider`.
+pub fn init_logging(tracer_provider: &SdkTracerProvider) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(RedactingMakeWriter::new(std::io::stdout));
+
+    let otel_layer =
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(SERVICE_NAME));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
+        .init();
+}
@@ -0,0 +1,3 @@
+pub mod logging;
+pub mod redaction;
+pub mod tracer;
@@ -0,0 +1,135 @@
+use regex::{Captures, Regex};
+use std::io::{self, Write};
+use std::sync::LazyLock;
+use tracing_subscriber::fmt::MakeWriter;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// JWTs and bearer credentials, wherever they appear in a line.
+static TOKEN_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*|\bbearer\s+[A-Za-z0-9._~+/=-]+",
+    )
+    .expect("token pattern should compile")
+});
+
+/// Secret-looking keys in both JSON (`"token":"..."`) and `key=value` form.
+static SECRET_FIELD_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)("?\b(?:[a-z_]*token|x-api-key|api_key|authorization|secret[a-z_]*|password|otp|otp_code|pin)"?\s*[:=]\s*"?)([^",\s}]+)"#,
+    )
+    .expect("secret field pattern should compile")
+});
+
+/// Phone numbers and NUBAN account numbers: standalone runs of 10-15 digits.
+/// A leading `-` is excluded so the all-digit tail of a UUID is left alone.
+static DIGIT_RUN_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(^|[^\w-])(\+?\d{10,15})\b").expect("digit run pattern should compile")
+});
+
+/// Masks credentials, phone numbers and account numbers in a formatted log line.
+/// Digit runs keep their last four digits so support can still correlate records.
+pub fn redact(line: &str) -> String {
+    let line = TOKEN_PATTERN.replace_all(line, REDACTED);
+    let line = SECRET_FIELD_PATTERN
+        .replace_all(&line, |caps: &Captures| format!("{}{}", &caps[1], REDACTED));
+
+    DIGIT_RUN_PATTERN
+        .replace_all(&line, |caps: &Captures| {
+            let digits = caps[2].trim_start_matches('+');
+            let visible = &digits[digits.len() - 4..];
+            format!("{}{}{}", &caps[1], "*".repeat(digits.len() - 4), visible)
+        })
+        .into_owned()
+}
+
+/// Wraps a `MakeWriter` so every line the fmt layer emits is redacted first.
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+        }
+    }
+}
+
+pub struct RedactingWriter<W> {
+    inner: W,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // The fmt layer hands over each event as a single buffer, so a write is
+    // always a whole line and patterns never straddle two calls.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.inner.write_all(redact(&line).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_phone_numbers_but_the_last_four_digits() {
+        assert_eq!(
+            redact("OTP sent phone=+2348012345678"),
+            "OTP sent phone=*********5678"
+        );
+        assert_eq!(
+            redact("account 0123456789 added"),
+            "account ******6789 added"
+        );
+    }
+
+    #[test]
+    fn redacts_bearer_tokens_and_jwts() {
+        assert_eq!(
+            redact("authorization header: Bearer abc.def-123"),
+            "authorization header: [REDACTED]"
+        );
+        assert_eq!(
+            redact("token eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.sig_1"),
+            "token [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn redacts_secret_json_fields() {
+        assert_eq!(
+            redact(r#"{"phone_verified":true,"refresh_token":"r3fr3sh","password": "hunter2"}"#),
+            r#"{"phone_verified":true,"refresh_token":"[REDACTED]","password": "[REDACTED]"}"#
+        );
+        assert_eq!(
+            redact("x-api-key=k3y status=401"),
+            "x-api-key=[REDACTED] status=401"
+        );
+    }
+
+    #[test]
+    fn leaves_ordinary_lines_alone() {
+        for line in [
+            "GET /api/v1/users/me 200 in 12ms",
+            "user_id=7f9c2ba4-e88f-11d0-9abc-001122334455 logged in",
+            "processed 1500 events",
+        ] {
+            assert_eq!(redact(line), line);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use crate::config::config::TotpConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::user_totp_db::UserTotpImpl;
+use crate::models::models::{NewRecoveryCode, NewUserTotp, User, UserTotp};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, TOTP, TotpUrlError};
+
+const STEP_SECS: u64 = 30;
+const SECRET_LEN: usize = 20;
+const NONCE_LEN: usize = 12;
+/// Recovery codes are shown as two groups of five, e.g. `k7q2m-x9fjp`.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Debug)]
+pub enum TotpError {
+    /// No `totp.encryption_key` is configured.
+    Unavailable,
+    AlreadyEnabled,
+    /// There is no enrolment to confirm or turn off.
+    NotEnrolled,
+    /// The user must use TOTP (admins always do) but has not enrolled.
+    EnrolmentRequired,
+    CodeRequired,
+    /// Wrong, expired or already used code or recovery code.
+    InvalidCode,
+    /// A stored secret could not be decrypted, usually after a key change.
+    Decrypt,
+    Secret(TotpUrlError),
+    Db(AppError),
+}
+
+impl From<AppError> for TotpError {
+    fn from(e: AppError) -> Self {
+        TotpError::Db(e)
+    }
+}
+
+impl From<TotpUrlError> for TotpError {
+    fn from(e: TotpUrlError) -> Self {
+        TotpError::Secret(e)
+    }
+}
+
+/// How a sensitive request got past `TotpAuthenticator::check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpCheck {
+    /// The user has no TOTP and does not need it.
+    NotRequired,
+    Code,
+    RecoveryCode,
+}
+
+/// What the user needs to add the account to an authenticator app.
+#[derive(Debug)]
+pub struct Enrolment {
+    /// Base32 secret, for apps that cannot scan a QR code.
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// RFC 6238 time-based codes (SHA-1, six digits, 30-second steps) as a
+/// second factor, with single-use recovery codes for lost devices.
+///
+/// Secrets are stored encrypted with AES-256-GCM, bound to the user they
+/// belong to. Each accepted code uses up its time step, so a code cannot be
+/// replayed within its window.
+#[derive(Clone)]
+pub struct TotpAuthenticator {
+    config: TotpConfig,
+    db: Database,
+    /// `None` without a usable `totp.encryption_key`.
+    cipher: Option<Aes256Gcm>,
+}
+
+impl TotpAuthenticator {
+    pub fn new(config: &TotpConfig, db: Database) -> Self {
+        let cipher = config
+            .encryption_key
+            .as_ref()
+            .filter(|key| !key.is_empty())
+            .and_then(|key| hex::decode(key.expose()).ok())
+            .filter(|key| key.len() == 32)
+            .map(|key| Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)));
+
+        TotpAuthenticator {
+            config: config.clone(),
+            db,
+            cipher,
+        }
+    }
+
+    /// Starts enrolment with a fresh secret, replacing an unconfirmed one.
+    /// TOTP is not required until `confirm` succeeds.
+    pub fn enrol(&self, user: &User) -> Result<Enrolment, TotpError> {
+        if self.enabled_totp(&user.id)?.is_some() {
+            return Err(TotpError::AlreadyEnabled);
+        }
+
+        let mut secret = vec![0u8; SECRET_LEN];
+        rand::rng().fill_bytes(&mut secret);
+        let totp = self.totp(secret.clone(), &user.phone)?;
+
+        let (secret_ciphertext, secret_nonce) = self.encrypt(&user.id, &secret)?;
+        self.db.create_pending_totp(NewUserTotp {
+            user_id: user.id.clone(),
+            secret_ciphertext,
+            secret_nonce,
+        })?;
+
+        Ok(Enrolment {
+            secret: totp.get_secret_base32(),
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    /// Completes enrolment with a code from the app. Returns the recovery
+    /// codes, which are only ever shown this once.
+    pub fn confirm(&self, user: &User, code: &str) -> Result<Vec<String>, TotpError> {
+        let stored = match self.db.get_user_totp(&user.id) {
+            Ok(stored) => stored,
+            Err(AppError::DieselError(diesel::result::Error::NotFound)) => {
+                return Err(TotpError::NotEnrolled);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if stored.enabled_at.is_some() {
+            return Err(TotpError::AlreadyEnabled);
+        }
+
+        let step = self
+            .matching_step(&stored, &user.phone, code)?
+            .ok_or(TotpError::InvalidCode)?;
+
+        let (codes, hashed) = self.recovery_codes(&user.id);
+        if !self.db.enable_user_totp(&user.id, step, hashed)? {
+            return Err(TotpError::AlreadyEnabled);
+        }
+
+        Ok(codes)
+    }
+
+    /// Checks the second factor for a sensitive request. `code` is either
+    /// a current code from the app or an unused recovery code, which is
+    /// then used up.
+    pub fn check(&self, user: &User, code: Option<&str>) -> Result<TotpCheck, TotpError> {
+        let Some(stored) = self.enabled_totp(&user.id)? else {
+            return if is_admin(user) {
+                Err(TotpError::EnrolmentRequired)
+            } else {
+                Ok(TotpCheck::NotRequired)
+            };
+        };
+        let code = code.map(str::trim).filter(|code| !code.is_empty());
+        let Some(code) = code else {
+            return Err(TotpError::CodeRequired);
+        };
+
+        if code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit()) {
+            let step = self.matching_step(&stored, &user.phone, code)?;
+            return match step {
+                Some(step) if self.db.use_totp_step(&user.id, step)? => Ok(TotpCheck::Code),
+                _ => Err(TotpError::InvalidCode),
+            };
+        }
+
+        if self
+            .db
+            .use_recovery_code(&user.id, &hash_recovery_code(code))?
+        {
+            return Ok(TotpCheck::RecoveryCode);
+        }
+
+        Err(TotpError::InvalidCode)
+    }
+
+    /// Turns TOTP off. Check the second factor first.
+    pub fn disable(&self, user: &User) -> Result<(), TotpError> {
+        if self.enabled_totp(&user.id)?.is_none() {
+            return Err(TotpError::NotEnrolled);
+        }
+
+        self.db.delete_user_totp(&user.id)?;
+        Ok(())
+    }
+
+    /// Replaces every recovery code, used or not. Check the second factor
+    /// first.
+    pub fn regenerate_recovery_codes(&self, user: &User) -> Result<Vec<String>, TotpError> {
+        if self.enabled_totp(&user.id)?.is_none() {
+            return Err(TotpError::NotEnrolled);
+        }
+
+        let (codes, hashed) = self.recovery_codes(&user.id);
+        self.db.replace_recovery_codes(&user.id, hashed)?;
+        Ok(codes)
+    }
+
+    fn enabled_totp(&self, user_id: &str) -> Result<Option<UserTotp>, TotpError> {
+        match self.db.get_user_totp(user_id) {
+            Ok(stored) => Ok(stored.enabled_at.is_some().then_some(stored)),
+            Err(AppError::DieselError(diesel::result::Error::NotFound)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The time step, within `skew_steps` of now, whose code is `code`.
+    fn matching_step(
+        &self,
+        stored: &UserTotp,
+        account_name: &str,
+        code: &str,
+    ) -> Result<Option<i64>, TotpError> {
+        let secret = self.decrypt(stored)?;
+        let totp = self.totp(secret, account_name)?;
+
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let step = step_matching(&totp, code, now, self.config.skew_steps);
+
+        Ok(step.and_then(|step| i64::try_from(step).ok()))
+    }
+
+    fn totp(&self, secret: Vec<u8>, account_name: &str) -> Result<TOTP, TotpError> {
+        Ok(TOTP::new(
+            Algorithm::SHA1,
+            6,
+            0,
+            STEP_SECS,
+            secret,
+            Some(self.config.issuer.clone()),
+            account_name.to_string(),
+        )?)
+    }
+
+    /// Encrypts a secret, with the user id as associated data so a secret
+    /// copied to another user's row will not decrypt.
+    fn encrypt(&self, user_id: &str, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), TotpError> {
+        let cipher = self.cipher.as_ref().ok_or(TotpError::Unavailable)?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: secret,
+            aad: user_id.as_bytes(),
+        };
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| TotpError::Unavailable)?;
+
+        Ok((ciphertext, nonce.to_vec()))
+    }
+
+    fn decrypt(&self, stored: &UserTotp) -> Result<Vec<u8>, TotpError> {
+        let cipher = self.cipher.as_ref().ok_or(TotpError::Unavailable)?;
+        if stored.secret_nonce.len() != NONCE_LEN {
+            return Err(TotpError::Decrypt);
+        }
+
+        let payload = Payload {
+            msg: &stored.secret_ciphertext,
+            aad: stored.user_id.as_bytes(),
+        };
+        cipher
+            .decrypt(Nonce::from_slice(&stored.secret_nonce), payload)
+            .map_err(|_| TotpError::Decrypt)
+    }
+
+    /// New recovery codes, in the clear for the user and hashed for storage.
+    fn recovery_codes(&self, user_id: &str) -> (Vec<String>, Vec<NewRecoveryCode>) {
+        let mut rng = rand::rng();
+        let codes: Vec<String> = (0..self.config.recovery_codes)
+            .map(|_| {
+                let mut code: String = (0..10)
+                    .map(|_| {
+                        let index = rng.random_range(0..RECOVERY_CODE_ALPHABET.len());
+                        char::from(RECOVERY_CODE_ALPHABET[index])
+                    })
+                    .collect();
+                code.insert(5, '-');
+                code
+            })
+            .collect();
+
+        let hashed = codes
+            .iter()
+            .map(|code| NewRecoveryCode {
+                user_id: user_id.to_string(),
+                code_hash: hash_recovery_code(code),
+            })
+            .collect();
+
+        (codes, hashed)
+    }
+}
+
+pub fn is_admin(user: &User) -> bool {
+    user.role.eq_ignore_ascii_case("admin")
+}
+
+/// Recovery codes are random enough that a plain hash is safe to store.
+/// Case, spaces and dashes are ignored.
+fn hash_recovery_code(code: &str) -> String {
+    let normalised: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    hex::encode(Sha256::digest(normalised.as_bytes()))
+}
+
+/// The time step within `skew` steps of `now` (Unix seconds) whose code is
+/// `code`.
+fn step_matching(totp: &TOTP, code: &str, now: u64, skew: u8) -> Option<u64> {
+    let now = now / STEP_SECS;
+    let skew = u64::from(skew);
+    (now.saturating_sub(skew)..=now + skew).find(|step| {
+        bool::from(
+            totp.generate(step * STEP_SECS)
+                .as_bytes()
+                .ct_eq(code.as_bytes()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 key from RFC 6238 appendix B.
+    fn rfc6238() -> TOTP {
+        TOTP::new(
+            Algorithm::SHA1,
+            6,
+            0,
+            STEP_SECS,
+            b"12345678901234567890".to_vec(),
+            None,
+            "test".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn generates_rfc6238_codes() {
+        // Appendix B lists 8 digits; 6-digit codes are the last six.
+        let totp = rfc6238();
+        for (time, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(totp.generate(time), code, "time {}", time);
+        }
+    }
+
+    #[test]
+    fn matches_codes_within_skew() {
+        let totp = rfc6238();
+        assert_eq!(step_matching(&totp, "287082", 59, 0), Some(1));
+        assert_eq!(step_matching(&totp, "287082", 89, 1), Some(1));
+        assert_eq!(step_matching(&totp, "287082", 89, 0), None);
+        assert_eq!(step_matching(&totp, "287082", 119, 1), None);
+        assert_eq!(step_matching(&totp, "287082", 0, 1), Some(1));
+        assert_eq!(step_matching(&totp, "287083", 59, 1), None);
+        assert_eq!(step_matching(&totp, "28708", 59, 1), None);
+    }
+}
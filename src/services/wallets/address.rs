@@ -0,0 +1,185 @@
+use crate::models::models::Network;
+use sha3::{Digest, Keccak256};
+
+/// The Starknet field prime, 2^251 + 17 * 2^192 + 1, as 64 hex digits.
+/// Every felt, and so every address, is below it.
+const STARKNET_PRIME: &str = "0800000000000011000000000000000000000000000000000000000000000001";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// Not `0x` followed by hex digits of the right length.
+    Format,
+    /// The zero address, which no one owns.
+    Zero,
+    /// A Starknet value that is not a felt.
+    OutOfRange,
+    /// Mixed-case EVM address whose case does not match its EIP-55 checksum,
+    /// usually a typo.
+    Checksum,
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::Format => write!(f, "address is not valid hex of the right length"),
+            AddressError::Zero => write!(f, "address cannot be zero"),
+            AddressError::OutOfRange => write!(f, "address is outside the Starknet field"),
+            AddressError::Checksum => write!(f, "address checksum does not match"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Checks `address` for `network` and returns the one form it is stored and
+/// looked up in, so the same wallet typed two ways is still one wallet:
+/// Starknet addresses as `0x` and 64 lowercase hex digits, EVM addresses
+/// with their EIP-55 checksum.
+pub fn normalize(network: Network, address: &str) -> Result<String, AddressError> {
+    let digits = address
+        .trim()
+        .strip_prefix("0x")
+        .or_else(|| address.trim().strip_prefix("0X"))
+        .ok_or(AddressError::Format)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(AddressError::Format);
+    }
+
+    if network.is_evm() {
+        normalize_evm(digits)
+    } else {
+        normalize_starknet(digits)
+    }
+}
+
+fn normalize_starknet(digits: &str) -> Result<String, AddressError> {
+    if digits.len() > 64 {
+        return Err(AddressError::Format);
+    }
+
+    let padded = format!("{:0>64}", digits.to_ascii_lowercase());
+    if padded.bytes().all(|b| b == b'0') {
+        return Err(AddressError::Zero);
+    }
+    // Equal-length lowercase hex compares like the numbers it spells.
+    if padded.as_str() >= STARKNET_PRIME {
+        return Err(AddressError::OutOfRange);
+    }
+
+    Ok(format!("0x{}", padded))
+}
+
+fn normalize_evm(digits: &str) -> Result<String, AddressError> {
+    if digits.len() != 40 {
+        return Err(AddressError::Format);
+    }
+    if digits.bytes().all(|b| b == b'0') {
+        return Err(AddressError::Zero);
+    }
+
+    let checksummed = eip55(&digits.to_ascii_lowercase());
+    // All one case carries no checksum; mixed case must match it.
+    let has_lower = digits.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = digits.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper && checksummed[2..] != *digits {
+        return Err(AddressError::Checksum);
+    }
+
+    Ok(checksummed)
+}
+
+/// EIP-55: each letter is uppercased where the matching nibble of the
+/// Keccak-256 hash of the lowercase hex is 8 or more.
+fn eip55(lowercase: &str) -> String {
+    let hash = Keccak256::digest(lowercase.as_bytes());
+
+    let mut checksummed = String::with_capacity(42);
+    checksummed.push_str("0x");
+    for (i, c) in lowercase.chars().enumerate() {
+        let nibble = if i % 2 == 0 {
+            hash[i / 2] >> 4
+        } else {
+            hash[i / 2] & 0x0f
+        };
+        checksummed.push(if nibble >= 8 {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        });
+    }
+    checksummed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The examples from EIP-55, including the all-caps and all-lowercase
+    /// ones, whose checksums happen to need no letters changed.
+    const EIP55: [&str; 8] = [
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksums_eip55_examples() {
+        for address in EIP55 {
+            let lowercase = address.to_ascii_lowercase();
+            let uppercase = format!("0x{}", address[2..].to_ascii_uppercase());
+            for input in [address, lowercase.as_str(), uppercase.as_str()] {
+                assert_eq!(normalize(Network::Ethereum, input).as_deref(), Ok(address));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_a_wrong_checksum() {
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let flipped = address.replacen('a', "A", 1);
+        assert_eq!(
+            normalize(Network::Base, &flipped),
+            Err(AddressError::Checksum)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_evm_addresses() {
+        for input in [
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg",
+        ] {
+            assert_eq!(
+                normalize(Network::Ethereum, input),
+                Err(AddressError::Format)
+            );
+        }
+        assert_eq!(
+            normalize(Network::Ethereum, &format!("0x{}", "0".repeat(40))),
+            Err(AddressError::Zero)
+        );
+    }
+
+    #[test]
+    fn pads_starknet_addresses() {
+        assert_eq!(
+            normalize(
+                Network::Starknet,
+                "0x49D36570D4E46F48E99674BD3FCC84644DDD6B96F7C741B1562B82F9E004DC7"
+            )
+            .as_deref(),
+            Ok("0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7")
+        );
+        assert_eq!(
+            normalize(Network::Starknet, &format!("0x{}", STARKNET_PRIME)),
+            Err(AddressError::OutOfRange)
+        );
+        assert_eq!(normalize(Network::Starknet, "0x0"), Err(AddressError::Zero));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod address;
+pub mod ownership;
+pub mod signatures;
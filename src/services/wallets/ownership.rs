@@ -0,0 +1,206 @@
+use super::signatures::{
+    SignatureError, evm_challenge_message, parse_felt, starknet_challenge_hash,
+    starknet_challenge_typed_data, starknet_keccak, verify_personal_sign,
+    verify_starknet_signature,
+};
+use crate::config::config::WalletsConfig;
+use crate::database::db::{AppError, Database};
+use crate::database::user_wallet_db::UserWalletImpl;
+use crate::helpers::time_helpers::secs_after;
+use crate::models::models::{Network, UserWallet};
+use crate::services::telemetry::tracer::inject_trace_context;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde_json::json;
+use starknet_crypto::FieldElement;
+use std::time::Duration;
+
+/// Entry points account contracts expose their signer's public key on:
+/// OpenZeppelin and Braavos, then Argent, in Cairo 1 and Cairo 0 spellings.
+const PUBLIC_KEY_ENTRY_POINTS: [&str; 4] =
+    ["get_public_key", "getPublicKey", "get_owner", "getSigner"];
+
+#[derive(Debug)]
+pub enum OwnershipError {
+    AlreadyVerified,
+    /// No challenge was issued, or it expired or was already answered.
+    NoChallenge,
+    Signature(SignatureError),
+    /// Starknet wallets need `wallets.starknet_rpc_url`.
+    Unavailable,
+    /// The node gave no public key for the account, usually because it is
+    /// not deployed yet.
+    NoPublicKey,
+    Rpc(String),
+    Db(AppError),
+}
+
+impl From<AppError> for OwnershipError {
+    fn from(e: AppError) -> Self {
+        OwnershipError::Db(e)
+    }
+}
+
+impl From<SignatureError> for OwnershipError {
+    fn from(e: SignatureError) -> Self {
+        OwnershipError::Signature(e)
+    }
+}
+
+/// Proof that a user holds the key of a wallet they linked.
+///
+/// The user asks for a challenge, signs it with the wallet and sends the
+/// signature back. EVM wallets `personal_sign` a text message and the
+/// signer is recovered from the signature. Starknet accounts are contracts,
+/// so they sign SNIP-12 typed data and the signature is checked against the
+/// public key the account reports on chain. Each challenge is good for one
+/// successful answer within `challenge_ttl_secs`.
+#[derive(Clone)]
+pub struct WalletOwnership {
+    config: WalletsConfig,
+    db: Database,
+    client: reqwest::Client,
+}
+
+impl WalletOwnership {
+    pub fn new(config: &WalletsConfig, db: Database) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.rpc_timeout_ms))
+            .build()?;
+
+        Ok(WalletOwnership {
+            config: config.clone(),
+            db,
+            client,
+        })
+    }
+
+    /// Issues a new challenge for `wallet`, replacing any earlier one, and
+    /// returns what the wallet should sign and when it stops being accepted.
+    pub fn challenge(
+        &self,
+        wallet: &UserWallet,
+    ) -> Result<(serde_json::Value, DateTime<Utc>), OwnershipError> {
+        if wallet.verified_at.is_some() {
+            return Err(OwnershipError::AlreadyVerified);
+        }
+        let network = wallet_network(wallet)?;
+        if !network.is_evm() && self.config.starknet_rpc_url.is_none() {
+            return Err(OwnershipError::Unavailable);
+        }
+
+        // 31 bytes, so the nonce is also a felt.
+        let mut bytes = [0u8; 31];
+        rand::rng().fill_bytes(&mut bytes);
+        let nonce = format!("0x{}", hex::encode(bytes));
+        let expires_at = secs_after(Utc::now(), self.config.challenge_ttl_secs);
+        self.db
+            .set_wallet_challenge(&wallet.user_id, &wallet.id, &nonce, expires_at)?;
+
+        let address = wallet.wallet_address.as_deref().unwrap_or_default();
+        let challenge = if network.is_evm() {
+            json!({
+                "kind": "personal_sign",
+                "message": evm_challenge_message(address, network, &nonce),
+            })
+        } else {
+            let hash = starknet_challenge_hash(&self.config.starknet_chain_id, address, &nonce)?;
+            json!({
+                "kind": "typed_data",
+                "typedData": starknet_challenge_typed_data(&self.config.starknet_chain_id, &nonce),
+                "messageHash": format!("{:#x}", hash),
+            })
+        };
+        Ok((challenge, expires_at))
+    }
+
+    /// Checks `signature` answers the wallet's outstanding challenge and, if
+    /// so, marks the wallet verified.
+    pub async fn verify(
+        &self,
+        wallet: &UserWallet,
+        signature: &[String],
+    ) -> Result<UserWallet, OwnershipError> {
+        if wallet.verified_at.is_some() {
+            return Err(OwnershipError::AlreadyVerified);
+        }
+        let nonce = match (&wallet.challenge_nonce, wallet.challenge_expires_at) {
+            (Some(nonce), Some(expires_at)) if expires_at > Utc::now() => nonce,
+            _ => return Err(OwnershipError::NoChallenge),
+        };
+        let network = wallet_network(wallet)?;
+        let address = wallet.wallet_address.as_deref().unwrap_or_default();
+
+        if network.is_evm() {
+            let [signature] = signature else {
+                return Err(SignatureError::Malformed.into());
+            };
+            let message = evm_challenge_message(address, network, nonce);
+            verify_personal_sign(address, &message, signature)?;
+        } else {
+            let hash = starknet_challenge_hash(&self.config.starknet_chain_id, address, nonce)?;
+            let public_key = self.starknet_public_key(address).await?;
+            verify_starknet_signature(&public_key, &hash, signature)?;
+        }
+
+        // Answered twice at once, only one of them completes it.
+        self.db
+            .complete_wallet_challenge(&wallet.id, nonce)?
+            .ok_or(OwnershipError::NoChallenge)
+    }
+
+    /// Reads the account's signer key from the node, trying each of
+    /// `PUBLIC_KEY_ENTRY_POINTS` in turn.
+    async fn starknet_public_key(&self, account: &str) -> Result<FieldElement, OwnershipError> {
+        let Some(url) = &self.config.starknet_rpc_url else {
+            return Err(OwnershipError::Unavailable);
+        };
+
+        for entry_point in PUBLIC_KEY_ENTRY_POINTS {
+            let payload = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "starknet_call",
+                "params": {
+                    "request": {
+                        "contract_address": account,
+                        "entry_point_selector": format!("{:#x}", starknet_keccak(entry_point.as_bytes())),
+                        "calldata": []
+                    },
+                    "block_id": "latest"
+                }
+            });
+            let response: serde_json::Value = inject_trace_context(self.client.post(url))
+                .json(&payload)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(|e| OwnershipError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| OwnershipError::Rpc(e.to_string()))?;
+
+            // A JSON-RPC error here means the account has no such entry
+            // point, or no contract at all.
+            let public_key = response["result"]
+                .as_array()
+                .and_then(|result| result.first())
+                .and_then(|value| value.as_str())
+                .and_then(parse_felt)
+                .filter(|key| *key != FieldElement::ZERO);
+            if let Some(public_key) = public_key {
+                return Ok(public_key);
+            }
+        }
+        Err(OwnershipError::NoPublicKey)
+    }
+}
+
+fn wallet_network(wallet: &UserWallet) -> Result<Network, OwnershipError> {
+    // Every stored network is one of ours, enforced by a check constraint.
+    wallet
+        .network
+        .as_deref()
+        .and_then(Network::parse)
+        .ok_or(OwnershipError::Unavailable)
+}
@@ -0,0 +1,390 @@
+use super::address::normalize;
+use crate::models::models::Network;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use starknet_crypto::{FieldElement, pedersen_hash};
+
+/// Shown to the user in their wallet, so it should say what signing does.
+/// As a Starknet short string it must stay within 31 characters.
+const STATEMENT: &str = "Prove wallet ownership";
+const DOMAIN_NAME: &str = "Kharon";
+const DOMAIN_TYPE: &str = "StarkNetDomain(name:felt,version:felt,chainId:felt)";
+const CHALLENGE_TYPE: &str = "Challenge(statement:felt,nonce:felt)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    /// Not the shape a signature on the wallet's network has.
+    Malformed,
+    /// Well formed, but not made by the wallet's key.
+    Mismatch,
+    /// A Starknet signature in a layout we cannot find the owner's
+    /// signature in.
+    Unsupported,
+}
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Malformed => write!(f, "signature is malformed"),
+            SignatureError::Mismatch => write!(f, "signature was not made by this wallet"),
+            SignatureError::Unsupported => write!(
+                f,
+                "signature layout is not supported; send [r, s] from the account's owner key"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// The text an EVM wallet is asked to `personal_sign`.
+pub fn evm_challenge_message(address: &str, network: Network, nonce: &str) -> String {
+    format!(
+        "Sign this message to prove you own this wallet on {}.\n\nWallet: {}\nNetwork: {}\nNonce: {}",
+        DOMAIN_NAME,
+        address,
+        network.as_str(),
+        nonce
+    )
+}
+
+/// Checks a 65-byte `personal_sign` signature of `message` was made by the
+/// key behind the checksummed `address`.
+pub fn verify_personal_sign(
+    address: &str,
+    message: &str,
+    signature: &str,
+) -> Result<(), SignatureError> {
+    let bytes = hex::decode(signature.trim().trim_start_matches("0x"))
+        .map_err(|_| SignatureError::Malformed)?;
+    if bytes.len() != 65 {
+        return Err(SignatureError::Malformed);
+    }
+    // Wallets send v as 27 or 28; some hardware wallets as 0 or 1.
+    let v = match bytes[64] {
+        27 | 28 => bytes[64] - 27,
+        0 | 1 => bytes[64],
+        _ => return Err(SignatureError::Malformed),
+    };
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or(SignatureError::Malformed)?;
+    let mut signature =
+        Signature::from_slice(&bytes[..64]).map_err(|_| SignatureError::Malformed)?;
+    // A high s is as valid to ecrecover, but k256 only accepts the low form;
+    // flipping s flips the parity of the recovered point.
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+    let hash = Keccak256::digest(prefixed.as_bytes());
+    let key = VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id)
+        .map_err(|_| SignatureError::Mismatch)?;
+
+    let point = key.to_encoded_point(false);
+    let digest = Keccak256::digest(&point.as_bytes()[1..]);
+    let recovered = normalize(
+        Network::Ethereum,
+        &format!("0x{}", hex::encode(&digest[12..])),
+    )
+    .map_err(|_| SignatureError::Mismatch)?;
+    if recovered != address {
+        return Err(SignatureError::Mismatch);
+    }
+    Ok(())
+}
+
+/// The SNIP-12 (revision 0) typed data a Starknet account is asked to sign
+/// with `signMessage`.
+pub fn starknet_challenge_typed_data(chain_id: &str, nonce: &str) -> serde_json::Value {
+    json!({
+        "types": {
+            "StarkNetDomain": [
+                { "name": "name", "type": "felt" },
+                { "name": "version", "type": "felt" },
+                { "name": "chainId", "type": "felt" }
+            ],
+            "Challenge": [
+                { "name": "statement", "type": "felt" },
+                { "name": "nonce", "type": "felt" }
+            ]
+        },
+        "primaryType": "Challenge",
+        "domain": { "name": DOMAIN_NAME, "version": "1", "chainId": chain_id },
+        "message": { "statement": STATEMENT, "nonce": nonce }
+    })
+}
+
+/// The hash `account` signs for `starknet_challenge_typed_data`.
+pub fn starknet_challenge_hash(
+    chain_id: &str,
+    account: &str,
+    nonce: &str,
+) -> Result<FieldElement, SignatureError> {
+    let account = parse_felt(account).ok_or(SignatureError::Malformed)?;
+    let nonce = parse_felt(nonce).ok_or(SignatureError::Malformed)?;
+
+    let domain = pedersen_array(&[
+        starknet_keccak(DOMAIN_TYPE.as_bytes()),
+        short_string(DOMAIN_NAME),
+        FieldElement::ONE,
+        short_string(chain_id),
+    ]);
+    let challenge = pedersen_array(&[
+        starknet_keccak(CHALLENGE_TYPE.as_bytes()),
+        short_string(STATEMENT),
+        nonce,
+    ]);
+    Ok(pedersen_array(&[
+        short_string("StarkNet Message"),
+        domain,
+        account,
+        challenge,
+    ]))
+}
+
+/// Checks the account owner's part of `signature` is a Stark curve
+/// signature of `hash` by `public_key`. Accepted layouts:
+///
+/// - `[r, s]`, from OpenZeppelin and Braavos accounts;
+/// - `[r, s, guardian_r, guardian_s]`, from Argent accounts with a guardian;
+/// - `[count, 0, signer, r, s, ...]`, Argent's signer list, whose first
+///   entry must be a Stark key matching `public_key`.
+///
+/// Guardian signatures are not checked; owning the key is what is proved.
+pub fn verify_starknet_signature(
+    public_key: &FieldElement,
+    hash: &FieldElement,
+    signature: &[String],
+) -> Result<(), SignatureError> {
+    let felts = signature
+        .iter()
+        .map(|felt| parse_felt(felt))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(SignatureError::Malformed)?;
+    let (r, s) = match felts.as_slice() {
+        [r, s] | [r, s, _, _] => (*r, *s),
+        [count, signer_type, signer, r, s, ..]
+            if *count != FieldElement::ZERO && *signer_type == FieldElement::ZERO =>
+        {
+            if signer != public_key {
+                return Err(SignatureError::Mismatch);
+            }
+            (*r, *s)
+        }
+        [] | [_] => return Err(SignatureError::Malformed),
+        _ => return Err(SignatureError::Unsupported),
+    };
+
+    match starknet_crypto::verify(public_key, hash, &r, &s) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(SignatureError::Mismatch),
+        Err(_) => Err(SignatureError::Malformed),
+    }
+}
+
+/// A felt written in hex with `0x`, or in decimal.
+pub fn parse_felt(value: &str) -> Option<FieldElement> {
+    let value = value.trim();
+    if value.starts_with("0x") || value.starts_with("0X") {
+        FieldElement::from_hex_be(value).ok()
+    } else {
+        FieldElement::from_dec_str(value).ok()
+    }
+}
+
+/// Keccak-256 truncated to 250 bits, as Starknet uses for type hashes and
+/// entry point selectors.
+pub fn starknet_keccak(bytes: &[u8]) -> FieldElement {
+    let mut hash: [u8; 32] = Keccak256::digest(bytes).into();
+    hash[0] &= 0x03;
+    // Below 2^250, so always a felt.
+    FieldElement::from_bytes_be(&hash).unwrap_or(FieldElement::ZERO)
+}
+
+/// A Cairo short string: up to 31 ASCII characters read as a big-endian
+/// number.
+fn short_string(value: &str) -> FieldElement {
+    FieldElement::from_byte_slice_be(value.as_bytes()).unwrap_or(FieldElement::ZERO)
+}
+
+/// Pedersen hash chain over `values` followed by their count.
+fn pedersen_array(values: &[FieldElement]) -> FieldElement {
+    let hash = values
+        .iter()
+        .fold(FieldElement::ZERO, |acc, value| pedersen_hash(&acc, value));
+    pedersen_hash(&hash, &FieldElement::from(values.len() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet_crypto::{get_public_key, rfc6979_generate_k, sign};
+
+    /// `web3.eth.accounts.sign("Some data", key)` from the web3.js docs.
+    const EVM_ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+    const EVM_MESSAGE: &str = "Some data";
+    const EVM_SIGNATURE: &str = "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+
+    fn evm_signature_bytes() -> Vec<u8> {
+        hex::decode(&EVM_SIGNATURE[2..]).unwrap()
+    }
+
+    #[test]
+    fn verifies_personal_sign_vector() {
+        assert_eq!(
+            verify_personal_sign(EVM_ADDRESS, EVM_MESSAGE, EVM_SIGNATURE),
+            Ok(())
+        );
+
+        let mut bytes = evm_signature_bytes();
+        bytes[64] -= 27;
+        let raw_v = hex::encode(&bytes);
+        assert_eq!(
+            verify_personal_sign(EVM_ADDRESS, EVM_MESSAGE, &raw_v),
+            Ok(())
+        );
+
+        assert_eq!(
+            verify_personal_sign(EVM_ADDRESS, "Other data", EVM_SIGNATURE),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_personal_sign(EVM_ADDRESS, EVM_MESSAGE, &EVM_SIGNATURE[..130]),
+            Err(SignatureError::Malformed)
+        );
+    }
+
+    #[test]
+    fn verifies_high_s_personal_sign() {
+        let bytes = evm_signature_bytes();
+        let signature = Signature::from_slice(&bytes[..64]).unwrap();
+        let flipped =
+            Signature::from_scalars(signature.r().to_bytes(), (-*signature.s()).to_bytes())
+                .unwrap();
+        assert!(flipped.normalize_s().is_some());
+
+        let mut high_s = flipped.to_bytes().to_vec();
+        high_s.push(if bytes[64] == 27 { 28 } else { 27 });
+        assert_eq!(
+            verify_personal_sign(EVM_ADDRESS, EVM_MESSAGE, &hex::encode(high_s)),
+            Ok(())
+        );
+    }
+
+    /// The mail example starknet.js tests `typedData.getMessageHash`
+    /// against, encoded with the same steps as the challenge.
+    #[test]
+    fn hashes_starknet_js_typed_data_example() {
+        let person = |name: &str, wallet: &str| {
+            pedersen_array(&[
+                starknet_keccak(b"Person(name:felt,wallet:felt)"),
+                short_string(name),
+                parse_felt(wallet).unwrap(),
+            ])
+        };
+        let domain = pedersen_array(&[
+            starknet_keccak(DOMAIN_TYPE.as_bytes()),
+            short_string("StarkNet Mail"),
+            FieldElement::ONE,
+            FieldElement::ONE,
+        ]);
+        let mail = pedersen_array(&[
+            starknet_keccak(
+                b"Mail(from:Person,to:Person,contents:felt)Person(name:felt,wallet:felt)",
+            ),
+            person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+            person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+            short_string("Hello, Bob!"),
+        ]);
+        let hash = pedersen_array(&[
+            short_string("StarkNet Message"),
+            domain,
+            parse_felt("0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826").unwrap(),
+            mail,
+        ]);
+
+        assert_eq!(
+            starknet_keccak(DOMAIN_TYPE.as_bytes()),
+            parse_felt("0x1bfc207425a47a5dfa1a50a4f5241203f50624ca5fdf5e18755765416b8e288")
+                .unwrap()
+        );
+        assert_eq!(
+            hash,
+            parse_felt("0x6fcff244f63e38b9d88b9e3378d44757710d1b244282b435cb472053c8d78d0")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn computes_entry_point_selector() {
+        assert_eq!(
+            starknet_keccak(b"get_public_key"),
+            parse_felt("0x1a35984e05126dbecb7c3bb9929e7dd9106d460c59b1633739a5c733a5fb13b")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn finds_owner_signature_in_account_layouts() {
+        let felt = |value: &FieldElement| format!("{:#x}", value);
+        let private_key = parse_felt("0x1234567890abcdef").unwrap();
+        let public_key = get_public_key(&private_key);
+        let hash = starknet_challenge_hash(
+            "SN_MAIN",
+            "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+            "0x2a",
+        )
+        .unwrap();
+        let k = rfc6979_generate_k(&hash, &private_key, None);
+        let signature = sign(&private_key, &hash, &k).unwrap();
+        let (r, s) = (felt(&signature.r), felt(&signature.s));
+        let (count, starknet_signer) = (felt(&FieldElement::ONE), felt(&FieldElement::ZERO));
+
+        for layout in [
+            vec![r.clone(), s.clone()],
+            vec![r.clone(), s.clone(), "0x1".into(), "0x2".into()],
+            vec![
+                count.clone(),
+                starknet_signer.clone(),
+                felt(&public_key),
+                r.clone(),
+                s.clone(),
+            ],
+        ] {
+            assert_eq!(
+                verify_starknet_signature(&public_key, &hash, &layout),
+                Ok(())
+            );
+        }
+
+        let other_key = get_public_key(&FieldElement::TWO);
+        assert_eq!(
+            verify_starknet_signature(&other_key, &hash, &[r.clone(), s.clone()]),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_starknet_signature(
+                &public_key,
+                &hash,
+                &[
+                    count,
+                    starknet_signer,
+                    felt(&other_key),
+                    r.clone(),
+                    s.clone()
+                ]
+            ),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_starknet_signature(&public_key, &hash, &[r.clone(), s.clone(), "0x1".into()]),
+            Err(SignatureError::Unsupported)
+        );
+        assert_eq!(
+            verify_starknet_signature(&public_key, &hash, &[r, "not a felt".into()]),
+            Err(SignatureError::Malformed)
+        );
+    }
+}
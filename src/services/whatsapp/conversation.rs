@@ -0,0 +1,159 @@
+use crate::models::models::Bank;
+use serde::{Deserialize, Serialize};
+
+/// Where a WhatsApp user is in the conversation, stored per phone number in
+/// `whatsapp_conversations.state`.
+///
+/// A new number gets an account and a login code, and must send the code
+/// back before anything else. After that the menu offers adding a bank,
+/// which looks the account up with Flutterwave, asks the user to confirm
+/// the name and then for a `bank_add` code, and listing the banks already
+/// added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum ConversationState {
+    AwaitingOtp,
+    Menu,
+    AwaitingBankDetails,
+    AwaitingBankConfirmation {
+        bank_name: String,
+        bank_code: String,
+        account_number: String,
+        account_name: String,
+    },
+    AwaitingBankOtp {
+        bank_name: String,
+        bank_code: String,
+        account_number: String,
+        account_name: String,
+    },
+}
+
+/// A reply that means the same in every state; anything else is read as
+/// input for the current step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    AddBank,
+    ListBanks,
+    /// Back to the menu, abandoning the current step.
+    Menu,
+    /// Send a new code.
+    Resend,
+    Yes,
+    No,
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "1" | "add bank" => Some(Command::AddBank),
+            "2" | "banks" | "my banks" => Some(Command::ListBanks),
+            "menu" | "cancel" | "hi" | "hello" => Some(Command::Menu),
+            "resend" => Some(Command::Resend),
+            "yes" | "y" => Some(Command::Yes),
+            "no" | "n" => Some(Command::No),
+            _ => None,
+        }
+    }
+}
+
+/// Splits "0123456789 Access Bank" (or "Access Bank 0123456789") into the
+/// ten-digit account number and the bank name.
+pub fn parse_bank_details(text: &str) -> Option<(String, String)> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let is_account_number =
+        |word: &str| word.len() == 10 && word.bytes().all(|b| b.is_ascii_digit());
+
+    let (account_number, bank_words) = match words.as_slice() {
+        [first, rest @ ..] if is_account_number(first) => (*first, rest),
+        [rest @ .., last] if is_account_number(last) => (*last, rest),
+        _ => return None,
+    };
+    if bank_words.is_empty() {
+        return None;
+    }
+
+    Some((account_number.to_string(), bank_words.join(" ")))
+}
+
+/// The bank the user meant: an exact match ignoring case, else the only bank
+/// whose name contains what they typed.
+pub fn find_bank<'a>(banks: &'a [Bank], name: &str) -> Option<&'a Bank> {
+    let name = name.trim().to_lowercase();
+    if let Some(bank) = banks.iter().find(|bank| bank.name.to_lowercase() == name) {
+        return Some(bank);
+    }
+
+    let mut matches = banks
+        .iter()
+        .filter(|bank| bank.name.to_lowercase().contains(&name));
+    match (matches.next(), matches.next()) {
+        (Some(bank), None) => Some(bank),
+        _ => None,
+    }
+}
+
+pub const WELCOME: &str = "Welcome to Kharon! We've sent you a 6-digit code. Reply with the code to verify your account, or RESEND for a new one.";
+pub const ENTER_OTP: &str = "Reply with the 6-digit code we sent you, or RESEND for a new one.";
+pub const OTP_RESENT: &str = "We've sent you a new code. Reply with it to continue.";
+pub const OTP_TOO_SOON: &str =
+    "A code was sent a moment ago. Please wait a minute before asking for another.";
+pub const OTP_INVALID: &str =
+    "That code is wrong or has expired. Try again, or reply RESEND for a new one.";
+pub const OTP_EXHAUSTED: &str = "Too many wrong codes. Reply RESEND for a new one.";
+pub const LOCKED: &str =
+    "Your account is temporarily locked after too many failed attempts. Please try again later.";
+pub const VERIFIED: &str = "Your number is verified.";
+pub const MENU: &str = "What would you like to do?\n1. Add a bank account\n2. See your bank accounts\n\nReply with a number, or MENU at any time to come back here.";
+pub const ENTER_BANK_DETAILS: &str = "Send your 10-digit account number and bank name, e.g. 0123456789 Access Bank. Reply CANCEL to go back.";
+pub const BANK_DETAILS_INVALID: &str = "We couldn't read that. Send the 10-digit account number then the bank name, e.g. 0123456789 Access Bank.";
+pub const BANK_UNAVAILABLE: &str =
+    "Adding banks isn't available right now. Please try again later.";
+pub const BANK_NEEDS_APP: &str =
+    "Your account has two-factor authentication on, so please add banks in the Kharon app.";
+pub const BANK_CANCELLED: &str = "Okay, we haven't added that account.";
+pub const BANK_OTP_SENT: &str = "We've sent you a 6-digit code to confirm adding this account. Reply with it, RESEND for a new one, or CANCEL to go back.";
+pub const ENTER_BANK_OTP: &str =
+    "Reply with the 6-digit code to add this account, RESEND for a new one, or CANCEL to go back.";
+pub const CONFIRM_YES_NO: &str = "Please reply YES to add this account or NO to cancel.";
+pub const NO_BANKS: &str = "You haven't added any bank accounts yet. Reply 1 to add one.";
+pub const UNSUPPORTED: &str = "Sorry, we can only read text messages.";
+pub const FAILED: &str = "Something went wrong on our side. Please try again in a few minutes.";
+
+pub fn bank_not_found(name: &str) -> String {
+    format!(
+        "We couldn't find a bank called \"{}\". Check the name and try again, e.g. 0123456789 Access Bank.",
+        name
+    )
+}
+
+pub fn account_not_found(account_number: &str, bank_name: &str) -> String {
+    format!(
+        "We couldn't find account {} at {}. Check the details and try again.",
+        account_number, bank_name
+    )
+}
+
+pub fn confirm_account(account_name: &str, account_number: &str, bank_name: &str) -> String {
+    format!(
+        "Is this your account?\n{}\n{} - {}\n\nReply YES to add it or NO to cancel.",
+        account_name, account_number, bank_name
+    )
+}
+
+pub fn bank_added(bank_name: &str, account_number: &str) -> String {
+    format!(
+        "Done! {} account {} has been added.\n\n{}",
+        bank_name, account_number, MENU
+    )
+}
+
+pub fn bank_list<'a>(banks: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    let lines: Vec<String> = banks
+        .enumerate()
+        .map(|(i, (bank_name, account_number))| {
+            format!("{}. {} - {}", i + 1, bank_name, account_number)
+        })
+        .collect();
+    format!("Your bank accounts:\n{}", lines.join("\n"))
+}
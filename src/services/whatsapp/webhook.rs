@@ -0,0 +1,98 @@
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Checks `X-Hub-Signature-256`, which Meta sets to `sha256=<hex>`, the
+/// HMAC-SHA256 of the raw body under the app secret. The comparison is
+/// constant time.
+pub fn verify_signature(app_secret: &str, signature: Option<&str>, body: &[u8]) -> bool {
+    let Some(signature) = signature.and_then(|value| value.strip_prefix("sha256=")) else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(app_secret.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// The parts of a WhatsApp Cloud API webhook we act on. Delivery statuses
+/// and other fields are ignored.
+#[derive(Debug, Deserialize)]
+pub struct WebhookPayload {
+    #[serde(default)]
+    entry: Vec<WebhookEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookEntry {
+    #[serde(default)]
+    changes: Vec<WebhookChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookChange {
+    value: WebhookValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookValue {
+    #[serde(default)]
+    messages: Vec<InboundMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboundMessage {
+    from: String,
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<InboundText>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboundText {
+    body: String,
+}
+
+/// A text message from a user.
+#[derive(Debug, Clone)]
+pub struct TextMessage {
+    /// The sender's number, digits only.
+    pub from: String,
+    pub id: String,
+    pub body: String,
+}
+
+/// A message other than text, which the conversation cannot read.
+#[derive(Debug, Clone)]
+pub struct UnsupportedMessage {
+    pub from: String,
+    pub id: String,
+}
+
+impl WebhookPayload {
+    /// Inbound messages in the order Meta sent them; text as `Ok`, anything
+    /// else (images, voice notes, ...) as `Err`.
+    pub fn messages(self) -> Vec<Result<TextMessage, UnsupportedMessage>> {
+        self.entry
+            .into_iter()
+            .flat_map(|entry| entry.changes)
+            .flat_map(|change| change.value.messages)
+            .map(|message| match (message.kind.as_str(), message.text) {
+                ("text", Some(text)) => Ok(TextMessage {
+                    from: message.from,
+                    id: message.id,
+                    body: text.body,
+                }),
+                _ => Err(UnsupportedMessage {
+                    from: message.from,
+                    id: message.id,
+                }),
+            })
+            .collect()
+    }
+}